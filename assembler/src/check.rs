@@ -0,0 +1,119 @@
+use crate::program::Program;
+
+/// A ROM address where the assembled program and the expected words differ.
+/// Either side is `None` when one of the programs is shorter.
+#[derive(Debug, PartialEq)]
+pub struct Mismatch {
+    pub address: usize,
+    pub expected: Option<u16>,
+    pub found: Option<u16>,
+    pub line: Option<usize>,
+}
+
+/// Parses the contents of a `.hack` file, one 16-digit binary word per line.
+pub fn parse_hack(input: &str) -> Result<Vec<u16>, String> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let line = line.trim();
+            match u16::from_str_radix(line, 2) {
+                Ok(word) if line.len() == 16 => Ok(word),
+                _ => Err(format!("[line {}] invalid word `{}`", i + 1, line)),
+            }
+        })
+        .collect()
+}
+
+/// Compares the program word by word against the expected ROM.
+pub fn check(program: &Program, expected: &[u16]) -> Vec<Mismatch> {
+    let len = program.words.len().max(expected.len());
+    (0..len)
+        .filter_map(|address| {
+            let expected = expected.get(address).copied();
+            let found = program.words.get(address).copied();
+            if expected == found {
+                return None;
+            }
+            let line = program.lines.get(address).copied();
+            Some(Mismatch {
+                address,
+                expected,
+                found,
+                line,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    fn assemble(path: &str) -> Program {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("..").join(path);
+        Program::assemble(&fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    fn hack(path: &str) -> Vec<u16> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("..").join(path);
+        parse_hack(&fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_symbol_less_versions_match() {
+        for (program, symbol_less) in [
+            ("06/max/Max.asm", "06/max/MaxL.asm"),
+            ("06/rect/Rect.asm", "06/rect/RectL.asm"),
+            ("06/pong/Pong.asm", "06/pong/PongL.asm"),
+        ] {
+            let program = assemble(program);
+            let symbol_less = assemble(symbol_less);
+            assert_eq!(check(&program, &symbol_less.words), vec![]);
+        }
+    }
+
+    #[test]
+    fn test_expected_hack_files_match() {
+        for (program, expected) in [
+            ("06/add/Add.asm", "05/Add.hack"),
+            ("06/max/Max.asm", "05/Max.hack"),
+            ("06/rect/Rect.asm", "05/Rect.hack"),
+        ] {
+            assert_eq!(check(&assemble(program), &hack(expected)), vec![]);
+        }
+    }
+
+    #[test]
+    fn test_mismatches() {
+        let program = Program::assemble("@1\nD=A\n\n@2\n").unwrap();
+        let mismatches = check(&program, &[1, 0b1110110000010000, 3, 4]);
+        assert_eq!(
+            mismatches,
+            vec![
+                Mismatch {
+                    address: 2,
+                    expected: Some(3),
+                    found: Some(2),
+                    line: Some(4),
+                },
+                Mismatch {
+                    address: 3,
+                    expected: Some(4),
+                    found: None,
+                    line: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_hack() {
+        assert_eq!(parse_hack("0000000000000010\n\n"), Ok(vec![2]));
+        assert!(parse_hack("0000000000000010\n01\n").is_err());
+        assert!(parse_hack("000000000000002\n").is_err());
+    }
+}
@@ -3,6 +3,33 @@ use crate::instruction::{Computation, Destination, Instruction, Jump};
 use crate::token::Kind;
 use std::collections::HashMap;
 
+/// Symbols every Hack program can use without defining them.
+pub const PREDEFINED_SYMBOLS: [(&str, u16); 23] = [
+    ("R0", 0),
+    ("R1", 1),
+    ("R2", 2),
+    ("R3", 3),
+    ("R4", 4),
+    ("R5", 5),
+    ("R6", 6),
+    ("R7", 7),
+    ("R8", 8),
+    ("R9", 9),
+    ("R10", 10),
+    ("R11", 11),
+    ("R12", 12),
+    ("R13", 13),
+    ("R14", 14),
+    ("R15", 15),
+    ("SCREEN", 16384),
+    ("KBD", 24576),
+    ("SP", 0),
+    ("LCL", 1),
+    ("ARG", 2),
+    ("THIS", 3),
+    ("THAT", 4),
+];
+
 pub struct Generator<'s> {
     current: usize,
     next_variable: u16,
@@ -12,31 +39,7 @@ pub struct Generator<'s> {
 
 impl<'s> Generator<'s> {
     pub fn new(instructions: &'s [Instruction<'s>]) -> Self {
-        let symbols = HashMap::from([
-            ("R0", 0),
-            ("R1", 1),
-            ("R2", 2),
-            ("R3", 3),
-            ("R4", 4),
-            ("R5", 5),
-            ("R6", 6),
-            ("R7", 7),
-            ("R8", 8),
-            ("R9", 9),
-            ("R10", 10),
-            ("R11", 11),
-            ("R12", 12),
-            ("R13", 13),
-            ("R14", 14),
-            ("R15", 15),
-            ("SCREEN", 16384),
-            ("KBD", 24576),
-            ("SP", 0),
-            ("LCL", 1),
-            ("ARG", 2),
-            ("THIS", 3),
-            ("THAT", 4),
-        ]);
+        let symbols = HashMap::from(PREDEFINED_SYMBOLS);
         Self {
             instructions,
             symbols,
@@ -45,7 +48,7 @@ impl<'s> Generator<'s> {
         }
     }
 
-    pub fn register_labels(&mut self) -> Result<()> {
+    pub fn register_labels(&mut self) -> Result<'s, ()> {
         let mut pending = vec![];
         let mut i = 0;
         for instruction in self.instructions {
@@ -62,17 +65,29 @@ impl<'s> Generator<'s> {
                 }
             }
         }
+        // Labels at the end of the program point past its last instruction.
+        for label in pending {
+            self.symbols.insert(label, i);
+        }
         Ok(())
     }
 
-    fn translate(&mut self, instruction: &'s Instruction) -> Result<u16> {
+    /// Every symbol resolved so far: predefined ones, labels once they are
+    /// registered, and variables as their instructions get translated.
+    pub fn symbols(&self) -> &HashMap<&'s str, u16> {
+        &self.symbols
+    }
+
+    fn translate(&mut self, instruction: &'s Instruction) -> Result<'s, u16> {
         match instruction {
             Instruction::A(token) => match token.kind {
                 Kind::Number(n) => Ok(translate_a_number(n)),
                 Kind::Identifier(label) => Ok(self.translate_a_label(label)),
                 _ => Err(Error::code(token, "expect number or identifier")),
             },
-            Instruction::C { dest, comp, jump } => Ok(translate_c(*dest, *comp, *jump)),
+            Instruction::C {
+                dest, comp, jump, ..
+            } => Ok(translate_c(*dest, *comp, *jump)),
             Instruction::Label(token) => Err(Error::code(token, "can't translate a label")),
         }
     }
@@ -97,10 +112,14 @@ fn translate_a_number(n: u16) -> u16 {
 
 fn translate_c(dest: Destination, comp: Computation, jump: Jump) -> u16 {
     let n = translate_jump(jump) | translate_comp(comp) | translate_dest(dest);
-    n | 0b1110000000000000
+    if comp.is_shift() {
+        n | 0b1010000000000000
+    } else {
+        n | 0b1110000000000000
+    }
 }
 
-fn translate_jump(jump: Jump) -> u16 {
+pub(crate) fn translate_jump(jump: Jump) -> u16 {
     use Jump::*;
     match jump {
         Null => 0b000,
@@ -114,7 +133,7 @@ fn translate_jump(jump: Jump) -> u16 {
     }
 }
 
-fn translate_comp(comp: Computation) -> u16 {
+pub(crate) fn translate_comp(comp: Computation) -> u16 {
     use Computation::*;
     let n = match comp {
         Zero => 0b0101010,
@@ -145,11 +164,39 @@ fn translate_comp(comp: Computation) -> u16 {
         DAndM => 0b1000000,
         DOrA => 0b0010101,
         DOrM => 0b1010101,
+        #[cfg(feature = "extended-isa")]
+        DShiftLeft => 0b0110000,
+        #[cfg(feature = "extended-isa")]
+        AShiftLeft => 0b0100000,
+        #[cfg(feature = "extended-isa")]
+        MShiftLeft => 0b1100000,
+        #[cfg(feature = "extended-isa")]
+        DShiftRight => 0b0010000,
+        #[cfg(feature = "extended-isa")]
+        AShiftRight => 0b0000000,
+        #[cfg(feature = "extended-isa")]
+        MShiftRight => 0b1000000,
+        #[cfg(feature = "extended-isa")]
+        ZeroWithM => 0b1101010,
+        #[cfg(feature = "extended-isa")]
+        OneWithM => 0b1111111,
+        #[cfg(feature = "extended-isa")]
+        NegativeOneWithM => 0b1111010,
+        #[cfg(feature = "extended-isa")]
+        DWithM => 0b1001100,
+        #[cfg(feature = "extended-isa")]
+        NotDWithM => 0b1001101,
+        #[cfg(feature = "extended-isa")]
+        NegativeDWithM => 0b1001111,
+        #[cfg(feature = "extended-isa")]
+        DPlusOneWithM => 0b1011111,
+        #[cfg(feature = "extended-isa")]
+        DMinusOneWithM => 0b1001110,
     };
     n << 6
 }
 
-fn translate_dest(dest: Destination) -> u16 {
+pub(crate) fn translate_dest(dest: Destination) -> u16 {
     use Destination::*;
     let n = match dest {
         Null => 0b000,
@@ -0,0 +1,65 @@
+use crate::code::{translate_comp, translate_dest, translate_jump};
+use crate::instruction::{Computation, Destination, Jump};
+
+/// Turns a machine word back into assembly, e.g. `0b1110001100000001` into
+/// `D;JGT`. Returns `None` if the word isn't a valid instruction.
+pub fn disassemble(word: u16) -> Option<String> {
+    if word & 0b1000000000000000 == 0 {
+        return Some(format!("@{}", word));
+    }
+    let shift = match word >> 13 {
+        0b111 => false,
+        0b101 => true,
+        _ => return None,
+    };
+    let comp = *Computation::ALL
+        .iter()
+        .find(|c| c.is_shift() == shift && translate_comp(**c) == word & 0b0001111111000000)?;
+    let dest = Destination::ALL
+        .into_iter()
+        .find(|d| translate_dest(*d) == word & 0b111000)?;
+    let jump = Jump::ALL
+        .into_iter()
+        .find(|j| translate_jump(*j) == word & 0b111)?;
+    let mut text = String::new();
+    if !matches!(dest, Destination::Null) {
+        text += &format!("{}=", dest);
+    }
+    text += &comp.to_string();
+    if !matches!(jump, Jump::Null) {
+        text += &format!(";{}", jump);
+    }
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disassemble() {
+        assert_eq!(disassemble(0b0000000000010101).unwrap(), "@21");
+        assert_eq!(disassemble(0b1110110000010000).unwrap(), "D=A");
+        assert_eq!(disassemble(0b1110001100000001).unwrap(), "D;JGT");
+        assert_eq!(disassemble(0b1111110111101000).unwrap(), "AM=M+1");
+        assert_eq!(disassemble(0b1110101010000111).unwrap(), "0;JMP");
+        assert!(disassemble(0b1100110000010000).is_none());
+    }
+
+    #[cfg(feature = "extended-isa")]
+    #[test]
+    fn test_disassemble_extended_isa() {
+        assert_eq!(disassemble(0b1010110000010000).unwrap(), "D=D<<");
+        assert_eq!(disassemble(0b1011000000101000).unwrap(), "AM=M>>");
+        assert_eq!(disassemble(0b1111111111010000).unwrap(), "D=1(M)");
+        assert_eq!(disassemble(0b1111001100000001).unwrap(), "D(M);JGT");
+        assert!(disassemble(0b1010000001010000).is_none());
+    }
+
+    #[cfg(not(feature = "extended-isa"))]
+    #[test]
+    fn test_disassemble_extended_isa() {
+        assert!(disassemble(0b1010110000010000).is_none());
+        assert!(disassemble(0b1111111111010000).is_none());
+    }
+}
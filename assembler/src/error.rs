@@ -7,6 +7,7 @@ pub type Result<'s, T> = std::result::Result<T, Error<'s>>;
 pub enum Error<'s> {
     Scan {
         line: usize,
+        column: usize,
         lexeme: &'s str,
         message: &'s str,
     },
@@ -22,9 +23,10 @@ pub enum Error<'s> {
 }
 
 impl<'s> Error<'s> {
-    pub fn scan(line: usize, lexeme: &'s str, message: &'s str) -> Self {
+    pub fn scan(line: usize, column: usize, lexeme: &'s str, message: &'s str) -> Self {
         Self::Scan {
             line,
+            column,
             lexeme,
             message,
         }
@@ -46,6 +48,7 @@ impl fmt::Display for Error<'_> {
                 line,
                 lexeme,
                 message,
+                ..
             } => write!(f, "[line {}] scan error at `{}`: {}", line, lexeme, message),
             Self::Parse { token, message } => write!(
                 f,
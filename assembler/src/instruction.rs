@@ -1,10 +1,12 @@
 use crate::token::Token;
+use std::fmt;
 use std::str::FromStr;
 
 #[derive(Debug)]
 pub enum Instruction<'s> {
     A(&'s Token<'s>),
     C {
+        token: &'s Token<'s>,
         comp: Computation,
         dest: Destination,
         jump: Jump,
@@ -12,6 +14,15 @@ pub enum Instruction<'s> {
     Label(&'s Token<'s>),
 }
 
+impl<'s> Instruction<'s> {
+    /// The first token of the instruction.
+    pub fn token(&self) -> &'s Token<'s> {
+        match self {
+            Self::A(token) | Self::C { token, .. } | Self::Label(token) => token,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub enum Computation {
     Zero,
@@ -42,6 +53,199 @@ pub enum Computation {
     DAndM,
     DOrA,
     DOrM,
+    #[cfg(feature = "extended-isa")]
+    DShiftLeft,
+    #[cfg(feature = "extended-isa")]
+    AShiftLeft,
+    #[cfg(feature = "extended-isa")]
+    MShiftLeft,
+    #[cfg(feature = "extended-isa")]
+    DShiftRight,
+    #[cfg(feature = "extended-isa")]
+    AShiftRight,
+    #[cfg(feature = "extended-isa")]
+    MShiftRight,
+    // The computations that don't read A or M, encoded with the M bit set.
+    #[cfg(feature = "extended-isa")]
+    ZeroWithM,
+    #[cfg(feature = "extended-isa")]
+    OneWithM,
+    #[cfg(feature = "extended-isa")]
+    NegativeOneWithM,
+    #[cfg(feature = "extended-isa")]
+    DWithM,
+    #[cfg(feature = "extended-isa")]
+    NotDWithM,
+    #[cfg(feature = "extended-isa")]
+    NegativeDWithM,
+    #[cfg(feature = "extended-isa")]
+    DPlusOneWithM,
+    #[cfg(feature = "extended-isa")]
+    DMinusOneWithM,
+}
+
+impl Computation {
+    pub const ALL: &'static [Self] = {
+        use Computation::*;
+        &[
+            Zero,
+            One,
+            NegativeOne,
+            D,
+            A,
+            M,
+            NotD,
+            NotA,
+            NotM,
+            NegativeD,
+            NegativeA,
+            NegativeM,
+            DPlusOne,
+            APlusOne,
+            MPlusOne,
+            DMinusOne,
+            AMinusOne,
+            MMinusOne,
+            DPlusA,
+            DPlusM,
+            DMinusA,
+            DMinusM,
+            AMinusD,
+            MMinusD,
+            DAndA,
+            DAndM,
+            DOrA,
+            DOrM,
+            #[cfg(feature = "extended-isa")]
+            DShiftLeft,
+            #[cfg(feature = "extended-isa")]
+            AShiftLeft,
+            #[cfg(feature = "extended-isa")]
+            MShiftLeft,
+            #[cfg(feature = "extended-isa")]
+            DShiftRight,
+            #[cfg(feature = "extended-isa")]
+            AShiftRight,
+            #[cfg(feature = "extended-isa")]
+            MShiftRight,
+            #[cfg(feature = "extended-isa")]
+            ZeroWithM,
+            #[cfg(feature = "extended-isa")]
+            OneWithM,
+            #[cfg(feature = "extended-isa")]
+            NegativeOneWithM,
+            #[cfg(feature = "extended-isa")]
+            DWithM,
+            #[cfg(feature = "extended-isa")]
+            NotDWithM,
+            #[cfg(feature = "extended-isa")]
+            NegativeDWithM,
+            #[cfg(feature = "extended-isa")]
+            DPlusOneWithM,
+            #[cfg(feature = "extended-isa")]
+            DMinusOneWithM,
+        ]
+    };
+
+    /// Whether the computation is one of the shift operations of the
+    /// extended instruction set, which are encoded with a `101` prefix
+    /// instead of `111`.
+    pub fn is_shift(self) -> bool {
+        #[cfg(feature = "extended-isa")]
+        {
+            use Computation::*;
+            matches!(
+                self,
+                DShiftLeft | AShiftLeft | MShiftLeft | DShiftRight | AShiftRight | MShiftRight
+            )
+        }
+        #[cfg(not(feature = "extended-isa"))]
+        false
+    }
+
+    /// The same computation encoded with the M bit set, written `D(M)` and
+    /// the like, for those that don't read A or M.
+    #[cfg(feature = "extended-isa")]
+    pub fn with_m_bit(self) -> Option<Self> {
+        use Computation::*;
+        let comp = match self {
+            Zero => ZeroWithM,
+            One => OneWithM,
+            NegativeOne => NegativeOneWithM,
+            D => DWithM,
+            NotD => NotDWithM,
+            NegativeD => NegativeDWithM,
+            DPlusOne => DPlusOneWithM,
+            DMinusOne => DMinusOneWithM,
+            _ => return None,
+        };
+        Some(comp)
+    }
+}
+
+impl fmt::Display for Computation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Computation::*;
+        let s = match self {
+            Zero => "0",
+            One => "1",
+            NegativeOne => "-1",
+            D => "D",
+            A => "A",
+            M => "M",
+            NotD => "!D",
+            NotA => "!A",
+            NotM => "!M",
+            NegativeD => "-D",
+            NegativeA => "-A",
+            NegativeM => "-M",
+            DPlusOne => "D+1",
+            APlusOne => "A+1",
+            MPlusOne => "M+1",
+            DMinusOne => "D-1",
+            AMinusOne => "A-1",
+            MMinusOne => "M-1",
+            DPlusA => "D+A",
+            DPlusM => "D+M",
+            DMinusA => "D-A",
+            DMinusM => "D-M",
+            AMinusD => "A-D",
+            MMinusD => "M-D",
+            DAndA => "D&A",
+            DAndM => "D&M",
+            DOrA => "D|A",
+            DOrM => "D|M",
+            #[cfg(feature = "extended-isa")]
+            DShiftLeft => "D<<",
+            #[cfg(feature = "extended-isa")]
+            AShiftLeft => "A<<",
+            #[cfg(feature = "extended-isa")]
+            MShiftLeft => "M<<",
+            #[cfg(feature = "extended-isa")]
+            DShiftRight => "D>>",
+            #[cfg(feature = "extended-isa")]
+            AShiftRight => "A>>",
+            #[cfg(feature = "extended-isa")]
+            MShiftRight => "M>>",
+            #[cfg(feature = "extended-isa")]
+            ZeroWithM => "0(M)",
+            #[cfg(feature = "extended-isa")]
+            OneWithM => "1(M)",
+            #[cfg(feature = "extended-isa")]
+            NegativeOneWithM => "-1(M)",
+            #[cfg(feature = "extended-isa")]
+            DWithM => "D(M)",
+            #[cfg(feature = "extended-isa")]
+            NotDWithM => "!D(M)",
+            #[cfg(feature = "extended-isa")]
+            NegativeDWithM => "-D(M)",
+            #[cfg(feature = "extended-isa")]
+            DPlusOneWithM => "D+1(M)",
+            #[cfg(feature = "extended-isa")]
+            DMinusOneWithM => "D-1(M)",
+        };
+        f.write_str(s)
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -56,6 +260,30 @@ pub enum Destination {
     Amd,
 }
 
+impl Destination {
+    pub const ALL: [Self; 8] = {
+        use Destination::*;
+        [Null, M, D, Md, A, Am, Ad, Amd]
+    };
+}
+
+impl fmt::Display for Destination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Destination::*;
+        let s = match self {
+            Null => "",
+            M => "M",
+            D => "D",
+            Md => "MD",
+            A => "A",
+            Am => "AM",
+            Ad => "AD",
+            Amd => "AMD",
+        };
+        f.write_str(s)
+    }
+}
+
 pub struct DestinationParseError;
 
 impl FromStr for Destination {
@@ -89,6 +317,38 @@ pub enum Jump {
     NotEqual,
 }
 
+impl Jump {
+    pub const ALL: [Self; 8] = {
+        use Jump::*;
+        [
+            Null,
+            Unconditional,
+            Greater,
+            GreaterEqual,
+            Less,
+            LessEqual,
+            Equal,
+            NotEqual,
+        ]
+    };
+}
+
+impl fmt::Display for Jump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Jump::Null => "",
+            Jump::Unconditional => "JMP",
+            Jump::Greater => "JGT",
+            Jump::GreaterEqual => "JGE",
+            Jump::Less => "JLT",
+            Jump::LessEqual => "JLE",
+            Jump::Equal => "JEQ",
+            Jump::NotEqual => "JNE",
+        };
+        f.write_str(s)
+    }
+}
+
 pub struct JumpParseError;
 
 impl FromStr for Jump {
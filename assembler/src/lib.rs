@@ -0,0 +1,9 @@
+pub mod check;
+pub mod code;
+pub mod disassembler;
+pub mod error;
+pub mod instruction;
+pub mod parser;
+pub mod program;
+pub mod scanner;
+pub mod token;
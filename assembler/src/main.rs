@@ -1,33 +1,39 @@
-mod code;
-mod error;
-mod instruction;
-mod parser;
-mod scanner;
-mod token;
-
-use code::Generator;
-use error::Error;
-use parser::Parser;
-use scanner::Scanner;
+use assembler::check::{check, parse_hack, Mismatch};
+use assembler::code::Generator;
+use assembler::disassembler::disassemble;
+use assembler::parser::Parser;
+use assembler::program::Program;
+use assembler::scanner::Scanner;
+use cli::{is_std_stream, read_input, take_output_arg, write_output};
 use std::env::args;
-use std::fmt::Write;
-use std::path::Path;
+use std::fmt::{self, Write as _};
+use std::path::{Path, PathBuf};
 use std::{fs, process};
 
 type Result = std::result::Result<(), Box<dyn std::error::Error + Send + Sync + 'static>>;
 
+const USAGE: &str = "Usage: assembler [-o output] [file or - for stdin]
+       assembler --check [expected.hack] [file or - for stdin]";
+
 fn main() -> Result {
-    if args().count() != 2 {
-        println!("Usage: assembler [file]");
-    } else if let Some(arg) = args().nth(1) {
-        assemble_file(arg)?;
+    let mut args: Vec<String> = args().skip(1).collect();
+    let out_path = take_output_arg(&mut args, USAGE);
+    match args.as_slice() {
+        [flag, expected, path] if flag == "--check" && out_path.is_none() => {
+            check_file(expected, path)?
+        }
+        [path] => assemble_file(path, out_path)?,
+        _ => println!("{}", USAGE),
     }
     Ok(())
 }
 
-fn assemble_file<P: AsRef<Path>>(path: P) -> Result {
-    let out_path = path.as_ref().with_extension("hack");
-    let source = fs::read_to_string(path)?;
+fn assemble_file<P: AsRef<Path>>(path: P, out_path: Option<PathBuf>) -> Result {
+    let out_path = out_path.unwrap_or_else(|| match path.as_ref() {
+        p if is_std_stream(p) => p.to_path_buf(),
+        p => p.with_extension("hack"),
+    });
+    let source = read_input(path.as_ref())?;
     let mut scanner = Scanner::new(&source);
     let tokens = scanner.scan_tokens().unwrap_or_else(exit_with_error);
     let mut parser = Parser::new(tokens);
@@ -38,11 +44,54 @@ fn assemble_file<P: AsRef<Path>>(path: P) -> Result {
     for line in generator {
         writeln!(output, "{:0>16b}", line)?;
     }
-    fs::write(out_path, output)?;
+    write_output(&out_path, &output)?;
     Ok(())
 }
 
-fn exit_with_error<V>(e: Error) -> V {
+fn check_file<P: AsRef<Path>>(expected_path: P, path: P) -> Result {
+    let expected = parse_hack(&fs::read_to_string(expected_path)?).unwrap_or_else(exit_with_error);
+    let source = read_input(path.as_ref())?;
+    let program = Program::assemble(&source).unwrap_or_else(exit_with_error);
+    let mismatches = check(&program, &expected);
+    if mismatches.is_empty() {
+        eprintln!("All {} words match", expected.len());
+        return Ok(());
+    }
+    let source_lines: Vec<&str> = source.lines().collect();
+    for mismatch in &mismatches {
+        let Mismatch {
+            address,
+            expected,
+            found,
+            line,
+        } = mismatch;
+        let source_line = match line {
+            Some(n) => format!(" (line {}: `{}`)", n, source_lines[n - 1].trim()),
+            None => String::new(),
+        };
+        println!(
+            "ROM[{}]{}: expected {}, found {}",
+            address,
+            source_line,
+            describe(*expected),
+            describe(*found)
+        );
+    }
+    eprintln!("{} of {} words differ", mismatches.len(), expected.len());
+    process::exit(1)
+}
+
+fn describe(word: Option<u16>) -> String {
+    match word {
+        Some(word) => {
+            let text = disassemble(word).unwrap_or_else(|| "invalid instruction".to_string());
+            format!("{:0>16b} `{}`", word, text)
+        }
+        None => "nothing".to_string(),
+    }
+}
+
+fn exit_with_error<V, E: fmt::Display>(e: E) -> V {
     eprintln!("{}", e);
     process::exit(65)
 }
@@ -13,7 +13,7 @@ impl<'s> Parser<'s> {
         Self { tokens, current: 0 }
     }
 
-    pub fn parse(&mut self) -> Result<Vec<Instruction>> {
+    pub fn parse(&mut self) -> Result<'s, Vec<Instruction<'s>>> {
         let mut instructions = vec![];
         loop {
             while matches!(self.peek().kind, LineBreak) {
@@ -62,10 +62,16 @@ impl<'s> Parser<'s> {
     }
 
     fn c_instruction(&mut self) -> Result<'s, Instruction<'s>> {
+        let token = self.peek();
         let dest = self.destination()?;
         let comp = self.computation()?;
         let jump = self.jump()?;
-        let instruction = Instruction::C { dest, comp, jump };
+        let instruction = Instruction::C {
+            token,
+            dest,
+            comp,
+            jump,
+        };
         self.end_of_instruction()?;
         Ok(instruction)
     }
@@ -115,13 +121,16 @@ impl<'s> Parser<'s> {
             }
             Identifier(ident @ ("A" | "D" | "M")) => {
                 let token = self.peek();
-                if matches!(token.kind, LineBreak | Eof | Semicolon) {
+                if matches!(token.kind, LineBreak | Eof | Semicolon | LeftParen) {
                     match ident {
                         "A" => A,
                         "D" => D,
                         "M" => M,
                         _ => unreachable!("ident can only be A, D, or M"),
                     }
+                } else if matches!(token.kind, ShiftLeft | ShiftRight) {
+                    self.advance(); // The shift operator
+                    shift(ident, token)?
                 } else if let Some(next_token) = self.peek_next() {
                     self.advance(); // Second token of computation
                     self.advance(); // Third token of computation
@@ -152,6 +161,11 @@ impl<'s> Parser<'s> {
             _ => return Err(Error::parse(token, "unknown computation")),
         };
 
+        let comp = match self.peek().kind {
+            LeftParen => self.m_bit(comp)?,
+            _ => comp,
+        };
+
         // Ensure end of computation.
         let token = self.peek();
         match token.kind {
@@ -160,6 +174,18 @@ impl<'s> Parser<'s> {
         }
     }
 
+    /// Parses the `(M)` after a computation that sets the M bit.
+    fn m_bit(&mut self, comp: Computation) -> Result<'s, Computation> {
+        let token = self.advance(); // The left paren
+        if !matches!(self.advance().kind, Identifier("M")) {
+            return Err(Error::parse(self.previous(), "expect M"));
+        }
+        if !matches!(self.advance().kind, RightParen) {
+            return Err(Error::parse(self.previous(), "expect ')'"));
+        }
+        with_m_bit(comp, token)
+    }
+
     fn jump(&mut self) -> Result<'s, Jump> {
         let token = self.peek();
         match token.kind {
@@ -178,9 +204,13 @@ impl<'s> Parser<'s> {
     }
 
     fn end_of_instruction(&mut self) -> Result<'s, ()> {
+        if self.is_at_end() {
+            // The last instruction doesn't need a trailing line break.
+            return Ok(());
+        }
         let token = self.advance();
         match token.kind {
-            LineBreak | Eof => Ok(()),
+            LineBreak => Ok(()),
             _ => Err(Error::parse(token, "expect end of instruction")),
         }
     }
@@ -208,3 +238,40 @@ impl<'s> Parser<'s> {
         self.tokens.get(self.current + 1)
     }
 }
+
+#[cfg(feature = "extended-isa")]
+fn shift<'s>(ident: &str, token: &'s Token<'s>) -> Result<'s, Computation> {
+    use Computation::*;
+    let comp = match (ident, &token.kind) {
+        ("D", ShiftLeft) => DShiftLeft,
+        ("A", ShiftLeft) => AShiftLeft,
+        ("M", ShiftLeft) => MShiftLeft,
+        ("D", ShiftRight) => DShiftRight,
+        ("A", ShiftRight) => AShiftRight,
+        ("M", ShiftRight) => MShiftRight,
+        _ => unreachable!("should only be called with A, D, or M and a shift"),
+    };
+    Ok(comp)
+}
+
+#[cfg(not(feature = "extended-isa"))]
+fn shift<'s>(_ident: &str, token: &'s Token<'s>) -> Result<'s, Computation> {
+    Err(Error::parse(
+        token,
+        "shift computations require the `extended-isa` feature",
+    ))
+}
+
+#[cfg(feature = "extended-isa")]
+fn with_m_bit<'s>(comp: Computation, token: &'s Token<'s>) -> Result<'s, Computation> {
+    comp.with_m_bit()
+        .ok_or_else(|| Error::parse(token, "only computations without A or M can set the M bit"))
+}
+
+#[cfg(not(feature = "extended-isa"))]
+fn with_m_bit<'s>(_comp: Computation, token: &'s Token<'s>) -> Result<'s, Computation> {
+    Err(Error::parse(
+        token,
+        "setting the M bit requires the `extended-isa` feature",
+    ))
+}
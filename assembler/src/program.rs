@@ -0,0 +1,103 @@
+use crate::code::Generator;
+use crate::instruction::Instruction;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::token::Kind;
+
+/// An assembled program, along with the source line of each ROM word.
+pub struct Program {
+    pub words: Vec<u16>,
+    pub lines: Vec<usize>,
+    /// Every label and the ROM address it points at, in source order.
+    pub labels: Vec<(String, u16)>,
+}
+
+impl Program {
+    /// Assembles the whole source in memory. Errors are already formatted
+    /// since they can't outlive the tokens they point at.
+    pub fn assemble(source: &str) -> Result<Self, String> {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().map_err(|e| e.to_string())?;
+        let mut parser = Parser::new(tokens);
+        let instructions = parser.parse().map_err(|e| e.to_string())?;
+        let mut generator = Generator::new(&instructions);
+        generator.register_labels().map_err(|e| e.to_string())?;
+        let lines = instructions
+            .iter()
+            .filter(|i| !matches!(i, Instruction::Label(_)))
+            .map(|i| i.token().line)
+            .collect();
+        let labels = instructions
+            .iter()
+            .filter_map(|i| match i {
+                Instruction::Label(token) => match token.kind {
+                    Kind::Identifier(label) => {
+                        Some((label.to_string(), generator.symbols()[label]))
+                    }
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect();
+        let words = generator.collect();
+        Ok(Self {
+            words,
+            lines,
+            labels,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trailing_label() {
+        let program = Program::assemble("@END\n0;JMP\n(END)\n").unwrap();
+        assert_eq!(program.words, vec![2, 0b1110101010000111]);
+    }
+
+    #[test]
+    fn test_labels() {
+        let program =
+            Program::assemble("(START)\n@i\nM=0\n(LOOP)\n(AGAIN)\n@LOOP\n0;JMP\n").unwrap();
+        let labels: Vec<(&str, u16)> = program
+            .labels
+            .iter()
+            .map(|(label, address)| (&label[..], *address))
+            .collect();
+        assert_eq!(labels, [("START", 0), ("LOOP", 2), ("AGAIN", 2)]);
+    }
+
+    #[cfg(feature = "extended-isa")]
+    #[test]
+    fn test_extended_isa() {
+        let program =
+            Program::assemble("D=D<<\nAM=M>>\nA>>;JMP\nD=D(M)\n0(M);JMP\nM=-1(M)\n").unwrap();
+        assert_eq!(
+            program.words,
+            [
+                0b1010110000010000,
+                0b1011000000101000,
+                0b1010000000000111,
+                0b1111001100010000,
+                0b1111101010000111,
+                0b1111111010001000,
+            ]
+        );
+        let error = Program::assemble("D=M(M)\n").err().unwrap();
+        assert!(error.contains("only computations without A or M can set the M bit"));
+        let error = Program::assemble("D=D(A)\n").err().unwrap();
+        assert!(error.contains("expect M"));
+    }
+
+    #[cfg(not(feature = "extended-isa"))]
+    #[test]
+    fn test_extended_isa() {
+        let error = Program::assemble("D=D<<\n").err().unwrap();
+        assert!(error.contains("shift computations require the `extended-isa` feature"));
+        let error = Program::assemble("D=D(M)\n").err().unwrap();
+        assert!(error.contains("setting the M bit requires the `extended-isa` feature"));
+    }
+}
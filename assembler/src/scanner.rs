@@ -6,6 +6,7 @@ pub struct Scanner<'s> {
     current: usize,
     start: usize,
     line: usize,
+    line_start: usize,
     tokens: Vec<Token<'s>>,
 }
 
@@ -16,16 +17,18 @@ impl<'s> Scanner<'s> {
             current: 0,
             start: 0,
             line: 1,
+            line_start: 0,
             tokens: vec![],
         }
     }
 
-    pub fn scan_tokens(&mut self) -> Result<'s, &[Token]> {
+    pub fn scan_tokens(&mut self) -> Result<'s, &[Token<'s>]> {
         while !self.is_at_end() {
             self.start = self.current;
             self.scan_token()?;
         }
-        let eof = Token::new(Kind::Eof, self.line, "");
+        self.start = self.current;
+        let eof = Token::new(Kind::Eof, self.line, self.column(), "");
         self.tokens.push(eof);
         Ok(&self.tokens)
     }
@@ -35,10 +38,7 @@ impl<'s> Scanner<'s> {
         use Kind::*;
         match c {
             '@' => self.add_token(At),
-            '\n' => {
-                self.add_token(LineBreak);
-                self.line += 1;
-            }
+            '\n' => self.line_break(),
             '(' => self.add_token(LeftParen),
             ')' => self.add_token(RightParen),
             '=' => self.add_token(Equal),
@@ -48,24 +48,63 @@ impl<'s> Scanner<'s> {
             '+' => self.add_token(Plus),
             '&' => self.add_token(Ampersand),
             '|' => self.add_token(Pipe),
-            '/' => {
-                if self.peek() == Some('/') {
+            '<' | '>' => self.shift(c)?,
+            '/' => match self.peek() {
+                Some('/') => {
                     while !self.is_at_end() && self.peek() != Some('\n') {
                         self.advance();
                     }
                 }
-            }
+                Some('*') => self.block_comment()?,
+                _ => return Err(self.error("expect '//' or '/*'")),
+            },
             '0'..='9' => self.number()?,
             'a'..='z' | 'A'..='Z' => self.identifier(),
             ' ' | '\r' | '\t' => (),
-            _ => {
-                return Err(Error::scan(
-                    self.line,
-                    self.lexeme(),
-                    "unexpected character",
-                ))
+            _ => return Err(self.error("unexpected character")),
+        }
+        Ok(())
+    }
+
+    fn block_comment(&mut self) -> Result<'s, ()> {
+        let (line, column, start) = (self.line, self.column(), self.start);
+        self.advance(); // The asterisk
+        loop {
+            match self.peek() {
+                None => {
+                    let lexeme = &self.source[start..start + 2];
+                    let message = "unterminated block comment";
+                    return Err(Error::scan(line, column, lexeme, message));
+                }
+                Some('*') if self.source[self.current..].starts_with("*/") => {
+                    self.advance();
+                    self.advance();
+                    return Ok(());
+                }
+                Some('\n') => {
+                    // Line breaks inside the comment still end instructions.
+                    self.start = self.current;
+                    self.advance();
+                    self.line_break();
+                }
+                Some(_) => {
+                    self.advance();
+                }
             }
         }
+    }
+
+    fn shift(&mut self, c: char) -> Result<'s, ()> {
+        if self.peek() != Some(c) {
+            return Err(self.error("expect '<<' or '>>'"));
+        }
+        self.advance();
+        let kind = if c == '<' {
+            Kind::ShiftLeft
+        } else {
+            Kind::ShiftRight
+        };
+        self.add_token(kind);
         Ok(())
     }
 
@@ -74,12 +113,8 @@ impl<'s> Scanner<'s> {
             self.advance();
         }
         let lexeme = self.lexeme();
-        let n = lexeme
-            .parse()
-            .map_err(|_| Error::scan(self.line, lexeme, "invalid number"))?;
-        let kind = Kind::Number(n);
-        let token = Token::new(kind, self.line, self.lexeme());
-        self.tokens.push(token);
+        let n = lexeme.parse().map_err(|_| self.error("invalid number"))?;
+        self.add_token(Kind::Number(n));
         Ok(())
     }
 
@@ -90,16 +125,29 @@ impl<'s> Scanner<'s> {
         ) {
             self.advance();
         }
-        let kind = Kind::Identifier(self.lexeme());
-        let token = Token::new(kind, self.line, self.lexeme());
-        self.tokens.push(token);
+        self.add_token(Kind::Identifier(self.lexeme()));
+    }
+
+    fn line_break(&mut self) {
+        self.add_token(Kind::LineBreak);
+        self.line += 1;
+        self.line_start = self.current;
     }
 
     fn add_token(&mut self, kind: Kind<'s>) {
-        let token = Token::new(kind, self.line, self.lexeme());
+        let token = Token::new(kind, self.line, self.column(), self.lexeme());
         self.tokens.push(token);
     }
 
+    fn error(&self, message: &'s str) -> Error<'s> {
+        Error::scan(self.line, self.column(), self.lexeme(), message)
+    }
+
+    /// The column where the current lexeme starts, counting from 1.
+    fn column(&self) -> usize {
+        self.source[self.line_start..self.start].chars().count() + 1
+    }
+
     fn advance(&mut self) -> char {
         let mut char_indices = self.source[self.current..].char_indices().peekable();
         let (_, c) = char_indices.next().expect("should have next char");
@@ -128,3 +176,91 @@ impl<'s> Scanner<'s> {
         self.current >= self.source.len()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<String> {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().unwrap();
+        tokens.iter().map(|t| format!("{:?}", t.kind)).collect()
+    }
+
+    #[test]
+    fn test_line_comment() {
+        assert_eq!(kinds("@1 // hi"), ["At", "Number(1)", "Eof"]);
+    }
+
+    #[test]
+    fn test_block_comment() {
+        assert_eq!(kinds("/* hi */@1"), ["At", "Number(1)", "Eof"]);
+        assert_eq!(kinds("@1/* a * b / c **/"), ["At", "Number(1)", "Eof"]);
+        assert_eq!(
+            kinds("/* one\ntwo */\n@1"),
+            ["LineBreak", "LineBreak", "At", "Number(1)", "Eof"]
+        );
+    }
+
+    #[test]
+    fn test_block_comment_lines() {
+        let mut scanner = Scanner::new("/*\n\n*/ @1\nD");
+        let tokens = scanner.scan_tokens().unwrap();
+        let at = tokens.iter().find(|t| matches!(t.kind, Kind::At)).unwrap();
+        assert_eq!(at.line, 3);
+        assert_eq!(tokens.last().unwrap().line, 4);
+    }
+
+    #[test]
+    fn test_columns() {
+        let mut scanner = Scanner::new("  @LOOP\nAM=M+1 /* x\n */ 0;JMP");
+        let tokens = scanner.scan_tokens().unwrap();
+        let positions: Vec<_> = tokens.iter().map(|t| (t.line, t.column)).collect();
+        assert_eq!(
+            positions,
+            [
+                (1, 3),
+                (1, 4),
+                (1, 8),
+                (2, 1),
+                (2, 3),
+                (2, 4),
+                (2, 5),
+                (2, 6),
+                (2, 12),
+                (3, 5),
+                (3, 6),
+                (3, 7),
+                (3, 10)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unterminated_block_comment() {
+        let mut scanner = Scanner::new("@1\n/* oops");
+        match scanner.scan_tokens() {
+            Err(Error::Scan {
+                line,
+                column,
+                lexeme,
+                ..
+            }) => assert_eq!((line, column, lexeme), (2, 1, "/*")),
+            r => panic!("unexpected {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_stray_slash() {
+        let mut scanner = Scanner::new("D=D/A");
+        match scanner.scan_tokens() {
+            Err(Error::Scan {
+                line,
+                column,
+                lexeme,
+                ..
+            }) => assert_eq!((line, column, lexeme), (1, 4, "/")),
+            r => panic!("unexpected {:?}", r),
+        }
+    }
+}
@@ -2,12 +2,18 @@
 pub struct Token<'s> {
     pub kind: Kind<'s>,
     pub line: usize,
+    pub column: usize,
     pub lexeme: &'s str,
 }
 
 impl<'s> Token<'s> {
-    pub fn new(kind: Kind<'s>, line: usize, lexeme: &'s str) -> Self {
-        Self { kind, line, lexeme }
+    pub fn new(kind: Kind<'s>, line: usize, column: usize, lexeme: &'s str) -> Self {
+        Self {
+            kind,
+            line,
+            column,
+            lexeme,
+        }
     }
 }
 
@@ -20,5 +26,6 @@ pub enum Kind<'s> {
     LeftParen, RightParen,
     Equal, Semicolon,
     Bang, Minus, Plus, Ampersand, Pipe,
+    ShiftLeft, ShiftRight,
     Eof,
 }
@@ -0,0 +1,114 @@
+//! The tools parse their few arguments by hand. These take flags out of the
+//! arguments, leaving the positional ones, and exit with the tool's usage
+//! when a flag misses its value.
+
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::{fs, process};
+
+/// Removes `flag <value>` from the arguments, if present.
+pub fn take_arg(args: &mut Vec<String>, flag: &str, usage: &str) -> Option<String> {
+    let i = args.iter().position(|a| a == flag)?;
+    if i + 1 >= args.len() {
+        exit_with_usage(usage)
+    }
+    let value = args.remove(i + 1);
+    args.remove(i);
+    Some(value)
+}
+
+/// Removes `flag` from the arguments, returning whether it was there.
+pub fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    match args.iter().position(|a| a == flag) {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Removes `-o <path>` from the arguments, if present.
+pub fn take_output_arg(args: &mut Vec<String>, usage: &str) -> Option<PathBuf> {
+    take_arg(args, "-o", usage).map(PathBuf::from)
+}
+
+pub fn exit_with_usage<V>(usage: &str) -> V {
+    eprintln!("{}", usage);
+    process::exit(65)
+}
+
+/// Whether `path` is `-`, standing for stdin or stdout.
+pub fn is_std_stream(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+/// Reads the whole input from `path`, or from stdin if it's `-`.
+pub fn read_input(path: &Path) -> io::Result<String> {
+    if is_std_stream(path) {
+        let mut source = String::new();
+        io::stdin().read_to_string(&mut source)?;
+        Ok(source)
+    } else {
+        fs::read_to_string(path)
+    }
+}
+
+/// Writes the output to `path`, or to stdout if it's `-`.
+pub fn write_output(path: &Path, contents: &str) -> io::Result<()> {
+    if is_std_stream(path) {
+        io::stdout().write_all(contents.as_bytes())
+    } else {
+        fs::write(path, contents)?;
+        eprintln!("Successfully wrote {}", path.to_string_lossy());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &str) -> Vec<String> {
+        args.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_take_arg() {
+        let mut args = args("-n 10 Prog.hack -s 0=1 -s 1=2");
+        assert_eq!(take_arg(&mut args, "-n", "").as_deref(), Some("10"));
+        assert_eq!(take_arg(&mut args, "-s", "").as_deref(), Some("0=1"));
+        assert_eq!(take_arg(&mut args, "-s", "").as_deref(), Some("1=2"));
+        assert_eq!(take_arg(&mut args, "-s", ""), None);
+        assert_eq!(args, ["Prog.hack"]);
+    }
+
+    #[test]
+    fn test_take_flag() {
+        let mut args = args("--fast Prog.hack");
+        assert!(take_flag(&mut args, "--fast"));
+        assert!(!take_flag(&mut args, "--fast"));
+        assert_eq!(args, ["Prog.hack"]);
+    }
+
+    #[test]
+    fn test_take_output_arg() {
+        let mut args = args("- -o Prog.hack");
+        assert_eq!(
+            take_output_arg(&mut args, ""),
+            Some(PathBuf::from("Prog.hack"))
+        );
+        assert_eq!(args, ["-"]);
+        assert_eq!(take_output_arg(&mut args, ""), None);
+    }
+
+    #[test]
+    fn test_files() {
+        let path = std::env::temp_dir().join(format!("cli-test-{}.txt", process::id()));
+        write_output(&path, "@0\n").unwrap();
+        assert_eq!(read_input(&path).unwrap(), "@0\n");
+        fs::remove_file(&path).unwrap();
+        assert!(is_std_stream(Path::new("-")));
+        assert!(!is_std_stream(&path));
+    }
+}
@@ -17,7 +17,7 @@ impl<'c> Compiler<'c> {
         }
     }
 
-    pub fn compile(&mut self) -> Result<&[Command]> {
+    pub fn compile(&mut self) -> Result<&[Command<'c>]> {
         // TODO: maybe use kind_id instead?
         // Note that the ID could change if the Jack grammar changes.
         match self.node().kind() {
@@ -1,23 +1,30 @@
 mod compiler;
 
+use cli::{read_input, take_output_arg, write_output};
 use compiler::Compiler;
 use std::env::args;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::{fs, process};
 use tree_sitter::{Parser, Tree};
 use vm_translator::parser::Command;
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync + 'static>>;
 
+const USAGE: &str = "Usage: compiler [-o output] [file, directory, or - for stdin]";
+
 fn main() -> Result<()> {
-    if args().count() > 2 {
-        eprintln!("Usage: compiler [file or directory]");
+    let mut args: Vec<String> = args().skip(1).collect();
+    let out_path = take_output_arg(&mut args, USAGE);
+    if args.len() > 1 {
+        eprintln!("{}", USAGE);
         process::exit(65);
-    } else if let Some(arg) = args().nth(1) {
-        if fs::metadata(&arg)?.is_dir() {
-            compile_dir(&arg)?;
+    } else if let Some(arg) = args.first() {
+        if arg == "-" {
+            compile_stdin(out_path)?;
+        } else if fs::metadata(arg)?.is_dir() {
+            compile_dir(arg, out_path)?;
         } else {
-            compile_file(&arg)?;
+            compile_file(arg, out_path)?;
         }
     } else {
         todo!("run on cwd if called without args");
@@ -25,7 +32,7 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn compile_dir<P: AsRef<Path>>(path: P) -> Result<()> {
+fn compile_dir<P: AsRef<Path>>(path: P, out_path: Option<PathBuf>) -> Result<()> {
     let path_ref = path.as_ref();
     let dir_name = file_stem(path_ref)?;
     let mut contents = String::new();
@@ -40,25 +47,37 @@ fn compile_dir<P: AsRef<Path>>(path: P) -> Result<()> {
             _ => {}
         }
     }
-    let out_path = path_ref.join(dir_name).with_extension("vm");
-    fs::write(&out_path, contents)?;
-    eprintln!("Successfully wrote {}", out_path.to_string_lossy());
+    let out_path = out_path.unwrap_or_else(|| path_ref.join(dir_name).with_extension("vm"));
+    write_output(&out_path, &contents)?;
     Ok(())
 }
 
-fn compile_file<P: AsRef<Path>>(path: P) -> Result<()> {
+fn compile_file<P: AsRef<Path>>(path: P, out_path: Option<PathBuf>) -> Result<()> {
     let path_ref = path.as_ref();
-    let out_path = path_ref.with_extension("vm");
+    let out_path = out_path.unwrap_or_else(|| path_ref.with_extension("vm"));
     let stem = file_stem(path_ref)?;
     let source = fs::read_to_string(path_ref)?;
     let contents = compile_str(&source, stem)?;
-    fs::write(&out_path, contents)?;
-    eprintln!("Successfully wrote {}", out_path.to_string_lossy());
+    write_output(&out_path, &contents)?;
+    Ok(())
+}
+
+fn compile_stdin(out_path: Option<PathBuf>) -> Result<()> {
+    let source = read_input(Path::new("-"))?;
+    // There's no file name to go by, so use the name the class declares.
+    let tree = parse(&source)?;
+    let class_name = class_name(&tree, &source)?;
+    let contents = compile_tree(&tree, &source, class_name)?;
+    write_output(&out_path.unwrap_or_else(|| PathBuf::from("-")), &contents)?;
     Ok(())
 }
 
 fn compile_str(input: &str, class_name: &str) -> Result<String> {
     let tree = parse(input)?;
+    compile_tree(&tree, input, class_name)
+}
+
+fn compile_tree(tree: &Tree, input: &str, class_name: &str) -> Result<String> {
     let cursor = tree.walk();
     let mut compiler = Compiler::new(input, cursor);
     let commands = compiler.compile()?;
@@ -67,7 +86,6 @@ fn compile_str(input: &str, class_name: &str) -> Result<String> {
         out += &command_to_str(cmd, class_name);
         out += "\n";
     }
-    println!("\n========== vm commands ==========\n{}", out);
     Ok(out)
 }
 
@@ -78,7 +96,17 @@ fn parse(input: &str) -> Result<Tree> {
     Ok(tree)
 }
 
-fn command_to_str<'c>(command: &'c Command, class_name: &str) -> String {
+fn class_name<'s>(tree: &Tree, source: &'s str) -> Result<&'s str> {
+    let root = tree.root_node();
+    let mut cursor = root.walk();
+    let name = root
+        .named_children(&mut cursor)
+        .find(|node| node.kind() == "identifier")
+        .ok_or("expected class name")?;
+    Ok(name.utf8_text(source.as_bytes())?)
+}
+
+fn command_to_str(command: &Command, class_name: &str) -> String {
     use Command::*;
     match command {
         Add => "add".to_string(),
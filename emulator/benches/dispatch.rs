@@ -0,0 +1,53 @@
+//! The plain interpreter against the pre-decoded one, on the translation of
+//! `08/FunctionCalls/FibonacciElement`, computing the 4th element like the
+//! original and the 20th for a longer run.
+
+use assembler::program::Program;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use emulator::cpu::Cpu;
+use emulator::decoded::Decoded;
+use std::fs;
+use std::path::Path;
+use vm_translator::code::{boot, translate};
+use vm_translator::parser::parse;
+
+fn fibonacci(n: u16) -> Vec<u16> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../08/FunctionCalls/FibonacciElement");
+    let mut asm = boot();
+    for class in ["Main", "Sys"] {
+        let source = fs::read_to_string(dir.join(format!("{}.vm", class))).unwrap();
+        let source = source.replace("push constant 4", &format!("push constant {}", n));
+        asm += &translate(&parse(&source).unwrap().1, class);
+    }
+    Program::assemble(&asm).unwrap().words
+}
+
+fn bench(c: &mut Criterion) {
+    let mut group = c.benchmark_group("FibonacciElement");
+    group.sample_size(20);
+    for n in [4, 20] {
+        let words = fibonacci(n);
+        group.bench_with_input(BenchmarkId::new("plain", n), &words, |b, words| {
+            b.iter(|| {
+                let mut cpu = Cpu::new(words);
+                while !cpu.is_halted() {
+                    cpu.step();
+                }
+                cpu.time
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("decoded", n), &words, |b, words| {
+            // Decoding is done once per program, like loading it.
+            let decoded = Decoded::new(&Cpu::new(words).rom);
+            b.iter(|| {
+                let mut cpu = Cpu::new(words);
+                decoded.run(&mut cpu, u64::MAX);
+                cpu.time
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
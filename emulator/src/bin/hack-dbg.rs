@@ -0,0 +1,54 @@
+use cli::{exit_with_usage, take_arg};
+use emulator::debugger::Debugger;
+use emulator::load;
+use std::env::args;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::process;
+
+const USAGE: &str = "\
+Usage: hack-dbg [--restore state] program.asm|program.hack
+
+Debugs the program on the CPU emulator, from the start or from a state the
+emulator saved. Breakpoints can use the labels of a .asm program. Type
+`help` for the commands.";
+
+fn main() {
+    let mut args: Vec<String> = args().skip(1).collect();
+    let restore = take_arg(&mut args, "--restore", USAGE);
+    let [path] = args.as_slice() else {
+        exit_with_usage(USAGE)
+    };
+    let program = load::program(Path::new(path)).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(65)
+    });
+    let mut debugger = Debugger::new(program);
+    let first = match restore {
+        Some(state) => format!("restore {}", state),
+        None => "x 0".to_string(),
+    };
+    match debugger.execute(&first) {
+        Ok(output) => print!("{}", output),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(65);
+        }
+    }
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        print!("(hack-dbg) ");
+        io::stdout().flush().unwrap();
+        let Some(Ok(line)) = lines.next() else {
+            println!();
+            break;
+        };
+        if matches!(line.trim(), "quit" | "q") {
+            break;
+        }
+        match debugger.execute(&line) {
+            Ok(output) => print!("{}", output),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+}
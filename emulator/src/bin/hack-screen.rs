@@ -0,0 +1,201 @@
+use cli::{exit_with_usage, take_arg, take_flag};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, execute, queue, style};
+use emulator::cpu::{Cpu, KBD};
+use emulator::keyboard::key_code;
+use emulator::load;
+use emulator::screen::{self, HEIGHT, WIDTH};
+use std::env::args;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use std::{fmt, process};
+
+const USAGE: &str = "\
+Usage: hack-screen [-c cycles per second] [--scale n] [--half-blocks] program.asm|program.hack
+
+Runs the program in the terminal, drawing the screen with braille dots, or
+with half blocks, shrunk to fit unless a scale is given. Keys go to KBD.
+Ctrl-C quits.";
+
+const FRAMES_PER_SECOND: u64 = 30;
+
+/// How long a key stays down when the terminal can't report releases.
+/// Long enough to bridge the delay before the terminal repeats a held key.
+const HOLD: Duration = Duration::from_millis(500);
+
+fn main() {
+    let mut args: Vec<String> = args().skip(1).collect();
+    let speed: u64 = take_arg(&mut args, "-c", USAGE)
+        .map(|n| n.parse().unwrap_or_else(exit_with_error))
+        .unwrap_or(5_000_000);
+    let scale: Option<usize> =
+        take_arg(&mut args, "--scale", USAGE).map(|n| n.parse().unwrap_or_else(exit_with_error));
+    let half_blocks = take_flag(&mut args, "--half-blocks");
+    let [path] = args.as_slice() else {
+        exit_with_usage(USAGE)
+    };
+    let program = load::program(Path::new(path)).unwrap_or_else(exit_with_error);
+    // Dots per character, across and down.
+    let cell = if half_blocks { (1, 2) } else { (2, 4) };
+    let scale = match scale {
+        Some(0) => exit_with_error("the scale must be at least 1"),
+        Some(scale) => scale,
+        None => {
+            let (columns, rows) = terminal::size().unwrap_or_else(exit_with_error);
+            let rows = rows.saturating_sub(1).max(1) as usize;
+            let across = WIDTH.div_ceil(cell.0 * columns.max(1) as usize);
+            let down = HEIGHT.div_ceil(cell.1 * rows);
+            across.max(down)
+        }
+    };
+
+    let terminal = Terminal::enter().unwrap_or_else(exit_with_error);
+    let result = run(&program.words, speed, scale, half_blocks, &terminal);
+    drop(terminal);
+    result.unwrap_or_else(exit_with_error);
+}
+
+fn run(
+    program: &[u16],
+    speed: u64,
+    scale: usize,
+    half_blocks: bool,
+    terminal: &Terminal,
+) -> io::Result<()> {
+    let mut cpu = Cpu::new(program);
+    let mut out = io::stdout();
+    let frame = Duration::from_secs(1) / FRAMES_PER_SECOND as u32;
+    let mut released_at: Option<Instant> = None;
+    // Speeds that don't divide evenly into frames carry the rest over, so
+    // that even a few cycles per second get run.
+    let mut owed = 0;
+    loop {
+        let start = Instant::now();
+        owed += speed;
+        let cycles = owed / FRAMES_PER_SECOND;
+        owed %= FRAMES_PER_SECOND;
+        if !cpu.is_halted() {
+            for _ in 0..cycles {
+                cpu.step();
+            }
+        }
+
+        let lines = if half_blocks {
+            screen::half_blocks(&cpu.ram, scale)
+        } else {
+            screen::braille(&cpu.ram, scale)
+        };
+        for (row, line) in lines.iter().enumerate() {
+            queue!(out, cursor::MoveTo(0, row as u16), style::Print(line))?;
+        }
+        let state = if cpu.is_halted() { "halted" } else { "running" };
+        let status = format!(
+            "cycle {}, {}, KBD={} - Ctrl-C quits",
+            cpu.time, state, cpu.ram[KBD]
+        );
+        queue!(
+            out,
+            cursor::MoveTo(0, lines.len() as u16),
+            terminal::Clear(terminal::ClearType::CurrentLine),
+            style::Print(status)
+        )?;
+        out.flush()?;
+
+        while let Some(timeout) = frame.checked_sub(start.elapsed()) {
+            if !event::poll(timeout)? {
+                break;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if is_quit(&key) {
+                return Ok(());
+            }
+            let Some(code) = hack_key(key.code) else {
+                continue;
+            };
+            match key.kind {
+                KeyEventKind::Release => {
+                    if cpu.ram[KBD] == code {
+                        cpu.ram[KBD] = 0;
+                    }
+                }
+                _ => {
+                    cpu.ram[KBD] = code;
+                    released_at = (!terminal.reports_releases).then(|| Instant::now() + HOLD);
+                }
+            }
+        }
+        if released_at.is_some_and(|at| Instant::now() >= at) {
+            cpu.ram[KBD] = 0;
+            released_at = None;
+        }
+    }
+}
+
+fn is_quit(key: &KeyEvent) -> bool {
+    key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c')
+}
+
+/// The Hack key code of a key from the terminal.
+fn hack_key(code: KeyCode) -> Option<u16> {
+    let name = match code {
+        KeyCode::Char(c) => return key_code(&c.to_string()),
+        KeyCode::F(n) if (1..=12).contains(&n) => return Some(140 + n as u16),
+        KeyCode::Enter => "newline",
+        KeyCode::Backspace => "backspace",
+        KeyCode::Left => "left",
+        KeyCode::Up => "up",
+        KeyCode::Right => "right",
+        KeyCode::Down => "down",
+        KeyCode::Home => "home",
+        KeyCode::End => "end",
+        KeyCode::PageUp => "pageup",
+        KeyCode::PageDown => "pagedown",
+        KeyCode::Insert => "insert",
+        KeyCode::Delete => "delete",
+        KeyCode::Esc => "esc",
+        _ => return None,
+    };
+    key_code(name)
+}
+
+/// The terminal in raw mode on the alternate screen, until dropped.
+struct Terminal {
+    /// Whether the terminal tells when keys are released.
+    reports_releases: bool,
+}
+
+impl Terminal {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, cursor::Hide)?;
+        let reports_releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if reports_releases {
+            let flags = KeyboardEnhancementFlags::REPORT_EVENT_TYPES;
+            execute!(io::stdout(), PushKeyboardEnhancementFlags(flags))?;
+        }
+        Ok(Self { reports_releases })
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let mut out = io::stdout();
+        if self.reports_releases {
+            let _ = execute!(out, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(out, cursor::Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn exit_with_error<V, E: fmt::Display>(e: E) -> V {
+    eprintln!("{}", e);
+    process::exit(65)
+}
@@ -0,0 +1,189 @@
+pub const MEMORY_SIZE: usize = 32768;
+pub const SCREEN: usize = 16384;
+pub const KBD: usize = 24576;
+
+/// The Hack computer: CPU, instruction memory and data memory.
+#[derive(Clone)]
+pub struct Cpu {
+    pub a: u16,
+    pub d: u16,
+    pub pc: u16,
+    pub rom: Vec<u16>,
+    pub ram: Vec<u16>,
+    /// Instructions executed since the program was loaded.
+    pub time: u64,
+}
+
+impl Cpu {
+    pub fn new(program: &[u16]) -> Self {
+        let mut rom = vec![0; MEMORY_SIZE];
+        let len = program.len().min(MEMORY_SIZE);
+        rom[..len].copy_from_slice(&program[..len]);
+        Self {
+            a: 0,
+            d: 0,
+            pc: 0,
+            rom,
+            ram: vec![0; MEMORY_SIZE],
+            time: 0,
+        }
+    }
+
+    /// Executes the instruction at PC.
+    pub fn step(&mut self) {
+        let instruction = self.rom[self.pc as usize & 0x7FFF];
+        self.time += 1;
+        if instruction & 0x8000 == 0 {
+            self.a = instruction;
+            self.pc = self.pc.wrapping_add(1);
+            return;
+        }
+        let address = self.a as usize & 0x7FFF;
+        let y = if instruction & 0x1000 != 0 {
+            self.ram[address]
+        } else {
+            self.a
+        };
+        let out = compute(instruction, self.d, y);
+        let jump_to = self.a;
+        if instruction & 0b100000 != 0 {
+            self.a = out;
+        }
+        if instruction & 0b010000 != 0 {
+            self.d = out;
+        }
+        if instruction & 0b001000 != 0 {
+            self.ram[address] = out;
+        }
+        self.pc = if jumps(instruction, out) {
+            jump_to
+        } else {
+            self.pc.wrapping_add(1)
+        };
+    }
+}
+
+impl Cpu {
+    /// Whether the program is stuck in the loop programs end with: an
+    /// unconditional jump that stores nothing, to itself or to the `@END`
+    /// just before it, with PC on either instruction.
+    pub fn is_halted(&self) -> bool {
+        let pc = self.pc as usize & 0x7FFF;
+        let jumps_back = |at: usize| self.rom.get(at).is_some_and(|&i| i & 0x803F == 0x8007);
+        if self.rom[pc] as usize == pc {
+            return jumps_back(pc + 1);
+        }
+        let target = self.a as usize;
+        jumps_back(pc)
+            && (target == pc || (target + 1 == pc && self.rom[target] as usize == target))
+    }
+}
+
+/// The comp part of a C-instruction, with `y` being A or M.
+fn compute(instruction: u16, x: u16, y: u16) -> u16 {
+    #[cfg(feature = "extended-isa")]
+    if instruction & 0xE000 == 0xA000 {
+        let operand = if instruction & 0x0400 != 0 { x } else { y };
+        return if instruction & 0x0800 != 0 {
+            operand << 1
+        } else {
+            ((operand as i16) >> 1) as u16
+        };
+    }
+    alu(x, y, (instruction >> 6) as u8)
+}
+
+/// The Hack ALU, driven by the six control bits zx, nx, zy, ny, f and no.
+pub fn alu(x: u16, y: u16, control: u8) -> u16 {
+    let bit = |n: u8| control & (1 << (5 - n)) != 0;
+    let x = if bit(0) { 0 } else { x };
+    let x = if bit(1) { !x } else { x };
+    let y = if bit(2) { 0 } else { y };
+    let y = if bit(3) { !y } else { y };
+    let out = if bit(4) { x.wrapping_add(y) } else { x & y };
+    if bit(5) {
+        !out
+    } else {
+        out
+    }
+}
+
+fn jumps(instruction: u16, out: u16) -> bool {
+    let out = out as i16;
+    (instruction & 0b100 != 0 && out < 0)
+        || (instruction & 0b010 != 0 && out == 0)
+        || (instruction & 0b001 != 0 && out > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assembler::program::Program;
+
+    fn run(source: &str, steps: usize) -> Cpu {
+        let program = Program::assemble(source).unwrap();
+        let mut cpu = Cpu::new(&program.words);
+        for _ in 0..steps {
+            cpu.step();
+        }
+        cpu
+    }
+
+    #[test]
+    fn test_alu() {
+        let (x, y) = (5, 3);
+        assert_eq!(alu(x, y, 0b101010), 0);
+        assert_eq!(alu(x, y, 0b111111), 1);
+        assert_eq!(alu(x, y, 0b111010), 0xFFFF);
+        assert_eq!(alu(x, y, 0b001100), 5);
+        assert_eq!(alu(x, y, 0b110000), 3);
+        assert_eq!(alu(x, y, 0b001111), (-5i16) as u16);
+        assert_eq!(alu(x, y, 0b011111), 6);
+        assert_eq!(alu(x, y, 0b110010), 2);
+        assert_eq!(alu(x, y, 0b000010), 8);
+        assert_eq!(alu(x, y, 0b010011), 2);
+        assert_eq!(alu(x, y, 0b000111), (-2i16) as u16);
+        assert_eq!(alu(x, y, 0b000000), 1);
+        assert_eq!(alu(x, y, 0b010101), 7);
+    }
+
+    #[test]
+    fn test_step() {
+        let cpu = run("@7\nD=A\n@100\nAM=D-1\nD=D+M\n", 5);
+        assert_eq!((cpu.a, cpu.d, cpu.pc), (6, 7, 5));
+        assert_eq!(cpu.ram[100], 6);
+        assert_eq!(cpu.time, 5);
+    }
+
+    #[test]
+    fn test_jump() {
+        let source = "@3\nD=A\n(LOOP)\n@LOOP\nD=D-1;JGT\n@END\n(END)\n0;JMP\n";
+        let cpu = run(source, 10);
+        assert_eq!((cpu.d, cpu.pc), (0, 5));
+        let cpu = run(source, 20);
+        assert_eq!((cpu.d, cpu.pc), (0, 5));
+        assert!(cpu.is_halted());
+    }
+
+    #[test]
+    fn test_halted() {
+        let source = "@2\nD;JGT\n(END)\n@END\n0;JMP\n";
+        assert!(!run(source, 1).is_halted());
+        // Halted on both instructions of the loop.
+        for steps in 2..6 {
+            assert!(run(source, steps).is_halted());
+        }
+        assert!(run("@0\n0;JMP\n", 1).is_halted());
+        assert!(!run("@3\n0;JMP\nD=0\n@1\n0;JMP\n", 2).is_halted());
+    }
+
+    #[cfg(feature = "extended-isa")]
+    #[test]
+    fn test_shift() {
+        let cpu = run("@5\nD=A\nD=D<<\n@0\nM=-1\nM=M>>\nA=A>>\n", 7);
+        assert_eq!(cpu.d, 10);
+        assert_eq!(cpu.ram[0], 0xFFFF);
+        let cpu = run("@12\nA=A>>\n", 2);
+        assert_eq!(cpu.a, 6);
+    }
+}
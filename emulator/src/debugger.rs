@@ -0,0 +1,439 @@
+use crate::cpu::{Cpu, MEMORY_SIZE};
+use crate::keyboard::Keyboard;
+use crate::state;
+use assembler::code::PREDEFINED_SYMBOLS;
+use assembler::disassembler::disassemble;
+use assembler::program::Program;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::fs;
+
+/// The most cycles `continue` and `until` run before giving control back.
+pub const RUN_LIMIT: u64 = 100_000_000;
+
+pub const HELP: &str = "\
+break|b [address|label]     set a breakpoint, or list them
+delete|d address|label      remove a breakpoint
+watch|w [address|symbol]    stop when a RAM word changes, or list watchpoints
+unwatch address|symbol      remove a watchpoint
+step|s [n]                  execute one or n instructions
+continue|c                  run until a breakpoint, a watchpoint or the end
+until|u address|label       run until PC reaches the address
+print|p [A|D|PC|RAM[n]|RAM[n..m]|symbol]
+                            show the registers or memory
+set A|D|PC|RAM[n]|symbol value
+                            change a register or a RAM word
+disassemble|x [n]           show n instructions before and after PC
+reset                       reload the program, clearing the RAM
+save file                   save the state of the computer
+restore file                go back to a saved state
+quit|q                      leave the debugger
+";
+
+/// A CPU emulator controlled by debugger commands, one line at a time.
+pub struct Debugger {
+    pub cpu: Cpu,
+    pub keyboard: Keyboard,
+    program: Program,
+    breakpoints: BTreeSet<u16>,
+    watchpoints: BTreeSet<usize>,
+}
+
+impl Debugger {
+    pub fn new(program: Program) -> Self {
+        Self {
+            cpu: Cpu::new(&program.words),
+            keyboard: Keyboard::default(),
+            program,
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeSet::new(),
+        }
+    }
+
+    /// Runs a command, returning what to show for it.
+    pub fn execute(&mut self, line: &str) -> Result<String, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            [] => Ok(String::new()),
+            ["help" | "h"] => Ok(HELP.to_string()),
+            ["break" | "b"] => Ok(self
+                .breakpoints
+                .iter()
+                .map(|&address| format!("Breakpoint at {}\n", self.describe(address)))
+                .collect()),
+            ["break" | "b", at] => {
+                let address = self.rom_address(at)?;
+                self.breakpoints.insert(address);
+                Ok(format!("Breakpoint at {}\n", self.describe(address)))
+            }
+            ["delete" | "d", at] => {
+                let address = self.rom_address(at)?;
+                if !self.breakpoints.remove(&address) {
+                    return Err(format!("no breakpoint at {}", self.describe(address)));
+                }
+                Ok(String::new())
+            }
+            ["watch" | "w"] => Ok(self
+                .watchpoints
+                .iter()
+                .map(|&address| format!("Watchpoint on RAM[{}]\n", address))
+                .collect()),
+            ["watch" | "w", at] => {
+                let address = ram_address(at)?;
+                self.watchpoints.insert(address);
+                Ok(format!("Watchpoint on RAM[{}]\n", address))
+            }
+            ["unwatch", at] => {
+                let address = ram_address(at)?;
+                if !self.watchpoints.remove(&address) {
+                    return Err(format!("no watchpoint on RAM[{}]", address));
+                }
+                Ok(String::new())
+            }
+            ["step" | "s"] => Ok(self.run(Some(1), None)),
+            ["step" | "s", n] => {
+                let n = n.parse().map_err(|_| format!("invalid count `{}`", n))?;
+                Ok(self.run(Some(n), None))
+            }
+            ["continue" | "c"] => Ok(self.run(None, None)),
+            ["until" | "u", at] => {
+                let address = self.rom_address(at)?;
+                Ok(self.run(None, Some(address)))
+            }
+            ["print" | "p"] => Ok(format!(
+                "A={} D={} PC={} time={}\n",
+                self.cpu.a as i16, self.cpu.d as i16, self.cpu.pc, self.cpu.time
+            )),
+            ["print" | "p", what] => self.print(what),
+            ["set", what, value] => self.set(what, value),
+            ["disassemble" | "x"] => Ok(self.disassemble(5)),
+            ["disassemble" | "x", n] => {
+                let n = n.parse().map_err(|_| format!("invalid count `{}`", n))?;
+                Ok(self.disassemble(n))
+            }
+            ["reset"] => {
+                self.cpu = Cpu::new(&self.program.words);
+                self.keyboard = Keyboard::default();
+                Ok(self.disassemble(0))
+            }
+            ["save", path] => {
+                let bytes = state::save(&self.cpu, &self.keyboard);
+                fs::write(path, bytes).map_err(|e| format!("{}: {}", path, e))?;
+                Ok(format!("Saved cycle {} to {}\n", self.cpu.time, path))
+            }
+            ["restore", path] => {
+                let bytes = fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+                let (cpu, keyboard) =
+                    state::restore(&bytes).map_err(|e| format!("{}: {}", path, e))?;
+                (self.cpu, self.keyboard) = (cpu, keyboard);
+                Ok(self.disassemble(0))
+            }
+            _ => Err(format!("unknown command `{}`, try `help`", line.trim())),
+        }
+    }
+
+    /// Executes `steps` instructions, or runs until the program halts, and
+    /// stops early at breakpoints, at `until` or when a watched word
+    /// changes. A breakpoint at the starting PC doesn't stop it, so that
+    /// running again leaves the breakpoint.
+    fn run(&mut self, steps: Option<u64>, until: Option<u16>) -> String {
+        let mut report = String::new();
+        for i in 0..steps.unwrap_or(RUN_LIMIT) {
+            let pc = self.cpu.pc;
+            if i > 0 && self.breakpoints.contains(&pc) {
+                writeln!(report, "Breakpoint at {}", self.describe(pc)).unwrap();
+                break;
+            }
+            if i > 0 && until == Some(pc) {
+                break;
+            }
+            // Stepping goes on around the final loop, running doesn't.
+            if steps.is_none() && self.cpu.is_halted() {
+                writeln!(report, "Halted at cycle {}", self.cpu.time).unwrap();
+                break;
+            }
+            let watched: Vec<(usize, u16)> = self
+                .watchpoints
+                .iter()
+                .map(|&address| (address, self.cpu.ram[address]))
+                .collect();
+            self.keyboard.update(&mut self.cpu);
+            self.cpu.step();
+            let mut changed = false;
+            for (address, old) in watched {
+                let new = self.cpu.ram[address];
+                if new != old {
+                    let (old, new) = (old as i16, new as i16);
+                    writeln!(report, "Watchpoint RAM[{}]: {} -> {}", address, old, new).unwrap();
+                    changed = true;
+                }
+            }
+            if changed {
+                break;
+            }
+            if steps.is_none() && i + 1 == RUN_LIMIT {
+                writeln!(report, "Stopped after {} cycles", RUN_LIMIT).unwrap();
+            }
+        }
+        report + &self.disassemble(0)
+    }
+
+    fn print(&self, what: &str) -> Result<String, String> {
+        let value = match what {
+            "A" => self.cpu.a,
+            "D" => self.cpu.d,
+            "PC" => self.cpu.pc,
+            _ => {
+                let (start, end) = ram_range(what)?;
+                return Ok((start..end)
+                    .map(|address| format!("RAM[{}] = {}\n", address, self.cpu.ram[address] as i16))
+                    .collect());
+            }
+        };
+        Ok(format!("{} = {}\n", what, value as i16))
+    }
+
+    fn set(&mut self, what: &str, value: &str) -> Result<String, String> {
+        if what == "PC" {
+            self.cpu.pc = self.rom_address(value)?;
+            return Ok(self.disassemble(0));
+        }
+        let value = match value.parse::<i32>() {
+            Ok(value @ -32768..=65535) => value as u16,
+            _ => return Err(format!("invalid value `{}`", value)),
+        };
+        match what {
+            "A" => self.cpu.a = value,
+            "D" => self.cpu.d = value,
+            _ => self.cpu.ram[ram_address(what)?] = value,
+        }
+        Ok(String::new())
+    }
+
+    /// The instructions from `before` before PC to as many after it, with
+    /// `=>` at PC, `*` at breakpoints, and labels where they point.
+    fn disassemble(&self, before: usize) -> String {
+        let pc = self.cpu.pc as usize % MEMORY_SIZE;
+        let start = pc.saturating_sub(before);
+        let end = pc
+            .saturating_add(before)
+            .saturating_add(1)
+            .min(self.program.words.len().max(pc + 1));
+        let mut listing = String::new();
+        for address in start..end {
+            for (label, _) in self.labels(address as u16) {
+                writeln!(listing, "{:9}({})", "", label).unwrap();
+            }
+            let word = self.cpu.rom[address];
+            let instruction = disassemble(word).unwrap_or_else(|| format!("{:016b}", word));
+            let marker = if address == pc { "=>" } else { "  " };
+            let breakpoint = if self.breakpoints.contains(&(address as u16)) {
+                '*'
+            } else {
+                ' '
+            };
+            writeln!(
+                listing,
+                "{}{}{:>5}  {}",
+                marker, breakpoint, address, instruction
+            )
+            .unwrap();
+        }
+        listing
+    }
+
+    fn labels(&self, address: u16) -> impl Iterator<Item = &(String, u16)> {
+        self.program
+            .labels
+            .iter()
+            .filter(move |(_, at)| *at == address)
+    }
+
+    /// An address, with the first label pointing at it.
+    fn describe(&self, address: u16) -> String {
+        match self.labels(address).next() {
+            Some((label, _)) => format!("{} ({})", address, label),
+            None => address.to_string(),
+        }
+    }
+
+    fn rom_address(&self, at: &str) -> Result<u16, String> {
+        if let Ok(address) = at.parse::<usize>() {
+            return match address < MEMORY_SIZE {
+                true => Ok(address as u16),
+                false => Err(format!("address {} is out of ROM", address)),
+            };
+        }
+        self.program
+            .labels
+            .iter()
+            .find(|(label, _)| label == at)
+            .map(|&(_, address)| address)
+            .ok_or_else(|| format!("unknown label `{}`", at))
+    }
+}
+
+/// A RAM address, as `n`, `RAM[n]` or a predefined symbol like `SP`.
+fn ram_address(at: &str) -> Result<usize, String> {
+    match ram_range(at)? {
+        (start, end) if end == start + 1 => Ok(start),
+        _ => Err(format!("expect a single RAM address, found `{}`", at)),
+    }
+}
+
+/// The addresses in `RAM[n..m]`, or the one of a single address.
+fn ram_range(at: &str) -> Result<(usize, usize), String> {
+    if let Some(&(_, address)) = PREDEFINED_SYMBOLS.iter().find(|(name, _)| *name == at) {
+        return Ok((address as usize, address as usize + 1));
+    }
+    let invalid = || format!("invalid RAM address `{}`", at);
+    let inner = at
+        .strip_prefix("RAM[")
+        .and_then(|rest| rest.strip_suffix(']'))
+        .unwrap_or(at);
+    let parse = |n: &str| n.parse::<usize>().map_err(|_| invalid());
+    let (start, end) = match inner.split_once("..") {
+        Some((start, end)) => (parse(start)?, parse(end)?),
+        None => {
+            let address = parse(inner)?;
+            (address, address + 1)
+        }
+    };
+    if start >= end || end > MEMORY_SIZE {
+        return Err(invalid());
+    }
+    Ok((start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "\
+@3
+D=A
+@i
+M=D
+(LOOP)
+@i
+M=M-1
+D=M
+@LOOP
+D;JGT
+(END)
+@END
+0;JMP
+";
+
+    fn debugger() -> Debugger {
+        Debugger::new(Program::assemble(SOURCE).unwrap())
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let mut debugger = debugger();
+        assert_eq!(
+            debugger.execute("b LOOP").unwrap(),
+            "Breakpoint at 4 (LOOP)\n"
+        );
+        assert_eq!(
+            debugger.execute("c").unwrap(),
+            "Breakpoint at 4 (LOOP)\n         (LOOP)\n=>*    4  @16\n"
+        );
+        assert_eq!(debugger.cpu.ram[16], 3);
+        debugger.execute("continue").unwrap();
+        assert_eq!(debugger.cpu.ram[16], 2);
+        assert_eq!(debugger.execute("b").unwrap(), "Breakpoint at 4 (LOOP)\n");
+        debugger.execute("delete 4").unwrap();
+        assert!(debugger.execute("delete LOOP").is_err());
+        let report = debugger.execute("c").unwrap();
+        assert!(report.starts_with("Halted at cycle 19\n"), "{}", report);
+        assert!(debugger.execute("b NOWHERE").is_err());
+    }
+
+    #[test]
+    fn test_step_and_until() {
+        let mut debugger = debugger();
+        assert_eq!(debugger.execute("s").unwrap(), "=>     1  D=A\n");
+        debugger.execute("step 3").unwrap();
+        assert_eq!(debugger.cpu.pc, 4);
+        debugger.execute("until END").unwrap();
+        assert_eq!((debugger.cpu.pc, debugger.cpu.time), (9, 19));
+        // Steps go on around the final loop.
+        debugger.execute("s 3").unwrap();
+        assert_eq!(debugger.cpu.pc, 10);
+        debugger.execute("reset").unwrap();
+        assert_eq!((debugger.cpu.pc, debugger.cpu.ram[16]), (0, 0));
+    }
+
+    #[test]
+    fn test_watchpoints() {
+        let mut debugger = debugger();
+        debugger.execute("watch 16").unwrap();
+        assert_eq!(
+            debugger.execute("c").unwrap(),
+            "Watchpoint RAM[16]: 0 -> 3\n         (LOOP)\n=>     4  @16\n"
+        );
+        let report = debugger.execute("c").unwrap();
+        assert!(report.starts_with("Watchpoint RAM[16]: 3 -> 2\n"));
+        assert_eq!(debugger.execute("w").unwrap(), "Watchpoint on RAM[16]\n");
+        debugger.execute("unwatch RAM[16]").unwrap();
+        assert!(debugger.execute("unwatch RAM[16]").is_err());
+    }
+
+    #[test]
+    fn test_print_and_set() {
+        let mut debugger = debugger();
+        debugger.execute("set SP 256").unwrap();
+        debugger.execute("set RAM[1] -1").unwrap();
+        debugger.execute("set D 7").unwrap();
+        assert_eq!(
+            debugger.execute("p RAM[0..2]").unwrap(),
+            "RAM[0] = 256\nRAM[1] = -1\n"
+        );
+        assert_eq!(debugger.execute("p THIS").unwrap(), "RAM[3] = 0\n");
+        assert_eq!(debugger.execute("p D").unwrap(), "D = 7\n");
+        assert_eq!(
+            debugger.execute("set PC END").unwrap(),
+            "         (END)\n=>     9  @9\n"
+        );
+        assert_eq!(debugger.execute("p").unwrap(), "A=0 D=7 PC=9 time=0\n");
+        assert!(debugger.execute("set A 70000").is_err());
+        assert!(debugger.execute("p RAM[5..5]").is_err());
+        assert!(debugger.execute("p RAM[32768]").is_err());
+        assert!(debugger.execute("jump").is_err());
+    }
+
+    #[test]
+    fn test_save_and_restore() {
+        let path = std::env::temp_dir().join(format!("hack-dbg-{}.state", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut debugger = debugger();
+        debugger.execute("s 5").unwrap();
+        assert_eq!(
+            debugger.execute(&format!("save {}", path)).unwrap(),
+            format!("Saved cycle 5 to {}\n", path)
+        );
+        debugger.execute("c").unwrap();
+        assert_eq!(
+            debugger.execute(&format!("restore {}", path)).unwrap(),
+            "=>     5  M=M-1\n"
+        );
+        assert_eq!((debugger.cpu.time, debugger.cpu.ram[16]), (5, 3));
+        fs::remove_file(path).unwrap();
+        assert!(debugger.execute(&format!("restore {}", path)).is_err());
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let mut debugger = debugger();
+        debugger.execute("b 8").unwrap();
+        debugger.execute("s 6").unwrap();
+        assert_eq!(
+            debugger.execute("x 2").unwrap(),
+            "         (LOOP)\n       4  @16\n       5  M=M-1\n=>     6  D=M\n       7  @4\n  *    8  D;JGT\n"
+        );
+        let listing = debugger.execute(&format!("x {}", usize::MAX)).unwrap();
+        assert!(listing.starts_with("       0  @3\n"));
+    }
+}
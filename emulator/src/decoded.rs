@@ -0,0 +1,330 @@
+use crate::cpu::{alu, Cpu, MEMORY_SIZE};
+
+/// What a C-instruction computes from D and its `y` operand, A or M. The
+/// computations of the instruction set have their own case, so that running
+/// them takes a single operation rather than going through the ALU bits.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Comp {
+    Zero,
+    One,
+    MinusOne,
+    D,
+    Y,
+    NotD,
+    NotY,
+    NegD,
+    NegY,
+    DPlusOne,
+    YPlusOne,
+    DMinusOne,
+    YMinusOne,
+    DPlusY,
+    DMinusY,
+    YMinusD,
+    DAndY,
+    DOrY,
+    /// Any other setting of the ALU's control bits.
+    Alu(u8),
+    #[cfg(feature = "extended-isa")]
+    ShiftLeft {
+        d: bool,
+    },
+    #[cfg(feature = "extended-isa")]
+    ShiftRight {
+        d: bool,
+    },
+}
+
+impl Comp {
+    fn decode(instruction: u16) -> Self {
+        #[cfg(feature = "extended-isa")]
+        if instruction & 0xE000 == 0xA000 {
+            let d = instruction & 0x0400 != 0;
+            return if instruction & 0x0800 != 0 {
+                Comp::ShiftLeft { d }
+            } else {
+                Comp::ShiftRight { d }
+            };
+        }
+        let control = (instruction >> 6) as u8 & 0b111111;
+        match control {
+            0b101010 => Comp::Zero,
+            0b111111 => Comp::One,
+            0b111010 => Comp::MinusOne,
+            0b001100 => Comp::D,
+            0b110000 => Comp::Y,
+            0b001101 => Comp::NotD,
+            0b110001 => Comp::NotY,
+            0b001111 => Comp::NegD,
+            0b110011 => Comp::NegY,
+            0b011111 => Comp::DPlusOne,
+            0b110111 => Comp::YPlusOne,
+            0b001110 => Comp::DMinusOne,
+            0b110010 => Comp::YMinusOne,
+            0b000010 => Comp::DPlusY,
+            0b010011 => Comp::DMinusY,
+            0b000111 => Comp::YMinusD,
+            0b000000 => Comp::DAndY,
+            0b010101 => Comp::DOrY,
+            _ => Comp::Alu(control),
+        }
+    }
+
+    #[inline(always)]
+    fn eval(self, d: u16, y: u16) -> u16 {
+        match self {
+            Comp::Zero => 0,
+            Comp::One => 1,
+            Comp::MinusOne => 0xFFFF,
+            Comp::D => d,
+            Comp::Y => y,
+            Comp::NotD => !d,
+            Comp::NotY => !y,
+            Comp::NegD => d.wrapping_neg(),
+            Comp::NegY => y.wrapping_neg(),
+            Comp::DPlusOne => d.wrapping_add(1),
+            Comp::YPlusOne => y.wrapping_add(1),
+            Comp::DMinusOne => d.wrapping_sub(1),
+            Comp::YMinusOne => y.wrapping_sub(1),
+            Comp::DPlusY => d.wrapping_add(y),
+            Comp::DMinusY => d.wrapping_sub(y),
+            Comp::YMinusD => y.wrapping_sub(d),
+            Comp::DAndY => d & y,
+            Comp::DOrY => d | y,
+            Comp::Alu(control) => alu(d, y, control),
+            #[cfg(feature = "extended-isa")]
+            Comp::ShiftLeft { d: true } => d << 1,
+            #[cfg(feature = "extended-isa")]
+            Comp::ShiftLeft { d: false } => y << 1,
+            #[cfg(feature = "extended-isa")]
+            Comp::ShiftRight { d: true } => ((d as i16) >> 1) as u16,
+            #[cfg(feature = "extended-isa")]
+            Comp::ShiftRight { d: false } => ((y as i16) >> 1) as u16,
+        }
+    }
+}
+
+const DEST_A: u8 = 0b100;
+const DEST_D: u8 = 0b010;
+const DEST_M: u8 = 0b001;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    /// `@value`
+    Load(u16),
+    /// A C-instruction: what it computes, whether `y` is M rather than A,
+    /// where it stores the result, and its jump bits.
+    Compute {
+        comp: Comp,
+        memory: bool,
+        dest: u8,
+        jump: u8,
+    },
+}
+
+impl Op {
+    fn decode(instruction: u16) -> Self {
+        if instruction & 0x8000 == 0 {
+            return Op::Load(instruction);
+        }
+        Op::Compute {
+            comp: Comp::decode(instruction),
+            memory: instruction & 0x1000 != 0,
+            dest: (instruction >> 3) as u8 & 0b111,
+            jump: instruction as u8 & 0b111,
+        }
+    }
+}
+
+/// A ROM decoded once into operations, run in blocks of straight-line code:
+/// each block goes from an address to the next jump, and PC and the cycle
+/// count are only updated at the end of it. It must be decoded again when
+/// the ROM changes.
+pub struct Decoded {
+    ops: Vec<Op>,
+    /// The last address of the block starting at each address.
+    ends: Vec<u16>,
+    /// The addresses where a program may be in the loop programs end with.
+    halts: Vec<bool>,
+}
+
+impl Decoded {
+    pub fn new(rom: &[u16]) -> Self {
+        let ops: Vec<Op> = rom.iter().map(|&word| Op::decode(word)).collect();
+        // Where `Cpu::is_halted` can be true: on an unconditional jump, or on
+        // the `@END` of `(END)`, `@END`, `0;JMP`.
+        let jumps_back = |at: usize| rom.get(at).is_some_and(|&i| i & 0x803F == 0x8007);
+        let loop_start =
+            |at: usize| rom.get(at).is_some_and(|&i| i as usize == at) && jumps_back(at + 1);
+        let halts: Vec<bool> = (0..rom.len())
+            .map(|at| jumps_back(at) || loop_start(at))
+            .collect();
+        let mut ends = vec![0; rom.len()];
+        for address in (0..rom.len()).rev() {
+            let jumps = matches!(ops[address], Op::Compute { jump, .. } if jump != 0);
+            let last = address + 1 == rom.len() || loop_start(address + 1);
+            ends[address] = if jumps || last {
+                address as u16
+            } else {
+                ends[address + 1]
+            };
+        }
+        Self { ops, ends, halts }
+    }
+
+    /// Executes up to `cycles` instructions, exactly like as many calls to
+    /// `Cpu::step` would, but stops early when the program halts. Returns
+    /// the number of instructions executed.
+    pub fn run(&self, cpu: &mut Cpu, cycles: u64) -> u64 {
+        let (start_time, limit) = (cpu.time, cpu.time.saturating_add(cycles));
+        while cpu.time < limit {
+            let start = cpu.pc as usize % MEMORY_SIZE;
+            if self.halts[start] && cpu.is_halted() {
+                break;
+            }
+            let end = self.ends[start] as usize;
+            let len = (end - start + 1) as u64;
+            if limit - cpu.time < len {
+                cpu.step();
+                continue;
+            }
+            for op in &self.ops[start..end] {
+                match *op {
+                    Op::Load(value) => cpu.a = value,
+                    Op::Compute {
+                        comp, memory, dest, ..
+                    } => {
+                        execute(cpu, comp, memory, dest);
+                    }
+                }
+            }
+            let next = cpu.pc.wrapping_add(len as u16);
+            cpu.pc = match self.ops[end] {
+                Op::Load(value) => {
+                    cpu.a = value;
+                    next
+                }
+                Op::Compute {
+                    comp,
+                    memory,
+                    dest,
+                    jump,
+                } => {
+                    let target = cpu.a;
+                    let out = execute(cpu, comp, memory, dest) as i16;
+                    let jumps = (jump & 0b100 != 0 && out < 0)
+                        || (jump & 0b010 != 0 && out == 0)
+                        || (jump & 0b001 != 0 && out > 0);
+                    if jumps {
+                        target
+                    } else {
+                        next
+                    }
+                }
+            };
+            cpu.time += len;
+        }
+        cpu.time - start_time
+    }
+}
+
+#[inline(always)]
+fn execute(cpu: &mut Cpu, comp: Comp, memory: bool, dest: u8) -> u16 {
+    let address = cpu.a as usize & 0x7FFF;
+    let y = if memory { cpu.ram[address] } else { cpu.a };
+    let out = comp.eval(cpu.d, y);
+    if dest & DEST_A != 0 {
+        cpu.a = out;
+    }
+    if dest & DEST_D != 0 {
+        cpu.d = out;
+    }
+    if dest & DEST_M != 0 {
+        cpu.ram[address] = out;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assembler::program::Program;
+    use std::fs;
+    use std::path::Path;
+    use vm_translator::code::{boot, translate};
+    use vm_translator::parser::parse;
+
+    fn same(a: &Cpu, b: &Cpu) -> bool {
+        (a.a, a.d, a.pc, a.time) == (b.a, b.d, b.pc, b.time) && a.ram == b.ram
+    }
+
+    #[test]
+    fn test_decode() {
+        for control in 0..64u16 {
+            let instruction = 0xE000 | control << 6;
+            let op = Comp::decode(instruction);
+            for (d, y) in [(0, 0), (5, 3), (0xFFFF, 1), (0x8000, 0x7FFF)] {
+                assert_eq!(op.eval(d, y), alu(d, y, control as u8), "{:06b}", control);
+            }
+        }
+        assert_eq!(
+            Op::decode(0b1111110111011000),
+            Op::Compute {
+                comp: Comp::YPlusOne,
+                memory: true,
+                dest: DEST_M | DEST_D,
+                jump: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_fibonacci() {
+        let dir =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("../08/FunctionCalls/FibonacciElement");
+        let mut asm = boot();
+        for class in ["Main", "Sys"] {
+            let source = fs::read_to_string(dir.join(format!("{}.vm", class))).unwrap();
+            asm += &translate(&parse(&source).unwrap().1, class);
+        }
+        let program = Program::assemble(&asm).unwrap();
+        let mut plain = Cpu::new(&program.words);
+        while !plain.is_halted() {
+            plain.step();
+        }
+        // In one go, and in stretches that end within blocks.
+        for stretch in [u64::MAX, 7, 1] {
+            let mut cpu = Cpu::new(&program.words);
+            let decoded = Decoded::new(&cpu.rom);
+            while decoded.run(&mut cpu, stretch) > 0 {}
+            assert!(same(&cpu, &plain), "{}", stretch);
+            assert_eq!(cpu.ram[261], 3);
+        }
+    }
+
+    #[test]
+    fn test_random() {
+        // Any word is an instruction, and jumps can go anywhere.
+        let mut state: u32 = 0x9e3779b9;
+        let rom: Vec<u16> = (0..MEMORY_SIZE)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u16
+            })
+            .collect();
+        let mut plain = Cpu::new(&rom);
+        let mut cpu = Cpu::new(&rom);
+        let decoded = Decoded::new(&cpu.rom);
+        for _ in 0..100 {
+            let end = plain.time + 1000;
+            while plain.time < end && !plain.is_halted() {
+                plain.step();
+            }
+            decoded.run(&mut cpu, 1000);
+            assert!(same(&cpu, &plain));
+        }
+        assert!(cpu.time > 1000);
+    }
+}
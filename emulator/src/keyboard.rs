@@ -0,0 +1,242 @@
+use crate::cpu::{Cpu, KBD};
+
+/// Names of the keys that don't type a printable character, from code 128.
+const KEYS: [&str; 25] = [
+    "newline",
+    "backspace",
+    "left",
+    "up",
+    "right",
+    "down",
+    "home",
+    "end",
+    "pageup",
+    "pagedown",
+    "insert",
+    "delete",
+    "esc",
+    "f1",
+    "f2",
+    "f3",
+    "f4",
+    "f5",
+    "f6",
+    "f7",
+    "f8",
+    "f9",
+    "f10",
+    "f11",
+    "f12",
+];
+
+/// The Hack key code of a printable character, or of a key by name.
+pub fn key_code(key: &str) -> Option<u16> {
+    let mut chars = key.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        if c == '\n' {
+            return Some(128);
+        }
+        return (' '..='~').contains(&c).then_some(c as u16);
+    }
+    let key = key.to_lowercase();
+    KEYS.iter()
+        .position(|name| *name == key)
+        .map(|i| 128 + i as u16)
+}
+
+/// Key presses and releases to feed a program through the `KBD` register.
+#[derive(Default)]
+pub struct Keyboard {
+    /// The cycle from which `KBD` holds each key code, 0 for a release,
+    /// sorted by cycle.
+    events: Vec<(u64, u16)>,
+    next: usize,
+}
+
+impl Keyboard {
+    /// Parses a key script, where each line is `<cycle> press <key>` or
+    /// `<cycle> release`. A key is a code, a printable character or the
+    /// name of a key like `left` or `f1`. `//` starts a comment.
+    pub fn parse(script: &str) -> Result<Self, String> {
+        let mut keyboard = Self::default();
+        for (i, line) in script.lines().enumerate() {
+            let line = line.split("//").next().unwrap_or_default();
+            let error = || format!("[line {}] invalid key event `{}`", i + 1, line.trim());
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let code = match words[..] {
+                [] => continue,
+                [_, "release"] => 0,
+                [_, "press", key] => match key.parse() {
+                    Ok(code) => code,
+                    Err(_) => key_code(key).ok_or_else(error)?,
+                },
+                _ => return Err(error()),
+            };
+            let cycle = words[0].parse().map_err(|_| error())?;
+            keyboard.events.push((cycle, code));
+        }
+        keyboard.sort();
+        Ok(keyboard)
+    }
+
+    /// Types `text` from cycle `start`, one character every `period` cycles,
+    /// each key held down for half of that. Line breaks press `newline`, and
+    /// characters without a key code are skipped.
+    pub fn typing(text: &str, start: u64, period: u64) -> Self {
+        let codes = text.chars().filter_map(|c| key_code(&c.to_string()));
+        let events = codes
+            .enumerate()
+            .flat_map(|(i, code)| {
+                let pressed = start + i as u64 * period;
+                [(pressed, code), (pressed + period / 2, 0)]
+            })
+            .collect();
+        Self { events, next: 0 }
+    }
+
+    /// Adds the events of another keyboard, whose cycles count from `time`,
+    /// the CPU's current cycle. The events before it have been fed.
+    pub fn merge(&mut self, other: Keyboard, time: u64) {
+        let events = other.events.into_iter();
+        self.events
+            .extend(events.map(|(cycle, code)| (time.saturating_add(cycle), code)));
+        self.sort();
+        self.next = self.events.partition_point(|&(cycle, _)| cycle < time);
+    }
+
+    fn sort(&mut self) {
+        // Stable, so that of two events at the same cycle, the last one wins.
+        self.events.sort_by_key(|&(cycle, _)| cycle);
+    }
+
+    /// Sets `KBD` to the key pressed at the CPU's current cycle, if that
+    /// changed. Call it before each step.
+    pub fn update(&mut self, cpu: &mut Cpu) {
+        if let Some(code) = self.poll(cpu.time) {
+            cpu.ram[KBD] = code;
+        }
+    }
+
+    /// The key pressed at `time`, if that changed since the last poll. This
+    /// is `update` for machines other than the CPU, counting time their way.
+    pub fn poll(&mut self, time: u64) -> Option<u16> {
+        let mut pressed = None;
+        while let Some(&(cycle, code)) = self.events.get(self.next) {
+            if cycle > time {
+                break;
+            }
+            pressed = Some(code);
+            self.next += 1;
+        }
+        pressed
+    }
+
+    /// The cycle of the next event to feed, if there is one.
+    pub fn next_cycle(&self) -> Option<u64> {
+        self.events.get(self.next).map(|&(cycle, _)| cycle)
+    }
+
+    /// The events, and how many of them were fed to the program.
+    pub fn queue(&self) -> (&[(u64, u16)], usize) {
+        (&self.events, self.next)
+    }
+
+    /// A keyboard with the given events, sorted by cycle, of which `next`
+    /// were fed to the program.
+    pub fn from_queue(events: Vec<(u64, u16)>, next: usize) -> Self {
+        Self { events, next }
+    }
+
+    /// Whether every event has been fed to the program.
+    pub fn is_done(&self) -> bool {
+        self.next == self.events.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::SCREEN;
+    use assembler::program::Program;
+
+    #[test]
+    fn test_key_code() {
+        assert_eq!(key_code("a"), Some(97));
+        assert_eq!(key_code(" "), Some(32));
+        assert_eq!(key_code("Left"), Some(130));
+        assert_eq!(key_code("\n"), Some(128));
+        assert_eq!(key_code("f12"), Some(152));
+        assert_eq!(key_code("é"), None);
+        assert_eq!(key_code("shift"), None);
+    }
+
+    #[test]
+    fn test_parse() {
+        let keyboard =
+            Keyboard::parse("// Pong\n20 release\n10 press 130\n\n15 press q // quit\n").unwrap();
+        assert_eq!(keyboard.events, [(10, 130), (15, 113), (20, 0)]);
+        assert_eq!(
+            Keyboard::parse("10 press\n").err().unwrap(),
+            "[line 1] invalid key event `10 press`"
+        );
+        assert!(Keyboard::parse("soon release\n").is_err());
+        assert!(Keyboard::parse("10 press shift\n").is_err());
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut cpu = Cpu::new(&[]);
+        let mut keyboard = Keyboard::parse("5 press 1\n20 release\n40 press 2\n").unwrap();
+        while cpu.time < 30 {
+            keyboard.update(&mut cpu);
+            cpu.step();
+        }
+        keyboard.merge(Keyboard::parse("0 press 3\n5 release\n").unwrap(), cpu.time);
+        assert_eq!(
+            keyboard.queue(),
+            (&[(5, 1), (20, 0), (30, 3), (35, 0), (40, 2)][..], 2)
+        );
+        keyboard.update(&mut cpu);
+        assert_eq!(cpu.ram[KBD], 3);
+    }
+
+    #[test]
+    fn test_typing() {
+        let mut keyboard = Keyboard::typing("hi\n", 100, 10);
+        assert_eq!(
+            keyboard.events,
+            [
+                (100, 104),
+                (105, 0),
+                (110, 105),
+                (115, 0),
+                (120, 128),
+                (125, 0)
+            ]
+        );
+        keyboard.merge(Keyboard::parse("0 press esc\n").unwrap(), 0);
+        assert_eq!(keyboard.events[0], (0, 140));
+    }
+
+    #[test]
+    fn test_fill() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../04/fill/Fill.asm");
+        let source = std::fs::read_to_string(path).unwrap();
+        // The assembler only knows the computations of the spec.
+        let source = source.replace("D=A+D", "D=D+A");
+        let program = Program::assemble(&source).unwrap();
+        let mut cpu = Cpu::new(&program.words);
+        let mut keyboard = Keyboard::parse("300000 press a\n600000 release\n").unwrap();
+        let mut screen_at = |cycle| {
+            while cpu.time < cycle {
+                keyboard.update(&mut cpu);
+                cpu.step();
+            }
+            (cpu.ram[SCREEN], cpu.ram[KBD - 1])
+        };
+        assert_eq!(screen_at(300000), (0, 0));
+        assert_eq!(screen_at(600000), (0xFFFF, 0xFFFF));
+        assert_eq!(screen_at(900000), (0, 0));
+        assert!(keyboard.is_done());
+    }
+}
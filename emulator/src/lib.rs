@@ -0,0 +1,10 @@
+pub mod cpu;
+pub mod debugger;
+pub mod decoded;
+pub mod keyboard;
+pub mod load;
+pub mod profile;
+pub mod screen;
+pub mod script;
+pub mod state;
+pub mod trace;
@@ -0,0 +1,44 @@
+use assembler::check::parse_hack;
+use assembler::program::Program;
+use std::fs;
+use std::path::Path;
+
+/// Reads a program from a `.asm` file, which keeps its labels, or from a
+/// `.hack` file, which has none.
+pub fn program(path: &Path) -> Result<Program, String> {
+    let source = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    if path.extension().is_some_and(|extension| extension == "asm") {
+        return Program::assemble(&source);
+    }
+    let words = parse_hack(&source)?;
+    Ok(Program {
+        lines: (1..=words.len()).collect(),
+        words,
+        labels: vec![],
+    })
+}
+
+/// The ROM address of `label`, if the program has it.
+pub fn label(program: &Program, label: &str) -> Option<u16> {
+    program
+        .labels
+        .iter()
+        .find(|(name, _)| name == label)
+        .map(|&(_, address)| address)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_program() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+        let asm = program(&root.join("06/max/Max.asm")).unwrap();
+        let hack = program(&root.join("05/Max.hack")).unwrap();
+        assert_eq!(asm.words, hack.words);
+        assert_eq!(label(&asm, "INFINITE_LOOP"), Some(14));
+        assert_eq!(label(&hack, "INFINITE_LOOP"), None);
+        assert!(program(&root.join("06/max/Missing.asm")).is_err());
+    }
+}
@@ -0,0 +1,265 @@
+use assembler::program::Program;
+use cli::{exit_with_usage, take_arg, take_flag};
+use emulator::cpu::Cpu;
+use emulator::decoded::Decoded;
+use emulator::keyboard::Keyboard;
+use emulator::load;
+use emulator::profile::Profiler;
+use emulator::screen;
+use emulator::script::CpuEmulator;
+use emulator::state;
+use emulator::trace::Tracer;
+use std::env::args;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::{fmt, fs, process};
+use test_script::error::Error;
+use test_script::runner::Runner;
+
+const USAGE: &str = "\
+Usage: emulator script.tst
+       emulator [-n cycles] [-s address=value]... [-k keys.txt] [-t text.txt]
+                [--type-period cycles] [--screen-at cycle]... [--screen-at-wait]
+                [--screen-at-halt] [-f png|pbm] [-o directory]
+                [--profile] [--folded stacks.folded]
+                [--trace trace.txt] [--trace-only start..end|label]...
+                [--restore state] [--save state] [--fast]
+                program.asm|program.hack
+       emulator --restore state [options...] [program.asm|program.hack]
+
+Runs a test script, or runs a program until it halts or for the given
+number of cycles, writing the screen to program-<cycle>.png at the chosen
+cycles, whenever Sys.wait is called, or when the program halts.
+
+Key events come from lines like `1000 press left` and `2000 release` in
+keys.txt, or from typing text.txt, a key every 100000 cycles by default.
+
+--profile prints the cycles spent under each label and in each VM
+function. --folded writes them by call stack, for flame graph tools.
+
+--trace writes the cycle, PC, instruction, A, D and RAM write of each
+instruction executed, to stdout with `-`. --trace-only restricts it to ROM
+addresses, or to the code of a label or VM function.
+
+--save writes the state of the computer when the run ends, and --restore
+starts from a saved state, with the labels of the program if one is given.
+The cycles of -n, keys.txt and text.txt then count from the saved cycle.
+
+--fast decodes the program once and runs straight-line code in blocks,
+which is much faster, but cannot profile, trace or stop at Sys.wait.";
+
+fn main() {
+    let args: Vec<String> = args().skip(1).collect();
+    match args.as_slice() {
+        [path] if path.ends_with(".tst") => run_script(Path::new(path)),
+        _ => run_program(args),
+    }
+}
+
+fn run_script(path: &Path) {
+    let script = fs::read_to_string(path).unwrap_or_else(exit_with_error);
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let mut runner = Runner::new(CpuEmulator::default(), dir);
+    let result = runner.run(&script);
+    // Like the official tools, keep the output up to a failure.
+    runner.write_output_file().unwrap_or_else(exit_with_error);
+    match result {
+        Ok(()) => println!("End of script - Comparison ended successfully"),
+        Err(e @ Error::Comparison { .. }) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        Err(e) => exit_with_error(e),
+    }
+}
+
+fn run_program(mut args: Vec<String>) {
+    let cycles: u64 = take_arg(&mut args, "-n", USAGE)
+        .map(|n| n.parse().unwrap_or_else(exit_with_error))
+        .unwrap_or(10_000_000);
+    let mut settings: Vec<(usize, i16)> = vec![];
+    while let Some(setting) = take_arg(&mut args, "-s", USAGE) {
+        let parsed = setting
+            .split_once('=')
+            .and_then(|(address, value)| Some((address.parse().ok()?, value.parse().ok()?)));
+        match parsed {
+            Some((address, value)) if address < emulator::cpu::MEMORY_SIZE => {
+                settings.push((address, value))
+            }
+            _ => usage(),
+        }
+    }
+    let mut screen_at: Vec<u64> = vec![];
+    while let Some(cycle) = take_arg(&mut args, "--screen-at", USAGE) {
+        screen_at.push(cycle.parse().unwrap_or_else(exit_with_error));
+    }
+    let keys = take_arg(&mut args, "-k", USAGE);
+    let text = take_arg(&mut args, "-t", USAGE);
+    let period = take_arg(&mut args, "--type-period", USAGE)
+        .map(|n| n.parse().unwrap_or_else(exit_with_error))
+        .unwrap_or(100_000);
+    let at_wait = take_flag(&mut args, "--screen-at-wait");
+    let at_halt = take_flag(&mut args, "--screen-at-halt");
+    let print_profile = take_flag(&mut args, "--profile");
+    let folded = take_arg(&mut args, "--folded", USAGE);
+    let trace = take_arg(&mut args, "--trace", USAGE);
+    let mut trace_only = vec![];
+    while let Some(spec) = take_arg(&mut args, "--trace-only", USAGE) {
+        trace_only.push(spec);
+    }
+    let format = take_arg(&mut args, "-f", USAGE).unwrap_or_else(|| "png".to_string());
+    let render = match format.as_str() {
+        "png" => screen::png,
+        "pbm" => screen::pbm,
+        _ => usage(),
+    };
+    let out_dir = take_arg(&mut args, "-o", USAGE).map(PathBuf::from);
+    let restore = take_arg(&mut args, "--restore", USAGE);
+    let save = take_arg(&mut args, "--save", USAGE);
+    let fast = take_flag(&mut args, "--fast");
+    if fast && (at_wait || print_profile || folded.is_some() || trace.is_some()) {
+        usage()
+    }
+    let (path, program) = match (args.as_slice(), &restore) {
+        ([path], _) => {
+            let program = load::program(Path::new(path)).unwrap_or_else(exit_with_error);
+            (path.clone(), program)
+        }
+        ([], Some(restore)) => {
+            let program = Program {
+                words: vec![],
+                lines: vec![],
+                labels: vec![],
+            };
+            (restore.clone(), program)
+        }
+        _ => usage(),
+    };
+    let path = Path::new(&path);
+    let (mut cpu, mut keyboard) = match &restore {
+        Some(restore) => {
+            let bytes = fs::read(restore).unwrap_or_else(exit_with_error);
+            let state = state::restore(&bytes);
+            state.unwrap_or_else(|e| exit_with_error(format!("{}: {}", restore, e)))
+        }
+        None => (Cpu::new(&program.words), Keyboard::default()),
+    };
+    let wait = at_wait.then(|| {
+        load::label(&program, "Sys.wait").unwrap_or_else(|| {
+            exit_with_error("--screen-at-wait needs a .asm program with a Sys.wait function")
+        })
+    });
+    let out_dir = out_dir.unwrap_or_else(|| {
+        path.parent()
+            .unwrap_or_else(|| Path::new("."))
+            .to_path_buf()
+    });
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+
+    if let Some(keys) = keys {
+        let script = fs::read_to_string(keys).unwrap_or_else(exit_with_error);
+        let keys = Keyboard::parse(&script).unwrap_or_else(exit_with_error);
+        keyboard.merge(keys, cpu.time);
+    }
+    if let Some(text) = text {
+        let text = fs::read_to_string(text).unwrap_or_else(exit_with_error);
+        keyboard.merge(Keyboard::typing(&text, period, period), cpu.time);
+    }
+
+    let mut tracer = trace.map(|trace| {
+        let mut tracer = Tracer::default();
+        for spec in &trace_only {
+            tracer.only(&program, spec).unwrap_or_else(exit_with_error);
+        }
+        let out: Box<dyn Write> = match trace.as_str() {
+            "-" => Box::new(io::stdout()),
+            path => Box::new(fs::File::create(path).unwrap_or_else(exit_with_error)),
+        };
+        (tracer, BufWriter::new(out))
+    });
+    let mut profiler = (print_profile || folded.is_some()).then(|| Profiler::new(&program));
+    let decoded = fast.then(|| Decoded::new(&cpu.rom));
+    let end = cpu.time.saturating_add(cycles);
+    for &(address, value) in &settings {
+        cpu.ram[address] = value as u16;
+    }
+    let mut written = None;
+    let mut snapshot = |cpu: &Cpu| {
+        if written == Some(cpu.time) {
+            return;
+        }
+        written = Some(cpu.time);
+        let out_path = out_dir.join(format!("{}-{}.{}", stem, cpu.time, format));
+        fs::write(&out_path, render(&cpu.ram)).unwrap_or_else(exit_with_error);
+        eprintln!("Successfully wrote {}", out_path.to_string_lossy());
+    };
+    let halted = loop {
+        if screen_at.contains(&cpu.time) || wait == Some(cpu.pc) {
+            snapshot(&cpu);
+        }
+        if cpu.is_halted() {
+            if at_halt {
+                snapshot(&cpu);
+            }
+            break true;
+        }
+        if cpu.time >= end {
+            break false;
+        }
+        keyboard.update(&mut cpu);
+        if let Some(decoded) = &decoded {
+            // Up to the next cycle something has to happen at.
+            let next_screen = screen_at.iter().copied().filter(|&at| at > cpu.time).min();
+            let until = [Some(end), next_screen, keyboard.next_cycle()]
+                .into_iter()
+                .flatten()
+                .min()
+                .unwrap();
+            let cycles = until - cpu.time;
+            decoded.run(&mut cpu, cycles);
+            continue;
+        }
+        let mut step = |cpu: &mut Cpu| match &mut profiler {
+            Some(profiler) => profiler.step(cpu),
+            None => cpu.step(),
+        };
+        match &mut tracer {
+            Some((tracer, out)) => {
+                if let Some(line) = tracer.step(&mut cpu, step) {
+                    writeln!(out, "{}", line).unwrap_or_else(exit_with_error);
+                }
+            }
+            None => step(&mut cpu),
+        }
+    };
+    if let Some((_, mut out)) = tracer {
+        out.flush().unwrap_or_else(exit_with_error);
+    }
+    let state = if halted { "Halted" } else { "Stopped" };
+    println!(
+        "{} at cycle {}: PC={} A={} D={}",
+        state, cpu.time, cpu.pc, cpu.a as i16, cpu.d as i16
+    );
+    if let Some(profiler) = profiler {
+        if print_profile {
+            print!("\n{}", profiler.table());
+        }
+        if let Some(folded) = folded {
+            fs::write(&folded, profiler.folded()).unwrap_or_else(exit_with_error);
+            eprintln!("Successfully wrote {}", folded);
+        }
+    }
+    if let Some(save) = save {
+        fs::write(&save, state::save(&cpu, &keyboard)).unwrap_or_else(exit_with_error);
+        eprintln!("Successfully wrote {}", save);
+    }
+}
+
+fn usage<V>() -> V {
+    exit_with_usage(USAGE)
+}
+
+fn exit_with_error<V, E: fmt::Display>(e: E) -> V {
+    eprintln!("{}", e);
+    process::exit(65)
+}
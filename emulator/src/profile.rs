@@ -0,0 +1,316 @@
+use crate::cpu::{Cpu, MEMORY_SIZE};
+use assembler::program::Program;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::Write;
+
+/// The labels `vm-translator` puts after each call, where functions return.
+const RETURN_PREFIX: &str = "CALL_RET_";
+
+/// The name of code outside any label, or outside any function.
+const TOP: &str = "(top)";
+
+/// The addresses calls return to.
+fn returns(program: &Program) -> HashSet<u16> {
+    program
+        .labels
+        .iter()
+        .filter(|(label, _)| label.starts_with(RETURN_PREFIX))
+        .map(|&(_, address)| address)
+        .collect()
+}
+
+/// The entry address and name of every VM function the program calls,
+/// sorted by address.
+pub fn functions(program: &Program) -> Vec<(u16, String)> {
+    // A call ends in `@<function>`, `0;JMP`, just before its return label.
+    let entries: BTreeSet<u16> = returns(program)
+        .into_iter()
+        .filter_map(|address| {
+            let word = *program.words.get((address as usize).checked_sub(2)?)?;
+            (word & 0x8000 == 0).then_some(word)
+        })
+        .collect();
+    // The return label of the call before a function often points at its
+    // entry too.
+    entries
+        .into_iter()
+        .map(|entry| {
+            let name = program
+                .labels
+                .iter()
+                .find(|(label, address)| *address == entry && !label.starts_with(RETURN_PREFIX))
+                .map_or_else(|| entry.to_string(), |(label, _)| label.clone());
+            (entry, name)
+        })
+        .collect()
+}
+
+/// Counts the cycles a program spends at each ROM address and in each chain
+/// of VM function calls.
+pub struct Profiler {
+    /// Cycles spent at each ROM address.
+    pub counts: Vec<u64>,
+    labels: Vec<(String, u16)>,
+    /// The entry address and name of every function called from the code,
+    /// sorted by address.
+    functions: Vec<(u16, String)>,
+    returns: HashSet<u16>,
+    /// Each call chain seen, as its caller's chain and the entry address of
+    /// the function called, with the cycles spent in it.
+    chains: Vec<(usize, u16, u64)>,
+    children: HashMap<(usize, u16), usize>,
+    /// The current chain, and the return address of each of its calls.
+    chain: usize,
+    frames: Vec<(usize, u16)>,
+}
+
+impl Profiler {
+    pub fn new(program: &Program) -> Self {
+        let mut labels = program.labels.clone();
+        labels.sort_by_key(|&(_, address)| address);
+        Self {
+            counts: vec![0; MEMORY_SIZE],
+            labels,
+            functions: functions(program),
+            returns: returns(program),
+            chains: vec![(0, 0, 0)],
+            children: HashMap::new(),
+            chain: 0,
+            frames: vec![],
+        }
+    }
+
+    /// Executes the instruction at PC, counting its cycle and following
+    /// calls and returns.
+    pub fn step(&mut self, cpu: &mut Cpu) {
+        let pc = cpu.pc;
+        let instruction = cpu.rom[pc as usize & 0x7FFF];
+        self.counts[pc as usize & 0x7FFF] += 1;
+        self.chains[self.chain].2 += 1;
+        cpu.step();
+        // Calls and returns end in `0;JMP`, which may well jump to the next
+        // address, as the boot code's call to `Sys.init` can.
+        if instruction & 0x8007 != 0x8007 {
+            return;
+        }
+        let next = pc.wrapping_add(1);
+        // A function can start where the return label of a call points,
+        // so calls come first.
+        if self.returns.contains(&next) {
+            self.frames.push((self.chain, next));
+            let chains = &mut self.chains;
+            self.chain = *self
+                .children
+                .entry((self.chain, cpu.pc))
+                .or_insert_with(|| {
+                    chains.push((self.chain, cpu.pc, 0));
+                    chains.len() - 1
+                });
+        } else if self.returns.contains(&cpu.pc) {
+            // Unwind to the frame returning there, if there is one.
+            if let Some(i) = self.frames.iter().rposition(|&(_, at)| at == cpu.pc) {
+                self.chain = self.frames[i].0;
+                self.frames.truncate(i);
+            }
+        }
+    }
+
+    /// The cycles spent under each label, up to the next one, most first.
+    pub fn by_label(&self) -> Vec<(String, u64)> {
+        let mut totals: HashMap<&str, u64> = HashMap::new();
+        for (address, &count) in self.counts.iter().enumerate() {
+            if count > 0 {
+                let i = self
+                    .labels
+                    .partition_point(|&(_, at)| at as usize <= address);
+                let label = match i {
+                    0 => TOP,
+                    _ => &self.labels[i - 1].0,
+                };
+                *totals.entry(label).or_default() += count;
+            }
+        }
+        sorted(totals)
+    }
+
+    /// The cycles spent in the code of each VM function, and in it along
+    /// with the functions it called, most first.
+    pub fn by_function(&self) -> Vec<(String, u64, u64)> {
+        let mut own: HashMap<&str, u64> = HashMap::new();
+        for (address, &count) in self.counts.iter().enumerate() {
+            if count > 0 {
+                let i = self
+                    .functions
+                    .partition_point(|&(entry, _)| entry as usize <= address);
+                let function = match i {
+                    0 => TOP,
+                    _ => &self.functions[i - 1].1,
+                };
+                *own.entry(function).or_default() += count;
+            }
+        }
+        let mut total: HashMap<&str, u64> = HashMap::new();
+        for (i, &(_, _, count)) in self.chains.iter().enumerate() {
+            // Recursive calls count once.
+            let names: BTreeSet<&str> = self.chain_names(i).into_iter().collect();
+            for name in names {
+                *total.entry(name).or_default() += count;
+            }
+        }
+        sorted(own)
+            .into_iter()
+            .map(|(name, own)| {
+                let total = total.get(&name[..]).copied().unwrap_or(own).max(own);
+                (name, own, total)
+            })
+            .collect()
+    }
+
+    /// The functions of a call chain, outermost first.
+    fn chain_names(&self, mut chain: usize) -> Vec<&str> {
+        let mut names = vec![];
+        while chain != 0 {
+            let (caller, entry, _) = self.chains[chain];
+            names.push(self.function_name(entry));
+            chain = caller;
+        }
+        names.push(TOP);
+        names.reverse();
+        names
+    }
+
+    fn function_name(&self, entry: u16) -> &str {
+        match self.functions.binary_search_by_key(&entry, |&(at, _)| at) {
+            Ok(i) => &self.functions[i].1,
+            Err(_) => TOP,
+        }
+    }
+
+    /// The cycles of each call chain, in the folded-stack format flame
+    /// graph tools read: `outer;inner cycles` per line.
+    pub fn folded(&self) -> String {
+        let mut lines: Vec<String> = self
+            .chains
+            .iter()
+            .enumerate()
+            .filter(|&(_, &(_, _, count))| count > 0)
+            .map(|(i, &(_, _, count))| format!("{} {}", self.chain_names(i).join(";"), count))
+            .collect();
+        lines.sort();
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+
+    /// Tables of cycles by label and, if the program makes calls, by VM
+    /// function.
+    pub fn table(&self) -> String {
+        let all: u64 = self.counts.iter().sum();
+        let percent = |count: u64| 100.0 * count as f64 / all.max(1) as f64;
+        let mut table = String::new();
+        writeln!(table, "{:>12} {:>7}  label", "cycles", "%").unwrap();
+        for (label, count) in self.by_label() {
+            writeln!(table, "{:>12} {:>6.2}%  {}", count, percent(count), label).unwrap();
+        }
+        if !self.functions.is_empty() {
+            writeln!(
+                table,
+                "\n{:>12} {:>7} {:>12} {:>7}  function",
+                "self", "%", "total", "%"
+            )
+            .unwrap();
+            for (name, own, total) in self.by_function() {
+                writeln!(
+                    table,
+                    "{:>12} {:>6.2}% {:>12} {:>6.2}%  {}",
+                    own,
+                    percent(own),
+                    total,
+                    percent(total),
+                    name
+                )
+                .unwrap();
+            }
+        }
+        table
+    }
+}
+
+/// Most cycles first, then by name.
+fn sorted(totals: HashMap<&str, u64>) -> Vec<(String, u64)> {
+    let mut totals: Vec<(String, u64)> = totals
+        .into_iter()
+        .map(|(name, count)| (name.to_string(), count))
+        .collect();
+    totals.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    totals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+    use vm_translator::code::{boot, translate};
+    use vm_translator::parser::parse;
+
+    fn profile(source: &str) -> (Profiler, Cpu) {
+        let program = Program::assemble(source).unwrap();
+        let mut profiler = Profiler::new(&program);
+        let mut cpu = Cpu::new(&program.words);
+        while !cpu.is_halted() {
+            profiler.step(&mut cpu);
+        }
+        (profiler, cpu)
+    }
+
+    #[test]
+    fn test_labels() {
+        let (profiler, cpu) = profile("@3\nD=A\n(LOOP)\nD=D-1\n@LOOP\nD;JGT\n(END)\n@END\n0;JMP\n");
+        assert_eq!(cpu.time, 11);
+        let labels = [("LOOP".to_string(), 9), ("(top)".to_string(), 2)];
+        assert_eq!(profiler.by_label(), labels);
+        assert_eq!(profiler.folded(), "(top) 11\n");
+        assert_eq!(profiler.by_function(), [("(top)".to_string(), 11, 11)]);
+        assert!(!profiler.table().contains("function"));
+    }
+
+    #[test]
+    fn test_fibonacci() {
+        let dir =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("../08/FunctionCalls/FibonacciElement");
+        for classes in [["Main", "Sys"], ["Sys", "Main"]] {
+            let mut asm = boot();
+            for class in classes {
+                let source = fs::read_to_string(dir.join(format!("{}.vm", class))).unwrap();
+                asm += &translate(&parse(&source).unwrap().1, class);
+            }
+            check_fibonacci(&asm);
+        }
+    }
+
+    fn check_fibonacci(asm: &str) {
+        let (profiler, cpu) = profile(asm);
+        assert_eq!(cpu.ram[261], 3);
+
+        let functions = profiler.by_function();
+        let names: Vec<&str> = functions.iter().map(|(name, _, _)| &name[..]).collect();
+        assert_eq!(names, ["Main.fibonacci", "Sys.init", "(top)"]);
+        let sum: u64 = functions.iter().map(|&(_, own, _)| own).sum();
+        assert_eq!(sum, cpu.time);
+        let (_, own, total) = functions[1];
+        assert!(own < total && total < cpu.time);
+
+        let folded = profiler.folded();
+        let lines: Vec<&str> = folded.lines().collect();
+        assert!(lines[0].starts_with("(top) "));
+        assert!(lines[1].starts_with("(top);Sys.init "));
+        assert!(lines[2].starts_with("(top);Sys.init;Main.fibonacci "));
+        // fibonacci(4) makes calls 4 deep.
+        assert!(folded.contains(";Main.fibonacci;Main.fibonacci;Main.fibonacci;Main.fibonacci "));
+        assert!(!folded.contains(";Main.fibonacci;Main.fibonacci;Main.fibonacci;Main.fibonacci;"));
+        let cycles: u64 = lines
+            .iter()
+            .map(|line| line.rsplit_once(' ').unwrap().1.parse::<u64>().unwrap())
+            .sum();
+        assert_eq!(cycles, cpu.time);
+    }
+}
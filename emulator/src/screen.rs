@@ -0,0 +1,181 @@
+use crate::cpu::SCREEN;
+
+pub const WIDTH: usize = 512;
+pub const HEIGHT: usize = 256;
+
+/// Whether the pixel at column `x` and row `y` is black. Each row is 32
+/// words, and the least significant bit of a word is its leftmost pixel.
+pub fn pixel(ram: &[u16], x: usize, y: usize) -> bool {
+    ram[SCREEN + y * WIDTH / 16 + x / 16] >> (x % 16) & 1 == 1
+}
+
+/// The screen in bytes of 8 pixels, leftmost pixel in the most significant
+/// bit, with black as 1.
+fn packed_rows(ram: &[u16]) -> Vec<u8> {
+    let screen = &ram[SCREEN..SCREEN + WIDTH * HEIGHT / 16];
+    screen
+        .iter()
+        .flat_map(|word| word.reverse_bits().to_be_bytes())
+        .collect()
+}
+
+/// The screen as a binary PBM (P4) image.
+pub fn pbm(ram: &[u16]) -> Vec<u8> {
+    let mut image = format!("P4\n{} {}\n", WIDTH, HEIGHT).into_bytes();
+    image.extend(packed_rows(ram));
+    image
+}
+
+/// The screen as a 1-bit grayscale PNG image.
+pub fn png(ram: &[u16]) -> Vec<u8> {
+    // In grayscale, 0 is black, the other way around from PBM.
+    let data: Vec<u8> = packed_rows(ram).iter().map(|byte| !byte).collect();
+    let mut image = vec![];
+    let mut encoder = png::Encoder::new(&mut image, WIDTH as u32, HEIGHT as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::One);
+    let mut writer = encoder.write_header().expect("writing to memory");
+    writer.write_image_data(&data).expect("writing to memory");
+    writer.finish().expect("writing to memory");
+    image
+}
+
+/// Whether any pixel of the `scale` by `scale` block at column `x` and row
+/// `y` of blocks is black. Blocks past the edge of the screen are white.
+fn block(ram: &[u16], scale: usize, x: usize, y: usize) -> bool {
+    (y * scale..(y + 1) * scale).any(|row| {
+        (x * scale..(x + 1) * scale)
+            .any(|column| row < HEIGHT && column < WIDTH && pixel(ram, column, row))
+    })
+}
+
+/// The screen as lines of braille characters, with a dot for each black
+/// block of `scale` by `scale` pixels, 2 dots wide and 4 high per character.
+pub fn braille(ram: &[u16], scale: usize) -> Vec<String> {
+    // The bit of each dot in the character, by row, then by column.
+    const DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+    let (columns, rows) = (WIDTH.div_ceil(2 * scale), HEIGHT.div_ceil(4 * scale));
+    (0..rows)
+        .map(|y| {
+            (0..columns)
+                .map(|x| {
+                    let mut bits = 0;
+                    for (dy, row) in DOTS.iter().enumerate() {
+                        for (dx, bit) in row.iter().enumerate() {
+                            if block(ram, scale, 2 * x + dx, 4 * y + dy) {
+                                bits |= bit;
+                            }
+                        }
+                    }
+                    char::from_u32(0x2800 + bits).unwrap()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// The screen as lines of half blocks, each character showing two blocks
+/// of `scale` by `scale` pixels, one above the other.
+pub fn half_blocks(ram: &[u16], scale: usize) -> Vec<String> {
+    let (columns, rows) = (WIDTH.div_ceil(scale), HEIGHT.div_ceil(2 * scale));
+    (0..rows)
+        .map(|y| {
+            (0..columns)
+                .map(|x| {
+                    let upper = block(ram, scale, x, 2 * y);
+                    let lower = block(ram, scale, x, 2 * y + 1);
+                    match (upper, lower) {
+                        (false, false) => ' ',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (true, true) => '█',
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::{Cpu, MEMORY_SIZE};
+
+    fn screen_with(pixels: &[(usize, usize)]) -> Vec<u16> {
+        let mut ram = vec![0; MEMORY_SIZE];
+        for &(x, y) in pixels {
+            ram[SCREEN + y * 32 + x / 16] |= 1 << (x % 16);
+        }
+        ram
+    }
+
+    #[test]
+    fn test_pbm() {
+        let ram = screen_with(&[(0, 0), (9, 0), (511, 255)]);
+        let image = pbm(&ram);
+        let header = b"P4\n512 256\n";
+        assert_eq!(&image[..header.len()], header);
+        let data = &image[header.len()..];
+        assert_eq!(data.len(), 64 * 256);
+        assert_eq!(data[..3], [0b10000000, 0b01000000, 0]);
+        assert_eq!(data[data.len() - 1], 1);
+        assert!(pixel(&ram, 9, 0));
+        assert!(!pixel(&ram, 10, 0));
+    }
+
+    #[test]
+    fn test_png() {
+        let ram = screen_with(&[(1, 0), (511, 255)]);
+        let image = png(&ram);
+        let decoder = png::Decoder::new(&image[..]);
+        let mut reader = decoder.read_info().unwrap();
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data).unwrap();
+        assert_eq!((info.width, info.height), (512, 256));
+        assert_eq!(info.bit_depth, png::BitDepth::One);
+        assert_eq!(data[0], 0b10111111);
+        assert_eq!(data[64 * 256 - 1], 0b11111110);
+    }
+
+    #[test]
+    fn test_braille() {
+        let ram = screen_with(&[(0, 0), (1, 3), (511, 255)]);
+        let lines = braille(&ram, 1);
+        assert_eq!((lines.len(), lines[0].chars().count()), (64, 256));
+        assert!(lines[0].starts_with("\u{2881}\u{2800}"));
+        assert!(lines[63].ends_with('\u{2880}'));
+        let lines = braille(&ram, 2);
+        assert_eq!((lines.len(), lines[0].chars().count()), (32, 128));
+        assert!(lines[0].starts_with("\u{2803}\u{2800}"));
+    }
+
+    #[test]
+    fn test_half_blocks() {
+        let ram = screen_with(&[(0, 0), (1, 1), (2, 0), (2, 1), (511, 255)]);
+        let lines = half_blocks(&ram, 1);
+        assert_eq!((lines.len(), lines[0].chars().count()), (128, 512));
+        assert!(lines[0].starts_with("▀▄█ "));
+        assert!(lines[127].ends_with('▄'));
+        let lines = half_blocks(&ram, 3);
+        assert_eq!((lines.len(), lines[0].chars().count()), (43, 171));
+        assert!(lines[0].starts_with("▀ "));
+        assert!(lines[42].ends_with('▄'));
+    }
+
+    #[test]
+    fn test_rect() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../06/rect/Rect.asm");
+        let source = std::fs::read_to_string(path).unwrap();
+        let program = assembler::program::Program::assemble(&source).unwrap();
+        let mut cpu = Cpu::new(&program.words);
+        cpu.ram[0] = 4;
+        while !cpu.is_halted() {
+            cpu.step();
+        }
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                assert_eq!(pixel(&cpu.ram, x, y), x < 16 && y < 4, "({}, {})", x, y);
+            }
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use crate::cpu::{Cpu, MEMORY_SIZE};
+use assembler::check::parse_hack;
+use assembler::program::Program;
+use std::fs;
+use std::path::Path;
+use test_script::output::Value;
+use test_script::runner::Simulator;
+
+/// The CPU emulator dialect of test scripts: loads `.asm` or `.hack` files,
+/// and knows `A`, `D`, `PC`, `time`, `RAM[n]`, `ROM[n]` and `ticktock`.
+pub struct CpuEmulator {
+    pub cpu: Cpu,
+}
+
+impl Default for CpuEmulator {
+    fn default() -> Self {
+        Self { cpu: Cpu::new(&[]) }
+    }
+}
+
+impl Simulator for CpuEmulator {
+    fn load(&mut self, dir: &Path, file: Option<&str>) -> Result<(), String> {
+        let file = file.ok_or("expect a program to load")?;
+        let path = dir.join(file);
+        let source =
+            fs::read_to_string(&path).map_err(|e| format!("can't read `{}`: {}", file, e))?;
+        let words = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("asm") => Program::assemble(&source)?.words,
+            Some("hack") => parse_hack(&source)?,
+            _ => return Err(format!("expect a .asm or .hack file, found `{}`", file)),
+        };
+        self.cpu = Cpu::new(&words);
+        Ok(())
+    }
+
+    fn get(&self, name: &str) -> Result<Value, String> {
+        let word = match name {
+            "A" => self.cpu.a,
+            "D" => self.cpu.d,
+            "PC" => self.cpu.pc,
+            "time" => return Ok(Value::Number(self.cpu.time as i64)),
+            _ => match memory(name)? {
+                ("RAM", address) => self.cpu.ram[address],
+                (_, address) => self.cpu.rom[address],
+            },
+        };
+        Ok(Value::Number(word as i16 as i64))
+    }
+
+    fn set(&mut self, name: &str, value: i64) -> Result<(), String> {
+        let word = value as u16;
+        match name {
+            "A" => self.cpu.a = word,
+            "D" => self.cpu.d = word,
+            "PC" => self.cpu.pc = word,
+            _ => match memory(name)? {
+                ("RAM", address) => self.cpu.ram[address] = word,
+                (_, address) => self.cpu.rom[address] = word,
+            },
+        }
+        Ok(())
+    }
+
+    fn command(&mut self, words: &[String]) -> Result<(), String> {
+        match words {
+            [command] if command == "ticktock" => {
+                self.cpu.step();
+                Ok(())
+            }
+            _ => Err(format!("unknown command `{}`", words.join(" "))),
+        }
+    }
+}
+
+/// Splits `RAM[n]` or `ROM[n]` into the memory name and address.
+fn memory(name: &str) -> Result<(&str, usize), String> {
+    let unknown = || format!("unknown variable `{}`", name);
+    let (memory, rest) = name.split_once('[').ok_or_else(unknown)?;
+    let address = rest
+        .strip_suffix(']')
+        .and_then(|n| n.parse::<usize>().ok())
+        .filter(|&n| n < MEMORY_SIZE)
+        .ok_or_else(unknown)?;
+    match memory {
+        "RAM" | "ROM" => Ok((memory, address)),
+        _ => Err(unknown()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use test_script::runner::Runner;
+    use vm_translator::code::{boot, translate};
+    use vm_translator::parser::parse;
+
+    /// Copies a VM test directory somewhere writable, translates its `.vm`
+    /// files into `<Test>.asm` and runs `<Test>.tst` against `<Test>.cmp`.
+    fn run_vm_test(project: &str, test: &str) {
+        let source_dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("..")
+            .join(project);
+        let dir = std::env::temp_dir().join(format!("emulator-{}-{}", test, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut paths: Vec<PathBuf> = fs::read_dir(&source_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        paths.sort();
+        // Programs without Sys.vm are tested from a stack set up by the script.
+        let mut asm = if paths.iter().any(|p| p.ends_with("Sys.vm")) {
+            boot()
+        } else {
+            String::new()
+        };
+        for path in &paths {
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some("vm") => {
+                    let source = fs::read_to_string(path).unwrap();
+                    let stem = path.file_stem().unwrap().to_str().unwrap();
+                    asm += &translate(&parse(&source).unwrap().1, stem);
+                }
+                Some("tst" | "cmp") => {
+                    fs::copy(path, dir.join(path.file_name().unwrap())).unwrap();
+                }
+                _ => {}
+            }
+        }
+        fs::write(dir.join(test).with_extension("asm"), asm).unwrap();
+        let script = fs::read_to_string(dir.join(test).with_extension("tst")).unwrap();
+        let mut runner = Runner::new(CpuEmulator::default(), &dir);
+        let result = runner.run(&script);
+        fs::remove_dir_all(&dir).unwrap();
+        if let Err(e) = result {
+            panic!("{}: {}", test, e);
+        }
+    }
+
+    #[test]
+    fn test_stack_arithmetic() {
+        run_vm_test("07/StackArithmetic/SimpleAdd", "SimpleAdd");
+        run_vm_test("07/StackArithmetic/StackTest", "StackTest");
+    }
+
+    #[test]
+    fn test_memory_access() {
+        run_vm_test("07/MemoryAccess/BasicTest", "BasicTest");
+        run_vm_test("07/MemoryAccess/PointerTest", "PointerTest");
+        run_vm_test("07/MemoryAccess/StaticTest", "StaticTest");
+    }
+
+    #[test]
+    fn test_program_flow() {
+        run_vm_test("08/ProgramFlow/BasicLoop", "BasicLoop");
+        run_vm_test("08/ProgramFlow/FibonacciSeries", "FibonacciSeries");
+    }
+
+    #[test]
+    fn test_function_calls() {
+        run_vm_test("08/FunctionCalls/SimpleFunction", "SimpleFunction");
+        run_vm_test("08/FunctionCalls/NestedCall", "NestedCall");
+        run_vm_test("08/FunctionCalls/FibonacciElement", "FibonacciElement");
+        run_vm_test("08/FunctionCalls/StaticsTest", "StaticsTest");
+    }
+
+    #[test]
+    fn test_variables() {
+        let mut emulator = CpuEmulator::default();
+        emulator.set("RAM[24576]", -1).unwrap();
+        emulator.set("D", 42).unwrap();
+        assert_eq!(emulator.get("RAM[24576]").unwrap(), Value::Number(-1));
+        assert_eq!(emulator.get("D").unwrap(), Value::Number(42));
+        assert!(emulator.get("RAM[32768]").is_err());
+        assert!(emulator.get("RAM[x]").is_err());
+        assert!(emulator.get("M").is_err());
+    }
+}
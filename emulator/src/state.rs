@@ -0,0 +1,158 @@
+use crate::cpu::{Cpu, MEMORY_SIZE};
+use crate::keyboard::Keyboard;
+
+/// The first bytes of a state file.
+pub const MAGIC: &[u8; 8] = b"HACKSTAT";
+
+/// The version of the format `save` writes. `restore` reads every version
+/// up to this one.
+pub const VERSION: u16 = 1;
+
+/// The whole state of an emulated computer, in a little-endian binary
+/// format: the magic bytes and the version, then A, D, PC, the cycle count,
+/// ROM and RAM without their trailing zeros, and the keyboard events with
+/// how many were fed already.
+pub fn save(cpu: &Cpu, keyboard: &Keyboard) -> Vec<u8> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend(VERSION.to_le_bytes());
+    for register in [cpu.a, cpu.d, cpu.pc] {
+        bytes.extend(register.to_le_bytes());
+    }
+    bytes.extend(cpu.time.to_le_bytes());
+    for memory in [&cpu.rom, &cpu.ram] {
+        let len = memory
+            .iter()
+            .rposition(|&word| word != 0)
+            .map_or(0, |i| i + 1);
+        bytes.extend((len as u32).to_le_bytes());
+        for word in &memory[..len] {
+            bytes.extend(word.to_le_bytes());
+        }
+    }
+    let (events, next) = keyboard.queue();
+    bytes.extend((events.len() as u32).to_le_bytes());
+    for &(cycle, code) in events {
+        bytes.extend(cycle.to_le_bytes());
+        bytes.extend(code.to_le_bytes());
+    }
+    bytes.extend((next as u32).to_le_bytes());
+    bytes
+}
+
+/// Reads back a state written by `save`.
+pub fn restore(bytes: &[u8]) -> Result<(Cpu, Keyboard), String> {
+    let mut reader = Reader { bytes };
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err("not an emulator state".to_string());
+    }
+    let version = reader.u16()?;
+    if version == 0 || version > VERSION {
+        return Err(format!("unsupported state version {}", version));
+    }
+    let mut cpu = Cpu::new(&[]);
+    cpu.a = reader.u16()?;
+    cpu.d = reader.u16()?;
+    cpu.pc = reader.u16()?;
+    cpu.time = reader.u64()?;
+    for memory in [&mut cpu.rom, &mut cpu.ram] {
+        let len = reader.u32()? as usize;
+        if len > MEMORY_SIZE {
+            return Err("memory larger than 32K words".to_string());
+        }
+        for word in &mut memory[..len] {
+            *word = reader.u16()?;
+        }
+    }
+    let count = reader.u32()?;
+    let mut events = vec![];
+    for _ in 0..count {
+        events.push((reader.u64()?, reader.u16()?));
+    }
+    let next = reader.u32()? as usize;
+    if next > events.len() {
+        return Err("invalid keyboard events".to_string());
+    }
+    if !reader.bytes.is_empty() {
+        return Err("unexpected bytes after the emulator state".to_string());
+    }
+    Ok((cpu, Keyboard::from_queue(events, next)))
+}
+
+struct Reader<'b> {
+    bytes: &'b [u8],
+}
+
+impl<'b> Reader<'b> {
+    fn take(&mut self, n: usize) -> Result<&'b [u8], String> {
+        if self.bytes.len() < n {
+            return Err("truncated emulator state".to_string());
+        }
+        let (taken, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::KBD;
+    use assembler::program::Program;
+
+    #[test]
+    fn test_save_and_restore() {
+        let source = "(LOOP)\n@KBD\nD=M\n@i\nM=D+M\n@LOOP\n0;JMP\n";
+        let program = Program::assemble(source).unwrap();
+        let mut cpu = Cpu::new(&program.words);
+        let mut keyboard = Keyboard::parse("5 press 1\n20 release\n30 press 2\n").unwrap();
+        let run = |cpu: &mut Cpu, keyboard: &mut Keyboard, cycles| {
+            for _ in 0..cycles {
+                keyboard.update(cpu);
+                cpu.step();
+            }
+        };
+        run(&mut cpu, &mut keyboard, 25);
+        let bytes = save(&cpu, &keyboard);
+        assert_eq!(&bytes[..10], b"HACKSTAT\x01\x00");
+
+        let (mut restored, mut restored_keyboard) = restore(&bytes).unwrap();
+        assert_eq!(
+            (restored.a, restored.d, restored.pc, restored.time),
+            (cpu.a, cpu.d, cpu.pc, cpu.time)
+        );
+        assert_eq!(restored.rom, cpu.rom);
+        assert_eq!(restored.ram, cpu.ram);
+        run(&mut cpu, &mut keyboard, 20);
+        run(&mut restored, &mut restored_keyboard, 20);
+        assert_eq!(restored.ram[KBD], 2);
+        assert_eq!(restored.ram, cpu.ram);
+        assert!(restored_keyboard.is_done());
+    }
+
+    #[test]
+    fn test_invalid() {
+        let bytes = save(&Cpu::new(&[1, 2, 3]), &Keyboard::default());
+        assert!(restore(&bytes).is_ok());
+        let error = |bytes: &[u8]| restore(bytes).err().unwrap();
+        assert_eq!(error(b"P4\n512 256\n"), "not an emulator state");
+        assert_eq!(error(&bytes[..bytes.len() - 1]), "truncated emulator state");
+        let mut newer = bytes.clone();
+        newer[8] = 2;
+        assert_eq!(error(&newer), "unsupported state version 2");
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert_eq!(error(&longer), "unexpected bytes after the emulator state");
+    }
+}
@@ -0,0 +1,165 @@
+use crate::cpu::{Cpu, MEMORY_SIZE};
+use crate::profile::functions;
+use assembler::disassembler::disassemble;
+use assembler::program::Program;
+use std::ops::Range;
+
+/// Describes each instruction executed in the traced parts of the ROM.
+#[derive(Default)]
+pub struct Tracer {
+    /// The addresses to trace, or all of them if empty.
+    ranges: Vec<Range<usize>>,
+}
+
+impl Tracer {
+    /// Traces `start..end` of the ROM, or the code of a label up to the next
+    /// one, or of a VM function up to the next function.
+    pub fn only(&mut self, program: &Program, spec: &str) -> Result<(), String> {
+        let range = match spec.split_once("..") {
+            Some((start, end)) => {
+                let address = |n: &str| {
+                    n.parse::<usize>()
+                        .map_err(|_| format!("invalid address range `{}`", spec))
+                };
+                address(start)?..address(end)?
+            }
+            None => symbol_range(program, spec)?,
+        };
+        self.ranges.push(range);
+        Ok(())
+    }
+
+    fn traces(&self, pc: u16) -> bool {
+        let pc = pc as usize;
+        self.ranges.is_empty() || self.ranges.iter().any(|range| range.contains(&pc))
+    }
+
+    /// Executes the instruction at PC with `step`, returning its cycle, PC,
+    /// the instruction, A and D after it, and the RAM word it wrote, if the
+    /// instruction is traced.
+    pub fn step(&self, cpu: &mut Cpu, step: impl FnOnce(&mut Cpu)) -> Option<String> {
+        if !self.traces(cpu.pc) {
+            step(cpu);
+            return None;
+        }
+        let (time, pc) = (cpu.time, cpu.pc);
+        let word = cpu.rom[pc as usize % MEMORY_SIZE];
+        let address = cpu.a as usize % MEMORY_SIZE;
+        let old = cpu.ram[address];
+        step(cpu);
+        let instruction = disassemble(word).unwrap_or_else(|| format!("{:016b}", word));
+        let write = if word & 0x8008 == 0x8008 {
+            let (old, new) = (old as i16, cpu.ram[address] as i16);
+            format!("RAM[{}]: {} -> {}", address, old, new)
+        } else {
+            String::new()
+        };
+        let line = format!(
+            "{:>9} {:>5}  {:<12} A={:<6} D={:<6} {}",
+            time, pc, instruction, cpu.a as i16, cpu.d as i16, write
+        );
+        Some(line.trim_end().to_string())
+    }
+}
+
+fn symbol_range(program: &Program, symbol: &str) -> Result<Range<usize>, String> {
+    let start = program
+        .labels
+        .iter()
+        .find(|(label, _)| label == symbol)
+        .map(|&(_, address)| address)
+        .ok_or_else(|| format!("unknown label `{}`", symbol))?;
+    let functions = functions(program);
+    let ends: Vec<u16> = if functions.iter().any(|&(entry, _)| entry == start) {
+        functions.iter().map(|&(entry, _)| entry).collect()
+    } else {
+        program.labels.iter().map(|&(_, address)| address).collect()
+    };
+    let end = ends.into_iter().filter(|&end| end > start).min();
+    let end = end.map_or(program.words.len(), |end| end as usize);
+    Ok(start as usize..end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str =
+        "@3\nD=A\n@i\nM=D\n(LOOP)\n@i\nM=M-1\nD=M\n@LOOP\nD;JGT\n(END)\n@END\n0;JMP\n";
+
+    fn trace(tracer: &Tracer, cycles: usize) -> Vec<String> {
+        let program = Program::assemble(SOURCE).unwrap();
+        let mut cpu = Cpu::new(&program.words);
+        (0..cycles)
+            .filter_map(|_| tracer.step(&mut cpu, Cpu::step))
+            .collect()
+    }
+
+    #[test]
+    fn test_trace() {
+        let lines = trace(&Tracer::default(), 7);
+        assert_eq!(
+            lines,
+            [
+                "        0     0  @3           A=3      D=0",
+                "        1     1  D=A          A=3      D=3",
+                "        2     2  @16          A=16     D=3",
+                "        3     3  M=D          A=16     D=3      RAM[16]: 0 -> 3",
+                "        4     4  @16          A=16     D=3",
+                "        5     5  M=M-1        A=16     D=3      RAM[16]: 3 -> 2",
+                "        6     6  D=M          A=16     D=2",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_only() {
+        let program = Program::assemble(SOURCE).unwrap();
+        let mut tracer = Tracer::default();
+        tracer.only(&program, "LOOP").unwrap();
+        let lines = trace(&tracer, 30);
+        // Three times around the loop.
+        assert_eq!(lines.len(), 15);
+        assert!(lines[0].starts_with("        4     4  @16"));
+
+        let mut tracer = Tracer::default();
+        tracer.only(&program, "0..2").unwrap();
+        tracer.only(&program, "END").unwrap();
+        let lines = trace(&tracer, 22);
+        assert_eq!(lines.len(), 5);
+        assert!(lines[3].starts_with("       20    10  0;JMP"));
+        assert!(lines[4].starts_with("       21     9  @9"));
+
+        assert!(tracer.only(&program, "NOWHERE").is_err());
+        assert!(tracer.only(&program, "2..x").is_err());
+    }
+
+    #[test]
+    fn test_function() {
+        use crate::profile::Profiler;
+        use vm_translator::code::{boot, translate};
+        use vm_translator::parser::parse;
+
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../08/FunctionCalls/FibonacciElement");
+        let mut asm = boot();
+        for class in ["Main", "Sys"] {
+            let source = std::fs::read_to_string(dir.join(format!("{}.vm", class))).unwrap();
+            asm += &translate(&parse(&source).unwrap().1, class);
+        }
+        let program = Program::assemble(&asm).unwrap();
+        let mut tracer = Tracer::default();
+        tracer.only(&program, "Main.fibonacci").unwrap();
+        let mut profiler = Profiler::new(&program);
+        let mut cpu = Cpu::new(&program.words);
+        let mut lines = 0;
+        while !cpu.is_halted() {
+            if tracer.step(&mut cpu, |cpu| profiler.step(cpu)).is_some() {
+                lines += 1;
+            }
+        }
+        // All of the function, past the labels inside it.
+        let (_, own, _) = profiler.by_function().into_iter().next().unwrap();
+        assert_eq!(lines, own);
+    }
+}
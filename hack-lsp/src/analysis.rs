@@ -0,0 +1,367 @@
+use assembler::code::{Generator, PREDEFINED_SYMBOLS};
+use assembler::error::Error;
+use assembler::instruction::Instruction;
+use assembler::parser::Parser;
+use assembler::scanner::Scanner;
+use assembler::token::{Kind, Token};
+use std::collections::HashMap;
+
+/// A range of text within a line. Lines and columns count from 1, just like
+/// the assembler's tokens.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub len: usize,
+}
+
+impl Span {
+    fn of(token: &Token) -> Self {
+        Self {
+            line: token.line,
+            column: token.column,
+            len: token.lexeme.chars().count(),
+        }
+    }
+
+    fn contains(&self, line: usize, column: usize) -> bool {
+        self.line == line && (self.column..=self.column + self.len).contains(&column)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    pub span: Span,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn from_error(error: &Error) -> Self {
+        let (span, message) = match error {
+            Error::Scan {
+                line,
+                column,
+                lexeme,
+                message,
+            } => {
+                let len = lexeme.chars().count();
+                let span = Span {
+                    line: *line,
+                    column: *column,
+                    len,
+                };
+                (span, message.to_string())
+            }
+            Error::Parse { token, message } | Error::Code { token, message } => {
+                (Span::of(token), message.to_string())
+            }
+            Error::Io(e) => {
+                let span = Span {
+                    line: 1,
+                    column: 1,
+                    len: 0,
+                };
+                (span, e.to_string())
+            }
+        };
+        Self {
+            span,
+            severity: Severity::Error,
+            message,
+        }
+    }
+}
+
+/// A symbol as it appears in the source, either in an A-instruction or as
+/// a label definition.
+#[derive(Debug, PartialEq)]
+pub struct Occurrence {
+    pub name: String,
+    pub span: Span,
+    pub is_definition: bool,
+}
+
+/// What the language server knows about a document, rebuilt from scratch on
+/// every change.
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub diagnostics: Vec<Diagnostic>,
+    pub occurrences: Vec<Occurrence>,
+    /// Resolved value of every symbol, including predefined ones.
+    pub symbols: HashMap<String, u16>,
+    /// Encoded word of the instruction on each line.
+    pub words: HashMap<usize, u16>,
+}
+
+impl Analysis {
+    pub fn new(source: &str) -> Self {
+        // Until the document assembles, completion still offers these and
+        // the labels found among the tokens.
+        let symbols = PREDEFINED_SYMBOLS
+            .iter()
+            .map(|(name, value)| (name.to_string(), *value))
+            .collect();
+        let mut analysis = Self {
+            symbols,
+            ..Self::default()
+        };
+        let mut scanner = Scanner::new(source);
+        let tokens = match scanner.scan_tokens() {
+            Ok(tokens) => tokens,
+            Err(e) => return analysis.with_error(&e),
+        };
+        // Occurrences come straight from the tokens so that navigation keeps
+        // working while the rest of the document doesn't parse.
+        analysis.find_occurrences(tokens);
+        let mut parser = Parser::new(tokens);
+        let instructions = match parser.parse() {
+            Ok(instructions) => instructions,
+            Err(e) => return analysis.with_error(&e),
+        };
+        let mut generator = Generator::new(&instructions);
+        if let Err(e) = generator.register_labels() {
+            return analysis.with_error(&e);
+        }
+        let words: Vec<u16> = generator.by_ref().collect();
+        let lines = instructions
+            .iter()
+            .filter(|i| !matches!(i, Instruction::Label(_)))
+            .map(|i| i.token().line);
+        analysis.words = lines.zip(words).collect();
+        analysis.symbols = generator
+            .symbols()
+            .iter()
+            .map(|(name, value)| (name.to_string(), *value))
+            .collect();
+        analysis
+    }
+
+    fn with_error(mut self, error: &Error) -> Self {
+        self.diagnostics.push(Diagnostic::from_error(error));
+        self
+    }
+
+    fn find_occurrences(&mut self, tokens: &[Token]) {
+        for pair in tokens.windows(2) {
+            let [previous, token] = pair else {
+                unreachable!("windows should have two tokens");
+            };
+            let is_definition = match (&previous.kind, &token.kind) {
+                (Kind::At, Kind::Identifier(_)) => false,
+                (Kind::LeftParen, Kind::Identifier(_)) => true,
+                _ => continue,
+            };
+            let Kind::Identifier(name) = token.kind else {
+                unreachable!("token should be an identifier");
+            };
+            if is_definition {
+                if let Some(previous) = self.definition(name) {
+                    let message = format!(
+                        "label `{}` is already defined on line {}, this definition replaces it",
+                        name, previous.span.line
+                    );
+                    self.diagnostics.push(Diagnostic {
+                        span: Span::of(token),
+                        severity: Severity::Warning,
+                        message,
+                    });
+                }
+            }
+            self.occurrences.push(Occurrence {
+                name: name.to_string(),
+                span: Span::of(token),
+                is_definition,
+            });
+        }
+    }
+
+    pub fn occurrence_at(&self, line: usize, column: usize) -> Option<&Occurrence> {
+        self.occurrences
+            .iter()
+            .find(|o| o.span.contains(line, column))
+    }
+
+    /// The label definition for `name`. Like the assembler, the last one
+    /// wins if there are several.
+    pub fn definition(&self, name: &str) -> Option<&Occurrence> {
+        self.occurrences
+            .iter()
+            .rfind(|o| o.is_definition && o.name == name)
+    }
+
+    pub fn references<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Occurrence> {
+        self.occurrences.iter().filter(move |o| o.name == name)
+    }
+
+    pub fn hover(&self, line: usize, column: usize) -> Option<String> {
+        let mut sections = vec![];
+        if let Some(occurrence) = self.occurrence_at(line, column) {
+            let name = &occurrence.name;
+            if let Some(value) = self.symbols.get(name) {
+                let kind = match self.kind(name) {
+                    SymbolKind::Label => "label, ROM address",
+                    SymbolKind::Predefined => "predefined symbol, RAM address",
+                    SymbolKind::Variable => "variable, RAM address",
+                };
+                sections.push(format!("`{}`: {} {}", name, kind, value));
+            }
+        }
+        if let Some(word) = self.words.get(&line) {
+            sections.push(format!("Encoded as `{:0>16b}`", word));
+        }
+        if sections.is_empty() {
+            None
+        } else {
+            Some(sections.join("\n\n"))
+        }
+    }
+
+    /// The symbols to offer after `@`, sorted by name, with their values
+    /// when the document assembles.
+    pub fn completions(&self) -> Vec<(&str, SymbolKind, Option<u16>)> {
+        let mut completions: Vec<_> = self
+            .symbols
+            .iter()
+            .map(|(name, value)| (name.as_str(), self.kind(name), Some(*value)))
+            .collect();
+        for occurrence in &self.occurrences {
+            let name = occurrence.name.as_str();
+            if occurrence.is_definition && !self.symbols.contains_key(name) {
+                completions.push((name, SymbolKind::Label, None));
+            }
+        }
+        completions.sort_by_key(|&(name, _, _)| name);
+        completions.dedup_by_key(|&mut (name, _, _)| name);
+        completions
+    }
+
+    fn kind(&self, name: &str) -> SymbolKind {
+        if self.definition(name).is_some() {
+            SymbolKind::Label
+        } else if is_predefined(name) {
+            SymbolKind::Predefined
+        } else {
+            SymbolKind::Variable
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SymbolKind {
+    Label,
+    Predefined,
+    Variable,
+}
+
+pub fn is_predefined(name: &str) -> bool {
+    PREDEFINED_SYMBOLS.iter().any(|(symbol, _)| *symbol == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "\
+@i
+M=1
+(LOOP)
+  @i
+  D=M
+  @LOOP
+  D;JGT
+@SCREEN
+(LOOP)
+";
+
+    #[test]
+    fn test_diagnostics() {
+        let analysis = Analysis::new("@1\nD=Q\n");
+        assert_eq!(
+            analysis.diagnostics,
+            vec![Diagnostic {
+                span: Span {
+                    line: 2,
+                    column: 3,
+                    len: 1
+                },
+                severity: Severity::Error,
+                message: "expect A, D, or M".to_string(),
+            }]
+        );
+        let analysis = Analysis::new("@1\n\n  /");
+        assert_eq!(analysis.diagnostics[0].span.line, 3);
+        assert_eq!(analysis.diagnostics[0].span.column, 3);
+    }
+
+    #[test]
+    fn test_navigation_with_errors() {
+        let analysis = Analysis::new("(LOOP)\n@LOOP\nD=Q\n");
+        assert_eq!(analysis.diagnostics.len(), 1);
+        assert_eq!(analysis.references("LOOP").count(), 2);
+        assert_eq!(analysis.definition("LOOP").unwrap().span.line, 1);
+    }
+
+    #[test]
+    fn test_duplicate_labels() {
+        let analysis = Analysis::new(SOURCE);
+        assert_eq!(analysis.diagnostics.len(), 1);
+        assert_eq!(analysis.diagnostics[0].severity, Severity::Warning);
+        assert_eq!(analysis.diagnostics[0].span.line, 9);
+    }
+
+    #[test]
+    fn test_definition_and_references() {
+        let analysis = Analysis::new(SOURCE);
+        let occurrence = analysis.occurrence_at(6, 5).unwrap();
+        assert_eq!(occurrence.name, "LOOP");
+        assert_eq!(analysis.definition("LOOP").unwrap().span.line, 9);
+        let lines: Vec<_> = analysis.references("i").map(|o| o.span.line).collect();
+        assert_eq!(lines, [1, 4]);
+    }
+
+    #[test]
+    fn test_hover() {
+        let analysis = Analysis::new(SOURCE);
+        assert_eq!(
+            analysis.hover(6, 4).unwrap(),
+            "`LOOP`: label, ROM address 7\n\nEncoded as `0000000000000111`"
+        );
+        assert_eq!(
+            analysis.hover(1, 2).unwrap(),
+            "`i`: variable, RAM address 16\n\nEncoded as `0000000000010000`"
+        );
+        assert_eq!(
+            analysis.hover(8, 3).unwrap(),
+            "`SCREEN`: predefined symbol, RAM address 16384\n\nEncoded as `0100000000000000`"
+        );
+        assert_eq!(
+            analysis.hover(7, 3).unwrap(),
+            "Encoded as `1110001100000001`"
+        );
+        assert!(analysis.hover(3, 1).is_none());
+    }
+
+    #[test]
+    fn test_completions() {
+        let analysis = Analysis::new(SOURCE);
+        let completions = analysis.completions();
+        assert!(completions.contains(&("LOOP", SymbolKind::Label, Some(7))));
+        assert!(completions.contains(&("i", SymbolKind::Variable, Some(16))));
+        assert!(completions.contains(&("KBD", SymbolKind::Predefined, Some(24576))));
+
+        // What completion is triggered by doesn't parse.
+        let analysis = Analysis::new("(LOOP)\n@LOOP\n0;JMP\n@");
+        assert_eq!(analysis.diagnostics.len(), 1);
+        let completions = analysis.completions();
+        assert!(completions.contains(&("LOOP", SymbolKind::Label, None)));
+        assert!(completions.contains(&("SCREEN", SymbolKind::Predefined, Some(16384))));
+        assert_eq!(completions.len(), PREDEFINED_SYMBOLS.len() + 1);
+    }
+}
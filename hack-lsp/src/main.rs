@@ -0,0 +1,257 @@
+mod analysis;
+
+use analysis::{Analysis, Severity, Span, SymbolKind};
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as NotificationTrait, PublishDiagnostics,
+};
+use lsp_types::request::{
+    Completion, GotoDefinition, HoverRequest, References, Request as RequestTrait,
+};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams, Diagnostic,
+    DiagnosticSeverity, GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents,
+    HoverParams, HoverProviderCapability, Location, MarkupContent, MarkupKind, OneOf, Position,
+    PublishDiagnosticsParams, Range, ReferenceParams, ServerCapabilities,
+    TextDocumentPositionParams, TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+use serde_json::Value;
+use std::collections::HashMap;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync + 'static>>;
+
+fn main() -> Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = serde_json::to_value(ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        definition_provider: Some(OneOf::Left(true)),
+        references_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec!["@".to_string()]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    })?;
+    connection.initialize(capabilities)?;
+    let mut server = Server::default();
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    break;
+                }
+                let response = server.handle_request(request);
+                connection.sender.send(Message::Response(response))?;
+            }
+            Message::Notification(notification) => {
+                if let Some(diagnostics) = server.handle_notification(notification)? {
+                    connection.sender.send(Message::Notification(diagnostics))?;
+                }
+            }
+            Message::Response(_) => {}
+        }
+    }
+    // The writer thread only stops once every sender is gone.
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
+
+#[derive(Default)]
+struct Server {
+    documents: HashMap<Url, Analysis>,
+}
+
+impl Server {
+    fn handle_request(&self, request: Request) -> Response {
+        let id = request.id.clone();
+        let result = match request.method.as_str() {
+            GotoDefinition::METHOD => {
+                parse_params::<GotoDefinition>(request).map(|p| self.definition(p))
+            }
+            References::METHOD => parse_params::<References>(request).map(|p| self.references(p)),
+            HoverRequest::METHOD => parse_params::<HoverRequest>(request).map(|p| self.hover(p)),
+            Completion::METHOD => parse_params::<Completion>(request).map(|p| self.completion(p)),
+            method => {
+                let message = format!("unsupported request {}", method);
+                return Response::new_err(id, ErrorCode::MethodNotFound as i32, message);
+            }
+        };
+        match result {
+            Ok(value) => Response::new_ok(id, value),
+            Err(e) => Response::new_err(id, ErrorCode::InvalidParams as i32, e.to_string()),
+        }
+    }
+
+    /// Updates the documents and returns the diagnostics to publish, if any.
+    fn handle_notification(&mut self, notification: Notification) -> Result<Option<Notification>> {
+        let uri = match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: <DidOpenTextDocument as NotificationTrait>::Params =
+                    serde_json::from_value(notification.params)?;
+                let analysis = Analysis::new(&params.text_document.text);
+                self.documents
+                    .insert(params.text_document.uri.clone(), analysis);
+                params.text_document.uri
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: <DidChangeTextDocument as NotificationTrait>::Params =
+                    serde_json::from_value(notification.params)?;
+                // Changes always carry the full text since that's the only
+                // sync kind we advertise.
+                let Some(change) = params.content_changes.last() else {
+                    return Ok(None);
+                };
+                let analysis = Analysis::new(&change.text);
+                self.documents
+                    .insert(params.text_document.uri.clone(), analysis);
+                params.text_document.uri
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: <DidCloseTextDocument as NotificationTrait>::Params =
+                    serde_json::from_value(notification.params)?;
+                self.documents.remove(&params.text_document.uri);
+                params.text_document.uri
+            }
+            _ => return Ok(None),
+        };
+        let diagnostics = self
+            .documents
+            .get(&uri)
+            .map(|analysis| analysis.diagnostics.iter().map(to_lsp_diagnostic).collect())
+            .unwrap_or_default();
+        let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+        Ok(Some(Notification::new(
+            PublishDiagnostics::METHOD.to_string(),
+            params,
+        )))
+    }
+
+    fn definition(&self, params: GotoDefinitionParams) -> Value {
+        let TextDocumentPositionParams {
+            text_document,
+            position,
+        } = params.text_document_position_params;
+        let location = self.document_occurrence(&text_document.uri, position, |analysis, name| {
+            analysis.definition(name).map(|definition| {
+                Location::new(text_document.uri.clone(), to_range(definition.span))
+            })
+        });
+        let response = location.flatten().map(GotoDefinitionResponse::Scalar);
+        serde_json::to_value(response).expect("response should serialize")
+    }
+
+    fn references(&self, params: ReferenceParams) -> Value {
+        let TextDocumentPositionParams {
+            text_document,
+            position,
+        } = params.text_document_position;
+        let include_declaration = params.context.include_declaration;
+        let locations = self.document_occurrence(&text_document.uri, position, |analysis, name| {
+            analysis
+                .references(name)
+                .filter(|o| include_declaration || !o.is_definition)
+                .map(|o| Location::new(text_document.uri.clone(), to_range(o.span)))
+                .collect::<Vec<_>>()
+        });
+        serde_json::to_value(locations).expect("response should serialize")
+    }
+
+    fn hover(&self, params: HoverParams) -> Value {
+        let TextDocumentPositionParams {
+            text_document,
+            position,
+        } = params.text_document_position_params;
+        let (line, column) = from_position(position);
+        let hover = self
+            .documents
+            .get(&text_document.uri)
+            .and_then(|analysis| analysis.hover(line, column))
+            .map(|value| Hover {
+                contents: HoverContents::Markup(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value,
+                }),
+                range: None,
+            });
+        serde_json::to_value(hover).expect("response should serialize")
+    }
+
+    fn completion(&self, params: CompletionParams) -> Value {
+        let uri = params.text_document_position.text_document.uri;
+        let items: Vec<CompletionItem> = self
+            .documents
+            .get(&uri)
+            .map(|analysis| analysis.completions())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(name, kind, value)| {
+                let (kind, detail, memory) = match kind {
+                    SymbolKind::Label => (CompletionItemKind::FUNCTION, "label", "ROM"),
+                    SymbolKind::Predefined => (CompletionItemKind::CONSTANT, "predefined", "RAM"),
+                    SymbolKind::Variable => (CompletionItemKind::VARIABLE, "variable", "RAM"),
+                };
+                // Labels have no address while the document doesn't assemble.
+                let detail = match value {
+                    Some(value) => format!("{}, {} {}", detail, memory, value),
+                    None => detail.to_string(),
+                };
+                CompletionItem {
+                    label: name.to_string(),
+                    kind: Some(kind),
+                    detail: Some(detail),
+                    ..Default::default()
+                }
+            })
+            .collect();
+        serde_json::to_value(items).expect("response should serialize")
+    }
+
+    /// Runs `f` with the name of the symbol under the cursor, if there's one.
+    fn document_occurrence<T>(
+        &self,
+        uri: &Url,
+        position: Position,
+        f: impl FnOnce(&Analysis, &str) -> T,
+    ) -> Option<T> {
+        let analysis = self.documents.get(uri)?;
+        let (line, column) = from_position(position);
+        let occurrence = analysis.occurrence_at(line, column)?;
+        Some(f(analysis, &occurrence.name))
+    }
+}
+
+fn parse_params<R: RequestTrait>(request: Request) -> serde_json::Result<R::Params> {
+    serde_json::from_value(request.params)
+}
+
+fn to_lsp_diagnostic(diagnostic: &analysis::Diagnostic) -> Diagnostic {
+    let severity = match diagnostic.severity {
+        Severity::Error => DiagnosticSeverity::ERROR,
+        Severity::Warning => DiagnosticSeverity::WARNING,
+    };
+    Diagnostic {
+        range: to_range(diagnostic.span),
+        severity: Some(severity),
+        source: Some("hack-lsp".to_string()),
+        message: diagnostic.message.clone(),
+        ..Default::default()
+    }
+}
+
+/// Converts the assembler's 1-based lines and columns into LSP's 0-based
+/// positions.
+fn to_range(span: Span) -> Range {
+    let line = span.line as u32 - 1;
+    let start = span.column as u32 - 1;
+    Range::new(
+        Position::new(line, start),
+        Position::new(line, start + span.len as u32),
+    )
+}
+
+fn from_position(position: Position) -> (usize, usize) {
+    (position.line as usize + 1, position.character as usize + 1)
+}
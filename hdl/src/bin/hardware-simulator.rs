@@ -0,0 +1,36 @@
+use hdl::script::HardwareSimulator;
+use std::env::args;
+use std::path::Path;
+use std::{fmt, fs, process};
+use test_script::error::Error;
+use test_script::runner::Runner;
+
+const USAGE: &str = "Usage: hardware-simulator [script.tst]";
+
+fn main() {
+    let args: Vec<String> = args().skip(1).collect();
+    let [path] = args.as_slice() else {
+        eprintln!("{}", USAGE);
+        process::exit(65);
+    };
+    let path = Path::new(path);
+    let script = fs::read_to_string(path).unwrap_or_else(exit_with_error);
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let mut runner = Runner::new(HardwareSimulator::default(), dir);
+    let result = runner.run(&script);
+    // Like the official tools, keep the output up to a failure.
+    runner.write_output_file().unwrap_or_else(exit_with_error);
+    match result {
+        Ok(()) => println!("End of script - Comparison ended successfully"),
+        Err(e @ Error::Comparison { .. }) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        Err(e) => exit_with_error(e),
+    }
+}
+
+fn exit_with_error<V, E: fmt::Display>(e: E) -> V {
+    eprintln!("{}", e);
+    process::exit(65)
+}
@@ -0,0 +1,90 @@
+use assembler::check::parse_hack;
+use cli::{exit_with_usage, take_arg, take_flag};
+use emulator::cpu::Cpu;
+use hdl::computer::{compare, Computer};
+use hdl::library::Library;
+use std::env::args;
+use std::path::{Path, PathBuf};
+use std::{fmt, fs, process};
+
+const USAGE: &str = "\
+Usage: hdl-computer [-n cycles] [-s address=value]... [--compare] program.hack [chip directories...]
+
+Runs the program on Computer.hdl, found in the chip directories or next to
+the program. With --compare, also runs it on the CPU emulator and stops at
+the first cycle where they disagree.";
+
+fn main() {
+    let mut args: Vec<String> = args().skip(1).collect();
+    let cycles = take_arg(&mut args, "-n", USAGE)
+        .map(|n| n.parse().unwrap_or_else(exit_with_error))
+        .unwrap_or(10000);
+    let mut settings: Vec<(usize, i16)> = vec![];
+    while let Some(setting) = take_arg(&mut args, "-s", USAGE) {
+        let parsed = setting
+            .split_once('=')
+            .and_then(|(address, value)| Some((address.parse().ok()?, value.parse().ok()?)));
+        let Some((address, value)) = parsed else {
+            exit_with_usage(USAGE)
+        };
+        settings.push((address, value));
+    }
+    let check = take_flag(&mut args, "--compare");
+    let Some((path, dirs)) = args.split_first() else {
+        exit_with_usage(USAGE)
+    };
+    let path = Path::new(path);
+    let source = fs::read_to_string(path).unwrap_or_else(exit_with_error);
+    let program = parse_hack(&source).unwrap_or_else(exit_with_error);
+    let dirs: Vec<PathBuf> = match dirs {
+        [] => vec![path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .to_path_buf()],
+        dirs => dirs.iter().map(PathBuf::from).collect(),
+    };
+
+    let mut library = Library::new(dirs);
+    let mut computer = Computer::new(&mut library, &program).unwrap_or_else(exit_with_error);
+    let mut cpu = Cpu::new(&program);
+    for &(address, value) in &settings {
+        let value = value as u16;
+        computer
+            .simulator
+            .set_state("RAM16K", Some(address), value)
+            .unwrap_or_else(exit_with_error);
+        cpu.ram[address] = value;
+    }
+    if check {
+        if computer.registers().is_none() {
+            eprintln!(
+                "Note: A and D aren't compared, since the CPU doesn't keep them in \
+                 ARegister and DRegister"
+            );
+        }
+        if let Some(divergence) = compare(&mut computer, &mut cpu, cycles) {
+            eprintln!("{}", divergence);
+            process::exit(1);
+        }
+        println!("No divergence in {} cycles", cycles);
+    } else {
+        for _ in 0..cycles {
+            computer.step();
+        }
+    }
+    print!("PC={}", computer.pc());
+    if let Some((a, d)) = computer.registers() {
+        print!(" A={} D={}", a as i16, d as i16);
+    }
+    println!();
+    for address in 0..16 {
+        if let Ok(value) = computer.simulator.state("RAM16K", Some(address)) {
+            println!("RAM[{}]={}", address, value as i16);
+        }
+    }
+}
+
+fn exit_with_error<V, E: fmt::Display>(e: E) -> V {
+    eprintln!("{}", e);
+    process::exit(65)
+}
@@ -0,0 +1,69 @@
+use hdl::library::Library;
+use hdl::lint::lint;
+use hdl::parser::parse;
+use std::env::args;
+use std::path::{Path, PathBuf};
+use std::{fmt, fs, process};
+
+const USAGE: &str = "Usage: hdl-lint [file.hdl or directory]...";
+
+fn main() {
+    let args: Vec<String> = args().skip(1).collect();
+    if args.is_empty() {
+        eprintln!("{}", USAGE);
+        process::exit(65);
+    }
+    let mut paths = vec![];
+    for arg in &args {
+        let path = PathBuf::from(arg);
+        if path.is_dir() {
+            paths.extend(hdl_files(&path).unwrap_or_else(exit_with_error));
+        } else {
+            paths.push(path);
+        }
+    }
+    let mut clean = true;
+    for path in paths {
+        clean &= lint_file(&path);
+    }
+    if !clean {
+        process::exit(1);
+    }
+}
+
+/// Prints what's wrong with the chip in `path`, if anything. Its parts are
+/// looked up next to it, then among the built-in chips.
+fn lint_file(path: &Path) -> bool {
+    let source = fs::read_to_string(path).unwrap_or_else(exit_with_error);
+    let chip = match parse(&source) {
+        Ok(chip) => chip,
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            return false;
+        }
+    };
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let mut library = Library::new(vec![dir.to_path_buf()]);
+    let findings = lint(&mut library, &chip);
+    for finding in &findings {
+        println!("{}: {}", path.display(), finding);
+    }
+    findings.is_empty()
+}
+
+fn hdl_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut paths = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "hdl") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+fn exit_with_error<V, E: fmt::Display>(e: E) -> V {
+    eprintln!("{}", e);
+    process::exit(65)
+}
@@ -0,0 +1,73 @@
+use hdl::library::Library;
+use hdl::stats::{Analyzer, Stats};
+use std::env::args;
+use std::path::{Path, PathBuf};
+use std::{fmt, process};
+
+const USAGE: &str = "Usage: hdl-stats chip.hdl [more chip directories...]";
+
+fn main() {
+    let args: Vec<String> = args().skip(1).collect();
+    let Some((path, extra_dirs)) = args.split_first() else {
+        eprintln!("{}", USAGE);
+        process::exit(65);
+    };
+    let path = Path::new(path);
+    let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+        eprintln!("{}", USAGE);
+        process::exit(65);
+    };
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let dirs = [dir.to_path_buf()]
+        .into_iter()
+        .chain(extra_dirs.iter().map(PathBuf::from))
+        .collect();
+    let mut library = Library::new(dirs);
+    let chip = library
+        .chip(name)
+        .unwrap_or_else(exit_with_error)
+        .unwrap_or_else(|| exit_with_error(format!("unknown chip `{}`", name)));
+    let mut analyzer = Analyzer::new(&mut library);
+    let stats = analyzer.stats(name).unwrap_or_else(exit_with_error);
+    println!(
+        "{}: {} Nand, critical path {}{}",
+        name,
+        stats.nands,
+        stats.depth,
+        builtins(&stats, ", plus ")
+    );
+    if chip.parts().is_empty() {
+        return;
+    }
+    println!();
+    println!("{:>5}  {:<12} {:>7} {:>5}", "Line", "Part", "Nand", "Path");
+    for part in chip.parts() {
+        let stats = analyzer.stats(&part.chip).unwrap_or_else(exit_with_error);
+        println!(
+            "{:>5}  {:<12} {:>7} {:>5}{}",
+            part.position.line,
+            part.chip,
+            stats.nands,
+            stats.depth,
+            builtins(&stats, "  ")
+        );
+    }
+}
+
+/// Lists the built-in chips the counts leave out, like `16 DFF`.
+fn builtins(stats: &Stats, prefix: &str) -> String {
+    if stats.builtins.is_empty() {
+        return String::new();
+    }
+    let counts: Vec<String> = stats
+        .builtins
+        .iter()
+        .map(|(name, count)| format!("{} {}", count, name))
+        .collect();
+    format!("{}{}", prefix, counts.join(", "))
+}
+
+fn exit_with_error<V, E: fmt::Display>(e: E) -> V {
+    eprintln!("{}", e);
+    process::exit(65)
+}
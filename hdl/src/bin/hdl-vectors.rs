@@ -0,0 +1,66 @@
+use cli::{take_arg, take_flag};
+use hdl::library::Library;
+use hdl::netlist::Netlist;
+use hdl::parser::parse;
+use hdl::vectors::{builtin, generate, simulated};
+use std::env::args;
+use std::path::Path;
+use std::{fmt, fs, process};
+
+const USAGE: &str = "Usage: hdl-vectors [-n samples] [-r reference.hdl] [--force] chip.hdl
+
+Writes chip.tst and chip.cmp next to chip.hdl, with the outputs of the
+known-good reference chip, or of the built-in chip of the same name. Existing
+files are only replaced with --force.";
+
+fn main() {
+    let mut args: Vec<String> = args().skip(1).collect();
+    let samples = take_arg(&mut args, "-n", USAGE)
+        .map(|n| n.parse().unwrap_or_else(exit_with_error))
+        .unwrap_or(32);
+    let reference = take_arg(&mut args, "-r", USAGE);
+    let force = take_flag(&mut args, "--force");
+    let [path] = args.as_slice() else {
+        eprintln!("{}", USAGE);
+        process::exit(65);
+    };
+    let path = Path::new(path);
+    let out_paths = [path.with_extension("tst"), path.with_extension("cmp")];
+    // Projects come with their own test files, which shouldn't be lost.
+    let existing = out_paths.iter().find(|p| p.exists());
+    if let (Some(existing), false) = (existing, force) {
+        let message = format!(
+            "{} already exists, use --force to replace it",
+            existing.display()
+        );
+        exit_with_error(message)
+    }
+    let source = fs::read_to_string(path).unwrap_or_else(exit_with_error);
+    let chip = parse(&source).unwrap_or_else(exit_with_error);
+    let reference = match reference {
+        Some(reference) => {
+            let reference = Path::new(&reference);
+            let name = reference.file_stem().and_then(|stem| stem.to_str());
+            let Some(name) = name else {
+                eprintln!("{}", USAGE);
+                process::exit(65);
+            };
+            let dir = reference.parent().unwrap_or_else(|| Path::new("."));
+            let mut library = Library::new(vec![dir.to_path_buf()]);
+            let netlist = Netlist::build(&mut library, name).unwrap_or_else(exit_with_error);
+            simulated(&chip, netlist)
+        }
+        None => builtin(&chip),
+    }
+    .unwrap_or_else(exit_with_error);
+    let vectors = generate(&chip, samples, reference);
+    for (out_path, contents) in out_paths.iter().zip([&vectors.tst, &vectors.cmp]) {
+        fs::write(out_path, contents).unwrap_or_else(exit_with_error);
+        eprintln!("Successfully wrote {}", out_path.to_string_lossy());
+    }
+}
+
+fn exit_with_error<V, E: fmt::Display>(e: E) -> V {
+    eprintln!("{}", e);
+    process::exit(65)
+}
@@ -0,0 +1,37 @@
+use cli::{take_output_arg, write_output};
+use hdl::library::Library;
+use hdl::verilog::export;
+use std::env::args;
+use std::path::{Path, PathBuf};
+use std::{fmt, process};
+
+const USAGE: &str = "Usage: hdl-verilog [-o output] chip.hdl [more chip directories...]";
+
+fn main() {
+    let mut args: Vec<String> = args().skip(1).collect();
+    let out_path = take_output_arg(&mut args, USAGE);
+    let Some((path, extra_dirs)) = args.split_first() else {
+        eprintln!("{}", USAGE);
+        process::exit(65);
+    };
+    let path = Path::new(path);
+    let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+        eprintln!("{}", USAGE);
+        process::exit(65);
+    };
+    // Parts come from the chip's own directory first, like in the simulator.
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let dirs = [dir.to_path_buf()]
+        .into_iter()
+        .chain(extra_dirs.iter().map(PathBuf::from))
+        .collect();
+    let mut library = Library::new(dirs);
+    let verilog = export(&mut library, name).unwrap_or_else(exit_with_error);
+    let out_path = out_path.unwrap_or_else(|| path.with_extension("v"));
+    write_output(&out_path, &verilog).unwrap_or_else(exit_with_error);
+}
+
+fn exit_with_error<V, E: fmt::Display>(e: E) -> V {
+    eprintln!("{}", e);
+    process::exit(65)
+}
@@ -0,0 +1,388 @@
+/// Chips the simulator implements natively, like the official built-in
+/// chips. Their pins are declared in HDL, and pin values are passed in the
+/// order of those declarations. Clocked chips keep their state in a slice
+/// of words owned by the simulator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Nand,
+    Not,
+    And,
+    Or,
+    Xor,
+    Mux,
+    DMux,
+    Not16,
+    And16,
+    Or16,
+    Mux16,
+    Mux4Way16,
+    Mux8Way16,
+    DMux4Way,
+    DMux8Way,
+    Or8Way,
+    HalfAdder,
+    FullAdder,
+    Add16,
+    Inc16,
+    ALU,
+    DFF,
+    Bit,
+    Register,
+    ARegister,
+    DRegister,
+    PC,
+    RAM8,
+    RAM64,
+    RAM512,
+    RAM4K,
+    RAM16K,
+    ROM32K,
+    Screen,
+    Keyboard,
+}
+
+impl Kind {
+    #[rustfmt::skip]
+    pub const ALL: &'static [Self] = &[
+        Self::Nand, Self::Not, Self::And, Self::Or, Self::Xor, Self::Mux, Self::DMux,
+        Self::Not16, Self::And16, Self::Or16, Self::Mux16, Self::Mux4Way16, Self::Mux8Way16,
+        Self::DMux4Way, Self::DMux8Way, Self::Or8Way,
+        Self::HalfAdder, Self::FullAdder, Self::Add16, Self::Inc16, Self::ALU,
+        Self::DFF, Self::Bit, Self::Register, Self::ARegister, Self::DRegister, Self::PC,
+        Self::RAM8, Self::RAM64, Self::RAM512, Self::RAM4K, Self::RAM16K,
+        Self::ROM32K, Self::Screen, Self::Keyboard,
+    ];
+
+    /// The built-in chip named in a `BUILTIN` statement.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|kind| format!("{:?}", kind) == name)
+    }
+
+    pub fn interface(self) -> &'static str {
+        use Kind::*;
+        match self {
+            Nand => "CHIP Nand { IN a, b; OUT out; BUILTIN Nand; }",
+            Not => "CHIP Not { IN in; OUT out; BUILTIN Not; }",
+            And => "CHIP And { IN a, b; OUT out; BUILTIN And; }",
+            Or => "CHIP Or { IN a, b; OUT out; BUILTIN Or; }",
+            Xor => "CHIP Xor { IN a, b; OUT out; BUILTIN Xor; }",
+            Mux => "CHIP Mux { IN a, b, sel; OUT out; BUILTIN Mux; }",
+            DMux => "CHIP DMux { IN in, sel; OUT a, b; BUILTIN DMux; }",
+            Not16 => "CHIP Not16 { IN in[16]; OUT out[16]; BUILTIN Not16; }",
+            And16 => "CHIP And16 { IN a[16], b[16]; OUT out[16]; BUILTIN And16; }",
+            Or16 => "CHIP Or16 { IN a[16], b[16]; OUT out[16]; BUILTIN Or16; }",
+            Mux16 => "CHIP Mux16 { IN a[16], b[16], sel; OUT out[16]; BUILTIN Mux16; }",
+            Mux4Way16 => {
+                "CHIP Mux4Way16 {
+                    IN a[16], b[16], c[16], d[16], sel[2]; OUT out[16]; BUILTIN Mux4Way16;
+                }"
+            }
+            Mux8Way16 => {
+                "CHIP Mux8Way16 {
+                    IN a[16], b[16], c[16], d[16], e[16], f[16], g[16], h[16], sel[3];
+                    OUT out[16];
+                    BUILTIN Mux8Way16;
+                }"
+            }
+            DMux4Way => "CHIP DMux4Way { IN in, sel[2]; OUT a, b, c, d; BUILTIN DMux4Way; }",
+            DMux8Way => {
+                "CHIP DMux8Way { IN in, sel[3]; OUT a, b, c, d, e, f, g, h; BUILTIN DMux8Way; }"
+            }
+            Or8Way => "CHIP Or8Way { IN in[8]; OUT out; BUILTIN Or8Way; }",
+            HalfAdder => "CHIP HalfAdder { IN a, b; OUT sum, carry; BUILTIN HalfAdder; }",
+            FullAdder => "CHIP FullAdder { IN a, b, c; OUT sum, carry; BUILTIN FullAdder; }",
+            Add16 => "CHIP Add16 { IN a[16], b[16]; OUT out[16]; BUILTIN Add16; }",
+            Inc16 => "CHIP Inc16 { IN in[16]; OUT out[16]; BUILTIN Inc16; }",
+            ALU => {
+                "CHIP ALU {
+                    IN x[16], y[16], zx, nx, zy, ny, f, no;
+                    OUT out[16], zr, ng;
+                    BUILTIN ALU;
+                }"
+            }
+            DFF => "CHIP DFF { IN in; OUT out; BUILTIN DFF; CLOCKED in; }",
+            Bit => "CHIP Bit { IN in, load; OUT out; BUILTIN Bit; CLOCKED in, load; }",
+            Register => {
+                "CHIP Register { IN in[16], load; OUT out[16]; BUILTIN Register; CLOCKED in, load; }"
+            }
+            ARegister => {
+                "CHIP ARegister {
+                    IN in[16], load; OUT out[16]; BUILTIN ARegister; CLOCKED in, load;
+                }"
+            }
+            DRegister => {
+                "CHIP DRegister {
+                    IN in[16], load; OUT out[16]; BUILTIN DRegister; CLOCKED in, load;
+                }"
+            }
+            PC => {
+                "CHIP PC {
+                    IN in[16], load, inc, reset;
+                    OUT out[16];
+                    BUILTIN PC;
+                    CLOCKED in, load, inc, reset;
+                }"
+            }
+            RAM8 => {
+                "CHIP RAM8 {
+                    IN in[16], load, address[3]; OUT out[16]; BUILTIN RAM8; CLOCKED in, load;
+                }"
+            }
+            RAM64 => {
+                "CHIP RAM64 {
+                    IN in[16], load, address[6]; OUT out[16]; BUILTIN RAM64; CLOCKED in, load;
+                }"
+            }
+            RAM512 => {
+                "CHIP RAM512 {
+                    IN in[16], load, address[9]; OUT out[16]; BUILTIN RAM512; CLOCKED in, load;
+                }"
+            }
+            RAM4K => {
+                "CHIP RAM4K {
+                    IN in[16], load, address[12]; OUT out[16]; BUILTIN RAM4K; CLOCKED in, load;
+                }"
+            }
+            RAM16K => {
+                "CHIP RAM16K {
+                    IN in[16], load, address[14]; OUT out[16]; BUILTIN RAM16K; CLOCKED in, load;
+                }"
+            }
+            ROM32K => "CHIP ROM32K { IN address[15]; OUT out[16]; BUILTIN ROM32K; }",
+            Screen => {
+                "CHIP Screen {
+                    IN in[16], load, address[13]; OUT out[16]; BUILTIN Screen; CLOCKED in, load;
+                }"
+            }
+            Keyboard => "CHIP Keyboard { OUT out[16]; BUILTIN Keyboard; }",
+        }
+    }
+
+    /// Words of state kept between clock cycles. Registers keep their value
+    /// and the one they'll take on the next tock.
+    pub fn state_size(self) -> usize {
+        use Kind::*;
+        match self {
+            DFF | Bit | Register | ARegister | DRegister | PC => 2,
+            RAM8 => 8,
+            RAM64 => 64,
+            RAM512 => 512,
+            RAM4K => 4096,
+            RAM16K => 16384,
+            ROM32K => 32768,
+            Screen => 8192,
+            Keyboard => 1,
+            _ => 0,
+        }
+    }
+
+    /// Whether an input only matters on the clock edge, and so can't be
+    /// part of a combinational loop.
+    pub fn is_clocked(self, input: usize) -> bool {
+        use Kind::*;
+        match self {
+            DFF | Bit | Register | ARegister | DRegister | PC => true,
+            RAM8 | RAM64 | RAM512 | RAM4K | RAM16K | Screen => input < 2,
+            _ => false,
+        }
+    }
+
+    /// Whether the chip reacts to the clock at all.
+    pub fn is_sequential(self) -> bool {
+        self.state_size() > 0 && !matches!(self, Kind::ROM32K | Kind::Keyboard)
+    }
+
+    /// Samples the inputs on the rising edge of the clock.
+    pub fn tick(self, state: &mut [u16], inputs: &[u16]) {
+        use Kind::*;
+        let bit = |i: usize| inputs[i] & 1 == 1;
+        match self {
+            DFF => state[1] = inputs[0],
+            Bit | Register | ARegister | DRegister => {
+                state[1] = if bit(1) { inputs[0] } else { state[0] };
+            }
+            PC => {
+                state[1] = if bit(3) {
+                    0
+                } else if bit(1) {
+                    inputs[0]
+                } else if bit(2) {
+                    state[0].wrapping_add(1)
+                } else {
+                    state[0]
+                };
+            }
+            RAM8 | RAM64 | RAM512 | RAM4K | RAM16K | Screen if bit(1) => {
+                state[inputs[2] as usize & (state.len() - 1)] = inputs[0];
+            }
+            _ => {}
+        }
+    }
+
+    /// Commits sampled values on the falling edge of the clock.
+    pub fn tock(self, state: &mut [u16]) {
+        use Kind::*;
+        if let DFF | Bit | Register | ARegister | DRegister | PC = self {
+            state[0] = state[1];
+        }
+    }
+
+    /// Reads the state as test scripts do with `Chip[i]`. Registers ignore
+    /// the index and show the value they're about to take.
+    pub fn read_state(self, state: &[u16], index: Option<usize>) -> Option<u16> {
+        match (self.state_size(), index) {
+            (0, _) => None,
+            (2, _) if self.is_register() => Some(state[1]),
+            (_, Some(i)) => state.get(i).copied(),
+            (_, None) => None,
+        }
+    }
+
+    pub fn write_state(self, state: &mut [u16], index: Option<usize>, value: u16) -> bool {
+        if self.is_register() {
+            state.fill(value);
+            return true;
+        }
+        match index.and_then(|i| state.get_mut(i)) {
+            Some(word) => {
+                *word = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn is_register(self) -> bool {
+        use Kind::*;
+        matches!(self, DFF | Bit | Register | ARegister | DRegister | PC)
+    }
+
+    /// Computes the outputs from the inputs and state. Bits beyond a pin's
+    /// width are ignored on both sides.
+    pub fn eval(self, state: &[u16], inputs: &[u16], outputs: &mut [u16]) {
+        use Kind::*;
+        let bit = |i: usize| inputs[i] & 1;
+        match self {
+            Nand => outputs[0] = !(inputs[0] & inputs[1]),
+            Not | Not16 => outputs[0] = !inputs[0],
+            And | And16 => outputs[0] = inputs[0] & inputs[1],
+            Or | Or16 => outputs[0] = inputs[0] | inputs[1],
+            Xor => outputs[0] = inputs[0] ^ inputs[1],
+            Mux | Mux16 => outputs[0] = inputs[bit(2) as usize],
+            Mux4Way16 => outputs[0] = inputs[(inputs[4] & 0b11) as usize],
+            Mux8Way16 => outputs[0] = inputs[(inputs[8] & 0b111) as usize],
+            DMux | DMux4Way | DMux8Way => {
+                let sel = inputs[1] as usize & (outputs.len() - 1);
+                outputs.fill(0);
+                outputs[sel] = bit(0);
+            }
+            Or8Way => outputs[0] = (inputs[0] & 0xFF != 0) as u16,
+            HalfAdder | FullAdder => {
+                let sum = inputs.iter().map(|i| i & 1).sum::<u16>();
+                outputs[0] = sum & 1;
+                outputs[1] = sum >> 1;
+            }
+            Add16 => outputs[0] = inputs[0].wrapping_add(inputs[1]),
+            Inc16 => outputs[0] = inputs[0].wrapping_add(1),
+            ALU => {
+                let (mut x, mut y) = (inputs[0], inputs[1]);
+                if bit(2) == 1 {
+                    x = 0;
+                }
+                if bit(3) == 1 {
+                    x = !x;
+                }
+                if bit(4) == 1 {
+                    y = 0;
+                }
+                if bit(5) == 1 {
+                    y = !y;
+                }
+                let mut out = if bit(6) == 1 {
+                    x.wrapping_add(y)
+                } else {
+                    x & y
+                };
+                if bit(7) == 1 {
+                    out = !out;
+                }
+                outputs[0] = out;
+                outputs[1] = (out == 0) as u16;
+                outputs[2] = out >> 15;
+            }
+            DFF | Bit | Register | ARegister | DRegister | PC | Keyboard => outputs[0] = state[0],
+            RAM8 | RAM64 | RAM512 | RAM4K | RAM16K | Screen => {
+                outputs[0] = state[inputs[2] as usize & (state.len() - 1)];
+            }
+            ROM32K => outputs[0] = state[inputs[0] as usize & 0x7FFF],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    fn eval(kind: Kind, inputs: &[u16], outputs: usize) -> Vec<u16> {
+        let mut out = vec![0; outputs];
+        kind.eval(&[], inputs, &mut out);
+        out
+    }
+
+    #[test]
+    fn test_interfaces() {
+        for &kind in Kind::ALL {
+            let chip = parse(kind.interface()).unwrap();
+            assert_eq!(chip.name, format!("{:?}", kind));
+            assert_eq!(Kind::from_name(&chip.name), Some(kind));
+            for (i, pin) in chip.inputs.iter().enumerate() {
+                assert_eq!(kind.is_clocked(i), chip.is_clocked(&pin.name), "{:?}", kind);
+            }
+        }
+        assert_eq!(Kind::from_name("Foo"), None);
+    }
+
+    #[test]
+    fn test_eval() {
+        assert_eq!(eval(Kind::Nand, &[1, 1], 1)[0] & 1, 0);
+        assert_eq!(eval(Kind::Mux4Way16, &[1, 2, 3, 4, 2], 1), [3]);
+        assert_eq!(eval(Kind::DMux4Way, &[1, 3], 4), [0, 0, 0, 1]);
+        assert_eq!(eval(Kind::DMux, &[1, 0], 2), [1, 0]);
+        assert_eq!(eval(Kind::FullAdder, &[1, 1, 1], 2), [1, 1]);
+        assert_eq!(eval(Kind::Or8Way, &[0x100], 1), [0]);
+        // x - y
+        let alu = [7, 3, 0, 1, 0, 0, 1, 1];
+        assert_eq!(eval(Kind::ALU, &alu, 3), [4, 0, 0]);
+        // !0 = -1
+        let alu = [7, 3, 1, 0, 1, 0, 1, 1];
+        assert_eq!(eval(Kind::ALU, &alu, 3), [0xFFFF, 0, 1]);
+    }
+
+    #[test]
+    fn test_clocked() {
+        let mut pc = vec![0; Kind::PC.state_size()];
+        Kind::PC.tick(&mut pc, &[7, 0, 1, 0]);
+        assert_eq!(Kind::PC.read_state(&pc, None), Some(1));
+        let mut out = [0];
+        Kind::PC.eval(&pc, &[7, 0, 1, 0], &mut out);
+        assert_eq!(out, [0]);
+        Kind::PC.tock(&mut pc);
+        Kind::PC.eval(&pc, &[7, 0, 1, 0], &mut out);
+        assert_eq!(out, [1]);
+        Kind::PC.tick(&mut pc, &[7, 1, 1, 0]);
+        Kind::PC.tock(&mut pc);
+        Kind::PC.eval(&pc, &[7, 0, 0, 0], &mut out);
+        assert_eq!(out, [7]);
+
+        let mut ram = vec![0; Kind::RAM8.state_size()];
+        Kind::RAM8.tick(&mut ram, &[42, 1, 11]);
+        assert_eq!(Kind::RAM8.read_state(&ram, Some(3)), Some(42));
+        Kind::RAM8.eval(&ram, &[0, 0, 3], &mut out);
+        assert_eq!(out, [42]);
+        assert!(Kind::RAM8.is_clocked(1) && !Kind::RAM8.is_clocked(2));
+    }
+}
@@ -0,0 +1,126 @@
+use std::rc::Rc;
+
+/// Where something starts in an HDL file, counting lines and columns from 1.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// A parsed `CHIP` definition.
+#[derive(Debug)]
+pub struct Chip {
+    pub name: String,
+    pub position: Position,
+    pub inputs: Vec<Pin>,
+    pub outputs: Vec<Pin>,
+    pub body: Body,
+    /// Input pins that only matter on the clock edge.
+    pub clocked: Vec<String>,
+}
+
+impl Chip {
+    pub fn input(&self, name: &str) -> Option<(usize, &Pin)> {
+        self.inputs.iter().enumerate().find(|(_, p)| p.name == name)
+    }
+
+    pub fn output(&self, name: &str) -> Option<(usize, &Pin)> {
+        self.outputs
+            .iter()
+            .enumerate()
+            .find(|(_, p)| p.name == name)
+    }
+
+    pub fn is_clocked(&self, input: &str) -> bool {
+        self.clocked.iter().any(|c| c == input)
+    }
+
+    pub fn parts(&self) -> &[Part] {
+        match &self.body {
+            Body::Parts(parts) => parts,
+            Body::Builtin(_) => &[],
+        }
+    }
+
+    /// Internal pins, as wide as the part outputs driving them.
+    /// `definitions` are the chips of the parts, in order.
+    pub fn internal_pins(&self, definitions: &[Rc<Chip>]) -> Vec<(&str, usize)> {
+        let mut internals: Vec<(&str, usize)> = vec![];
+        for (part, definition) in self.parts().iter().zip(definitions) {
+            for connection in &part.connections {
+                let (Wire::Bus(wire), Some((_, pin))) =
+                    (&connection.wire, definition.output(&connection.pin.name))
+                else {
+                    continue;
+                };
+                if self.input(&wire.name).is_some() || self.output(&wire.name).is_some() {
+                    continue;
+                }
+                let width = match (wire.range, connection.pin.bits(pin.width)) {
+                    (Some((_, hi)), _) => hi + 1,
+                    (None, Some(bits)) => bits.len(),
+                    (None, None) => continue,
+                };
+                match internals.iter_mut().find(|(name, _)| *name == wire.name) {
+                    Some((_, w)) => *w = (*w).max(width),
+                    None => internals.push((&wire.name, width)),
+                }
+            }
+        }
+        internals
+    }
+}
+
+#[derive(Debug)]
+pub enum Body {
+    Parts(Vec<Part>),
+    /// Implemented natively by the simulator, under the given name.
+    Builtin(String),
+}
+
+#[derive(Debug)]
+pub struct Pin {
+    pub name: String,
+    pub width: usize,
+    pub position: Position,
+}
+
+/// A chip used inside another one, like `Not(in=a, out=b)`.
+#[derive(Debug)]
+pub struct Part {
+    pub chip: String,
+    pub position: Position,
+    pub connections: Vec<Connection>,
+}
+
+/// `pin=wire`, where `pin` belongs to the part and `wire` to the chip using it.
+#[derive(Debug)]
+pub struct Connection {
+    pub pin: Bus,
+    pub wire: Wire,
+}
+
+#[derive(Debug)]
+pub enum Wire {
+    Bus(Bus),
+    Constant(bool, Position),
+}
+
+/// A pin name, optionally narrowed to `name[i]` or `name[i..j]`.
+#[derive(Debug)]
+pub struct Bus {
+    pub name: String,
+    pub range: Option<(usize, usize)>,
+    pub position: Position,
+}
+
+impl Bus {
+    /// The bits this refers to in a pin `width` bits wide, if they're in it.
+    pub fn bits(&self, width: usize) -> Option<std::ops::Range<usize>> {
+        match self.range {
+            None => Some(0..width),
+            Some((lo, hi)) if lo <= hi && hi < width => Some(lo..hi + 1),
+            Some(_) => None,
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use crate::chip::{Position, Wire};
+use crate::error::{Error, Result};
+use crate::library::Library;
+use crate::netlist::Netlist;
+use crate::simulator::Simulator;
+use emulator::cpu::Cpu;
+use std::fmt;
+
+/// A `Computer` chip running a program at gate level, with its `ROM32K`
+/// loaded like in `ROM32K load Max.hack`.
+pub struct Computer {
+    pub simulator: Simulator,
+    /// The wires the `CPU` part's `pc`, `writeM`, `addressM` and `outM`
+    /// outputs drive, whatever `Computer.hdl` calls them.
+    pc: String,
+    write_m: String,
+    address_m: String,
+    out_m: String,
+    /// Whether the CPU keeps A and D in the built-in registers.
+    registers: bool,
+    pub time: u64,
+}
+
+impl Computer {
+    pub fn new(library: &mut Library, program: &[u16]) -> Result<Self> {
+        let error = |message: String| Error::build("Computer", Position::default(), message);
+        let chip = library
+            .chip("Computer")?
+            .ok_or_else(|| error("unknown chip".to_string()))?;
+        let cpu = chip
+            .parts()
+            .iter()
+            .find(|part| part.chip == "CPU")
+            .ok_or_else(|| error("no CPU part".to_string()))?;
+        let wire = |pin: &str| {
+            cpu.connections
+                .iter()
+                .find_map(|connection| match &connection.wire {
+                    Wire::Bus(wire) if connection.pin.name == pin && wire.range.is_none() => {
+                        Some(wire.name.clone())
+                    }
+                    _ => None,
+                })
+                .ok_or_else(|| error(format!("CPU output `{}` isn't connected to a pin", pin)))
+        };
+        let (pc, write_m, address_m, out_m) = (
+            wire("pc")?,
+            wire("writeM")?,
+            wire("addressM")?,
+            wire("outM")?,
+        );
+
+        let mut simulator = Simulator::new(Netlist::build(library, "Computer")?);
+        let rom = simulator
+            .memory_mut("ROM32K")
+            .map_err(|e| error(e.to_string()))?;
+        if program.len() > rom.len() {
+            return Err(error("the program doesn't fit in ROM32K".to_string()));
+        }
+        rom[..program.len()].copy_from_slice(program);
+        simulator.eval();
+        let registers = simulator.state("ARegister", None).is_ok()
+            && simulator.state("DRegister", None).is_ok();
+        Ok(Self {
+            simulator,
+            pc,
+            write_m,
+            address_m,
+            out_m,
+            registers,
+            time: 0,
+        })
+    }
+
+    /// Runs one clock cycle.
+    pub fn step(&mut self) {
+        self.simulator.tick();
+        self.simulator.tock();
+        self.time += 1;
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.simulator.get(&self.pc).unwrap()
+    }
+
+    /// A and D, when the CPU keeps them in `ARegister` and `DRegister`.
+    pub fn registers(&self) -> Option<(u16, u16)> {
+        let register = |chip| self.simulator.state(chip, None).unwrap();
+        self.registers
+            .then(|| (register("ARegister"), register("DRegister")))
+    }
+
+    /// The address and value written to memory during this cycle, if any.
+    pub fn write(&self) -> Option<(u16, u16)> {
+        let get = |pin: &String| self.simulator.get(pin).unwrap();
+        (get(&self.write_m) == 1).then(|| (get(&self.address_m), get(&self.out_m)))
+    }
+}
+
+/// The first cycle where the gate-level computer and the emulator disagree.
+#[derive(Debug, PartialEq)]
+pub struct Divergence {
+    pub cycle: u64,
+    /// The emulator's PC at the start of the cycle.
+    pub pc: u16,
+    pub what: String,
+    pub computer: String,
+    pub emulator: String,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[cycle {}, PC {}] {} is {} at gate level, but {} in the emulator",
+            self.cycle, self.pc, self.what, self.computer, self.emulator
+        )
+    }
+}
+
+/// Runs both machines for `cycles` cycles, checking the PC, A and D if
+/// `Computer::registers` has them, and what's written to memory at every
+/// cycle.
+pub fn compare(computer: &mut Computer, cpu: &mut Cpu, cycles: u64) -> Option<Divergence> {
+    for _ in 0..cycles {
+        let (cycle, pc) = (cpu.time, cpu.pc);
+        let divergence = |what: &str, computer: String, emulator: String| {
+            Some(Divergence {
+                cycle,
+                pc,
+                what: what.to_string(),
+                computer,
+                emulator,
+            })
+        };
+        if computer.pc() != cpu.pc {
+            return divergence("PC", computer.pc().to_string(), cpu.pc.to_string());
+        }
+        if let Some((a, d)) = computer.registers() {
+            if a != cpu.a {
+                return divergence("A", a.to_string(), cpu.a.to_string());
+            }
+            if d != cpu.d {
+                return divergence("D", d.to_string(), cpu.d.to_string());
+            }
+        }
+        let write = computer.write();
+        let instruction = cpu.rom.get(cpu.pc as usize).copied().unwrap_or(0);
+        let address = cpu.a;
+        cpu.step();
+        let expected = (instruction & 0x8008 == 0x8008)
+            .then(|| (address, cpu.ram.get(address as usize).copied().unwrap_or(0)));
+        if write != expected {
+            let show = |write: Option<(u16, u16)>| match write {
+                Some((address, value)) => format!("RAM[{}] = {}", address, value as i16),
+                None => "no write".to_string(),
+            };
+            return divergence("the memory write", show(write), show(expected));
+        }
+        computer.step();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assembler::check::parse_hack;
+    use assembler::program::Program;
+    use std::fs;
+    use std::path::Path;
+
+    fn library(dirs: &[&str]) -> Library {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+        Library::new(dirs.iter().map(|dir| root.join(dir)).collect())
+    }
+
+    fn program(path: &str) -> Vec<u16> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("..").join(path);
+        parse_hack(&fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_max() {
+        let program = program("05/Max.hack");
+        let mut computer = Computer::new(&mut library(&["05"]), &program).unwrap();
+        computer.simulator.set_state("RAM16K", Some(0), 3).unwrap();
+        let mut cpu = Cpu::new(&program);
+        cpu.ram[0] = 3;
+        computer.simulator.set_state("RAM16K", Some(1), 5).unwrap();
+        cpu.ram[1] = 5;
+        assert_eq!(compare(&mut computer, &mut cpu, 20), None);
+        assert_eq!(computer.simulator.state("RAM16K", Some(2)), Ok(5));
+        assert_eq!(computer.time, 20);
+    }
+
+    #[test]
+    fn test_rect() {
+        // The CPU and the PC of the projects, down to the gates.
+        let program = program("05/Rect.hack");
+        let mut library = library(&["05", "03/a", "02", "01"]);
+        let mut computer = Computer::new(&mut library, &program).unwrap();
+        computer.simulator.set_state("RAM16K", Some(0), 4).unwrap();
+        let mut cpu = Cpu::new(&program);
+        cpu.ram[0] = 4;
+        assert_eq!(compare(&mut computer, &mut cpu, 200), None);
+        assert_eq!(cpu.ram[emulator::cpu::SCREEN + 32], 0xffff);
+    }
+
+    #[test]
+    fn test_divergence() {
+        // A CPU that loads D when the instruction asks for A.
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../05/CPU.hdl");
+        let source = fs::read_to_string(path).unwrap();
+        let source = source.replace("instruction[4], out=loadD", "instruction[5], out=loadD");
+        let mut library = library(&["05"]);
+        library.add(crate::parser::parse(&source).unwrap());
+        let program = Program::assemble("@7\nD=A\n@3\nM=D\n").unwrap().words;
+        let mut computer = Computer::new(&mut library, &program).unwrap();
+        let divergence = compare(&mut computer, &mut Cpu::new(&program), 10).unwrap();
+        assert_eq!(
+            divergence.to_string(),
+            "[cycle 2, PC 2] D is 0 at gate level, but 7 in the emulator"
+        );
+    }
+}
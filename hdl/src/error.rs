@@ -0,0 +1,84 @@
+use crate::chip::Position;
+use std::path::PathBuf;
+use std::{fmt, io};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    Scan {
+        position: Position,
+        lexeme: String,
+        message: String,
+    },
+    Parse {
+        position: Position,
+        lexeme: String,
+        message: String,
+    },
+    /// A chip that parses but can't be wired up.
+    Build {
+        chip: String,
+        position: Position,
+        message: String,
+    },
+    /// An error in a chip's HDL file.
+    File {
+        path: PathBuf,
+        error: Box<Error>,
+    },
+    Io(PathBuf, io::Error),
+}
+
+impl Error {
+    pub fn build(chip: &str, position: Position, message: impl Into<String>) -> Self {
+        Self::Build {
+            chip: chip.to_string(),
+            position,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Scan {
+                position,
+                lexeme,
+                message,
+            } => write!(
+                f,
+                "[line {}] scan error at `{}`: {}",
+                position.line, lexeme, message
+            ),
+            Self::Parse {
+                position,
+                lexeme,
+                message,
+            } => write!(
+                f,
+                "[line {}] parse error at `{}`: {}",
+                position.line, lexeme, message
+            ),
+            Self::Build {
+                chip,
+                position,
+                message,
+            } if position.line == 0 => write!(f, "build error in chip `{}`: {}", chip, message),
+            Self::Build {
+                chip,
+                position,
+                message,
+            } => write!(
+                f,
+                "[line {}] build error in chip `{}`: {}",
+                position.line, chip, message
+            ),
+            Self::File { path, error } => write!(f, "{}: {}", path.display(), error),
+            Self::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
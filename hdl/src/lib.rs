@@ -0,0 +1,15 @@
+pub mod builtin;
+pub mod chip;
+pub mod computer;
+pub mod error;
+pub mod library;
+pub mod lint;
+pub mod netlist;
+pub mod parser;
+pub mod scanner;
+pub mod script;
+pub mod simulator;
+pub mod stats;
+pub mod token;
+pub mod vectors;
+pub mod verilog;
@@ -0,0 +1,74 @@
+use crate::builtin::Kind;
+use crate::chip::{Body, Chip};
+use crate::error::{Error, Result};
+use crate::parser::parse;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// Finds chips by name: `Name.hdl` in the first directory that has one,
+/// otherwise the built-in chip, like the official hardware simulator.
+pub struct Library {
+    dirs: Vec<PathBuf>,
+    chips: HashMap<String, Rc<Chip>>,
+}
+
+impl Library {
+    pub fn new(dirs: Vec<PathBuf>) -> Self {
+        Self {
+            dirs,
+            chips: HashMap::new(),
+        }
+    }
+
+    /// Makes a chip available without a file, taking precedence over files.
+    pub fn add(&mut self, chip: Chip) {
+        self.chips.insert(chip.name.clone(), Rc::new(chip));
+    }
+
+    /// The chips of every part of `chip`, in order.
+    pub fn parts(&mut self, chip: &Chip) -> Result<Vec<Rc<Chip>>> {
+        let Body::Parts(parts) = &chip.body else {
+            return Ok(vec![]);
+        };
+        parts
+            .iter()
+            .map(|part| {
+                self.chip(&part.chip)?.ok_or_else(|| {
+                    let message = format!("unknown chip `{}`", part.chip);
+                    Error::build(&chip.name, part.position, message)
+                })
+            })
+            .collect()
+    }
+
+    /// Returns `None` for chips that are neither in the directories nor
+    /// built in.
+    pub fn chip(&mut self, name: &str) -> Result<Option<Rc<Chip>>> {
+        if let Some(chip) = self.chips.get(name) {
+            return Ok(Some(Rc::clone(chip)));
+        }
+        let file = self
+            .dirs
+            .iter()
+            .map(|dir| dir.join(name).with_extension("hdl"))
+            .find(|path| path.is_file());
+        let chip = match file {
+            Some(path) => {
+                let source = fs::read_to_string(&path).map_err(|e| Error::Io(path.clone(), e))?;
+                parse(&source).map_err(|error| Error::File {
+                    path,
+                    error: Box::new(error),
+                })?
+            }
+            None => match Kind::from_name(name) {
+                Some(kind) => parse(kind.interface())?,
+                None => return Ok(None),
+            },
+        };
+        let chip = Rc::new(chip);
+        self.chips.insert(name.to_string(), Rc::clone(&chip));
+        Ok(Some(chip))
+    }
+}
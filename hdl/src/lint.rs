@@ -0,0 +1,328 @@
+use crate::chip::{Chip, Position, Wire};
+use crate::library::Library;
+use std::collections::HashMap;
+use std::fmt;
+
+/// A likely mistake in a chip, where it was found.
+#[derive(Debug, PartialEq)]
+pub struct Finding {
+    pub position: Position,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[line {}, column {}] {}",
+            self.position.line, self.position.column, self.message
+        )
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Role {
+    Input,
+    Output,
+    Internal,
+}
+
+struct Signal {
+    role: Role,
+    width: usize,
+    /// Where each bit is driven from, if anywhere.
+    drivers: Vec<Option<Position>>,
+    read: bool,
+}
+
+/// Checks one chip without flattening it, so every problem is reported
+/// rather than only the first one. Only the interfaces of the parts are
+/// looked up in `library`.
+pub fn lint(library: &mut Library, chip: &Chip) -> Vec<Finding> {
+    let mut findings = vec![];
+    let mut report = |position, message: String| findings.push(Finding { position, message });
+
+    let mut definitions = vec![];
+    for part in chip.parts() {
+        let definition = match library.chip(&part.chip) {
+            Ok(Some(definition)) => Some(definition),
+            Ok(None) => {
+                report(part.position, format!("unknown chip `{}`", part.chip));
+                None
+            }
+            Err(e) => {
+                report(part.position, format!("can't load `{}`: {}", part.chip, e));
+                None
+            }
+        };
+        definitions.push(definition);
+    }
+
+    let mut signals: HashMap<&str, Signal> = HashMap::new();
+    for pin in &chip.inputs {
+        let signal = Signal {
+            role: Role::Input,
+            width: pin.width,
+            drivers: vec![Some(pin.position); pin.width],
+            read: false,
+        };
+        signals.insert(&pin.name, signal);
+    }
+    for pin in &chip.outputs {
+        let signal = Signal {
+            role: Role::Output,
+            width: pin.width,
+            drivers: vec![None; pin.width],
+            read: false,
+        };
+        signals.insert(&pin.name, signal);
+    }
+    // Internal pins are as wide as the widest use driving them.
+    for (part, definition) in chip.parts().iter().zip(&definitions) {
+        let Some(definition) = definition else {
+            continue;
+        };
+        for connection in &part.connections {
+            let (Wire::Bus(wire), Some((_, pin))) =
+                (&connection.wire, definition.output(&connection.pin.name))
+            else {
+                continue;
+            };
+            let width = match (wire.range, connection.pin.bits(pin.width)) {
+                (Some((_, hi)), _) => hi + 1,
+                (None, Some(bits)) => bits.len(),
+                (None, None) => continue,
+            };
+            let signal = signals.entry(&wire.name).or_insert(Signal {
+                role: Role::Internal,
+                width,
+                drivers: vec![],
+                read: false,
+            });
+            if signal.role == Role::Internal {
+                signal.width = signal.width.max(width);
+                signal.drivers.resize(signal.width, None);
+            }
+        }
+    }
+
+    let mut reads = vec![];
+    for (part, definition) in chip.parts().iter().zip(&definitions) {
+        let Some(definition) = definition else {
+            continue;
+        };
+        for connection in &part.connections {
+            let name = &connection.pin.name;
+            let (pin, is_input) = match (definition.input(name), definition.output(name)) {
+                (Some((_, pin)), _) => (pin, true),
+                (None, Some((_, pin))) => (pin, false),
+                (None, None) => {
+                    let message = format!("chip `{}` has no pin `{}`", part.chip, name);
+                    report(connection.pin.position, message);
+                    continue;
+                }
+            };
+            let Some(bits) = connection.pin.bits(pin.width) else {
+                let message = format!("`{}` is {} bits wide", name, pin.width);
+                report(connection.pin.position, message);
+                continue;
+            };
+            let wire = match &connection.wire {
+                Wire::Constant(_, position) if !is_input => {
+                    report(
+                        *position,
+                        "can't connect an output to a constant".to_string(),
+                    );
+                    continue;
+                }
+                Wire::Constant(..) => continue,
+                Wire::Bus(wire) => wire,
+            };
+            let Some(signal) = signals.get_mut(&wire.name[..]) else {
+                let message = format!("pin `{}` isn't driven by any part", wire.name);
+                report(wire.position, message);
+                continue;
+            };
+            let Some(wire_bits) = wire.bits(signal.width) else {
+                let message = format!("`{}` is {} bits wide", wire.name, signal.width);
+                report(wire.position, message);
+                continue;
+            };
+            if wire_bits.len() != bits.len() {
+                let message = format!(
+                    "width mismatch: `{}` has {} bits, connected to {}",
+                    name,
+                    bits.len(),
+                    wire_bits.len()
+                );
+                // Still count the wire as driven or read, so that the
+                // mismatch is all that's reported.
+                report(wire.position, message);
+            }
+            match (is_input, signal.role) {
+                (true, Role::Output) => {
+                    let message = format!("can't read output pin `{}`", wire.name);
+                    report(wire.position, message);
+                }
+                (true, _) => {
+                    signal.read = true;
+                    reads.push((wire, wire_bits));
+                }
+                (false, Role::Input) => {
+                    let message = format!("can't drive input pin `{}`", wire.name);
+                    report(wire.position, message);
+                }
+                (false, _) => {
+                    let driven = wire_bits.clone().find_map(|bit| signal.drivers[bit]);
+                    if let Some(other) = driven {
+                        let message = format!(
+                            "`{}` is already driven at line {}, column {}",
+                            wire.name, other.line, other.column
+                        );
+                        report(wire.position, message);
+                    }
+                    for bit in wire_bits {
+                        signal.drivers[bit].get_or_insert(wire.position);
+                    }
+                }
+            }
+        }
+    }
+
+    for (wire, bits) in reads {
+        let signal = &signals[&wire.name[..]];
+        if signal.drivers.iter().all(Option::is_none) {
+            let message = format!("pin `{}` isn't driven by any part", wire.name);
+            report(wire.position, message);
+        } else if let Some(bit) = bits.clone().find(|&bit| signal.drivers[bit].is_none()) {
+            let message = format!("`{}[{}]` isn't driven by any part", wire.name, bit);
+            report(wire.position, message);
+        }
+    }
+    for pin in &chip.outputs {
+        let signal = &signals[&pin.name[..]];
+        if signal.drivers.iter().all(Option::is_none) {
+            report(
+                pin.position,
+                format!("output pin `{}` isn't connected", pin.name),
+            );
+        } else if let Some(bit) = signal.drivers.iter().position(Option::is_none) {
+            let message = format!("`{}[{}]` isn't connected", pin.name, bit);
+            report(pin.position, message);
+        }
+    }
+    let mut unused: Vec<_> = signals
+        .iter()
+        .filter(|(_, signal)| signal.role == Role::Internal && !signal.read)
+        .filter_map(|(name, signal)| Some((*name, signal.drivers.iter().flatten().next()?)))
+        .collect();
+    unused.sort_by_key(|(_, position)| (position.line, position.column));
+    for (name, position) in unused {
+        report(*position, format!("internal pin `{}` is never used", name));
+    }
+
+    findings.sort_by_key(|finding| (finding.position.line, finding.position.column));
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use std::fs;
+    use std::path::Path;
+
+    fn lint_source(parts: &str) -> Vec<String> {
+        let source = format!(
+            "CHIP Foo {{\n    IN a, b[4];\n    OUT out, out4[4];\n    PARTS:\n{}}}",
+            parts
+        );
+        let chip = parse(&source).unwrap();
+        let findings = lint(&mut Library::new(vec![]), &chip);
+        findings.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_findings() {
+        assert_eq!(
+            lint_source(
+                "    Not(in=a, out=out);
+    Not16(in[0..3]=b, in[4..7]=b[0..2], out[0..3]=out4, out[4]=x);
+    Nor(a=a, b=a, out=y);
+    And(a=z, b=a, out=w);
+"
+            ),
+            [
+                "[line 6, column 32] width mismatch: `in` has 4 bits, connected to 3",
+                "[line 6, column 64] internal pin `x` is never used",
+                "[line 7, column 5] unknown chip `Nor`",
+                "[line 8, column 11] pin `z` isn't driven by any part",
+                "[line 8, column 23] internal pin `w` is never used",
+            ]
+        );
+        assert_eq!(
+            lint_source(
+                "    Not16(in[0..3]=b, out[0..1]=x[0..1], out[2]=x[3], out[0]=out);
+    Not16(in[0..2]=x[0..2], out[0..2]=out4[0..2], out[9]=out4[1], foo=a);
+"
+            ),
+            [
+                "[line 3, column 14] `out4[3]` isn't connected",
+                "[line 6, column 20] `x[2]` isn't driven by any part",
+                "[line 6, column 58] `out4` is already driven at line 6, column 39",
+                "[line 6, column 67] chip `Not16` has no pin `foo`",
+            ]
+        );
+        assert_eq!(
+            lint_source("    Not(in=a, out=a);\n    Not(in=out, out=true);\n"),
+            [
+                "[line 3, column 9] output pin `out` isn't connected",
+                "[line 3, column 14] output pin `out4` isn't connected",
+                "[line 5, column 19] can't drive input pin `a`",
+                "[line 6, column 12] can't read output pin `out`",
+                "[line 6, column 21] can't connect an output to a constant",
+            ]
+        );
+        assert_eq!(
+            lint_source(
+                "    Not(in=a, out=out);\n    Not(in=a, out=out);\n    Not16(out[0..3]=out4);\n"
+            ),
+            ["[line 6, column 19] `out` is already driven at line 5, column 19"]
+        );
+        assert_eq!(
+            lint_source(
+                "    Not(in=a, out=x[0..1]);\n    And(a=x[0], b=x[1], out=y);\n    \
+                 Not16(in[0..2]=y, out[0..3]=out4, out[4]=out);\n"
+            ),
+            [
+                "[line 5, column 19] width mismatch: `out` has 1 bits, connected to 2",
+                "[line 7, column 20] width mismatch: `in` has 3 bits, connected to 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_projects() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+        let mut findings = vec![];
+        for project in ["01", "02", "03/a", "03/b", "05"] {
+            let dir = root.join(project);
+            let mut library = Library::new(vec![dir.clone()]);
+            for entry in fs::read_dir(&dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.extension().is_some_and(|ext| ext == "hdl") {
+                    let chip = parse(&fs::read_to_string(&path).unwrap()).unwrap();
+                    let name = path.file_name().unwrap().to_string_lossy().to_string();
+                    for finding in lint(&mut library, &chip) {
+                        findings.push(format!("{}: {}", name, finding));
+                    }
+                }
+            }
+        }
+        // The carry out of the last full adder is left unused on purpose.
+        assert_eq!(
+            findings,
+            ["Add16.hdl: [line 31, column 63] internal pin `carry15` is never used"]
+        );
+    }
+}
@@ -0,0 +1,542 @@
+use crate::builtin::Kind;
+use crate::chip::{Body, Chip, Position, Wire};
+use crate::error::{Error, Result};
+use crate::library::Library;
+use std::collections::HashMap;
+
+/// A one-bit connection between components.
+pub type Net = usize;
+
+pub const FALSE: Net = 0;
+pub const TRUE: Net = 1;
+
+#[derive(Debug)]
+pub enum Component {
+    Nand {
+        a: Net,
+        b: Net,
+        out: Net,
+    },
+    /// Buses are listed pin by pin, least significant bit first.
+    Builtin {
+        kind: Kind,
+        inputs: Vec<Vec<Net>>,
+        outputs: Vec<Vec<Net>>,
+    },
+}
+
+impl Component {
+    pub fn outputs(&self) -> impl Iterator<Item = Net> + '_ {
+        let (nand, builtin) = match self {
+            Self::Nand { out, .. } => (Some(*out), None),
+            Self::Builtin { outputs, .. } => (None, Some(outputs.iter().flatten().copied())),
+        };
+        nand.into_iter().chain(builtin.into_iter().flatten())
+    }
+
+    /// Inputs that affect the outputs right away, as opposed to clocked ones.
+    pub fn combinational_inputs(&self) -> impl Iterator<Item = Net> + '_ {
+        let (nand, builtin) = match self {
+            Self::Nand { a, b, .. } => (Some([*a, *b]), None),
+            Self::Builtin { kind, inputs, .. } => {
+                let inputs = inputs.iter().enumerate();
+                let inputs = inputs.filter(|(i, _)| !kind.is_clocked(*i));
+                (
+                    None,
+                    Some(inputs.flat_map(|(_, nets)| nets.iter().copied())),
+                )
+            }
+        };
+        nand.into_iter()
+            .flatten()
+            .chain(builtin.into_iter().flatten())
+    }
+
+    fn nets_mut(&mut self) -> Vec<&mut Net> {
+        match self {
+            Self::Nand { a, b, out } => vec![a, b, out],
+            Self::Builtin {
+                inputs, outputs, ..
+            } => inputs.iter_mut().chain(outputs).flatten().collect(),
+        }
+    }
+}
+
+/// A chip flattened into Nand gates and built-in chips.
+#[derive(Debug)]
+pub struct Netlist {
+    pub chip: String,
+    pub nets: usize,
+    pub components: Vec<Component>,
+    /// Where each component comes from, like `ALU/Mux16#3/Mux#2/Nand#1`.
+    pub paths: Vec<String>,
+    /// The components in an order where each comes after those driving it.
+    pub order: Vec<usize>,
+    pub inputs: Vec<(String, Vec<Net>)>,
+    pub outputs: Vec<(String, Vec<Net>)>,
+    /// Internal pins of the top-level chip.
+    pub internals: Vec<(String, Vec<Net>)>,
+}
+
+impl Netlist {
+    pub fn build(library: &mut Library, name: &str) -> Result<Self> {
+        let chip = library
+            .chip(name)?
+            .ok_or_else(|| Error::build(name, Position::default(), "unknown chip"))?;
+        let mut builder = Builder {
+            library,
+            parents: vec![FALSE, TRUE],
+            components: vec![],
+            paths: vec![],
+            stack: vec![],
+            internals: vec![],
+        };
+        let inputs: Vec<_> = chip.inputs.iter().map(|p| builder.nets(p.width)).collect();
+        let outputs: Vec<_> = chip.outputs.iter().map(|p| builder.nets(p.width)).collect();
+        builder.instantiate(&chip, &inputs, &outputs, "")?;
+        let named = |pins: &[crate::chip::Pin], nets: Vec<Vec<Net>>| {
+            pins.iter().map(|p| p.name.clone()).zip(nets).collect()
+        };
+        let mut netlist = Self {
+            chip: chip.name.clone(),
+            nets: 0,
+            components: builder.components,
+            paths: builder.paths,
+            order: vec![],
+            inputs: named(&chip.inputs, inputs),
+            outputs: named(&chip.outputs, outputs),
+            internals: builder.internals,
+        };
+        netlist.renumber(&mut builder.parents);
+        netlist.order = netlist.sort()?;
+        Ok(netlist)
+    }
+
+    /// Replaces every net by its representative, numbering those densely.
+    fn renumber(&mut self, parents: &mut [Net]) {
+        let mut numbers = HashMap::new();
+        for net in [FALSE, TRUE] {
+            numbers.insert(net, net);
+        }
+        let mut renumber = |net: &mut Net| {
+            let root = find(parents, *net);
+            let next = numbers.len();
+            *net = *numbers.entry(root).or_insert(next);
+        };
+        for component in &mut self.components {
+            component.nets_mut().into_iter().for_each(&mut renumber);
+        }
+        for (_, nets) in self
+            .inputs
+            .iter_mut()
+            .chain(&mut self.outputs)
+            .chain(&mut self.internals)
+        {
+            nets.iter_mut().for_each(&mut renumber);
+        }
+        self.nets = numbers.len();
+    }
+
+    /// Orders the components so that each is evaluated after its inputs are.
+    fn sort(&self) -> Result<Vec<usize>> {
+        let mut drivers = vec![None; self.nets];
+        for (i, component) in self.components.iter().enumerate() {
+            for net in component.outputs() {
+                if let Some(other) = drivers[net].replace(i) {
+                    let message = format!(
+                        "`{}` and `{}` drive the same pin",
+                        self.paths[other], self.paths[i]
+                    );
+                    return Err(Error::build(&self.chip, Position::default(), message));
+                }
+            }
+        }
+        let mut dependents = vec![vec![]; self.components.len()];
+        let mut pending = vec![0; self.components.len()];
+        for (i, component) in self.components.iter().enumerate() {
+            for net in component.combinational_inputs() {
+                if let Some(driver) = drivers[net] {
+                    dependents[driver].push(i);
+                    pending[i] += 1;
+                }
+            }
+        }
+        let mut ready: Vec<usize> = (0..self.components.len())
+            .filter(|&i| pending[i] == 0)
+            .collect();
+        let mut order = Vec::with_capacity(self.components.len());
+        while let Some(i) = ready.pop() {
+            order.push(i);
+            for &dependent in &dependents[i] {
+                pending[dependent] -= 1;
+                if pending[dependent] == 0 {
+                    ready.push(dependent);
+                }
+            }
+        }
+        if let Some(start) = (0..self.components.len()).find(|&i| pending[i] > 0) {
+            let cycle = self.find_loop(start, &drivers, &pending);
+            let paths: Vec<_> = cycle.iter().map(|&i| &self.paths[i][..]).collect();
+            let message = format!("combinational loop through `{}`", paths.join("` -> `"));
+            return Err(Error::build(&self.chip, Position::default(), message));
+        }
+        Ok(order)
+    }
+
+    /// Walks back from a component left unsorted, through drivers that were
+    /// left unsorted too, until coming back around. Returns the loop in the
+    /// direction signals flow.
+    fn find_loop(&self, start: usize, drivers: &[Option<usize>], pending: &[usize]) -> Vec<usize> {
+        let mut walk = vec![start];
+        loop {
+            let current = *walk.last().unwrap();
+            let previous = self.components[current]
+                .combinational_inputs()
+                .filter_map(|net| drivers[net])
+                .find(|&driver| pending[driver] > 0)
+                .expect("unsorted components should have unsorted drivers");
+            if let Some(i) = walk.iter().position(|&c| c == previous) {
+                let mut cycle = walk.split_off(i);
+                cycle.reverse();
+                return cycle;
+            }
+            walk.push(previous);
+        }
+    }
+}
+
+struct Builder<'l> {
+    library: &'l mut Library,
+    /// Union-find forest of nets, where connected nets end up in one tree.
+    parents: Vec<Net>,
+    components: Vec<Component>,
+    paths: Vec<String>,
+    /// Chips being instantiated, to catch chips that contain themselves.
+    stack: Vec<String>,
+    internals: Vec<(String, Vec<Net>)>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Role {
+    Input,
+    Output,
+    Internal,
+}
+
+impl Builder<'_> {
+    fn nets(&mut self, width: usize) -> Vec<Net> {
+        let start = self.parents.len();
+        self.parents.extend(start..start + width);
+        (start..start + width).collect()
+    }
+
+    fn connect(&mut self, a: Net, b: Net) {
+        let (a, b) = (find(&mut self.parents, a), find(&mut self.parents, b));
+        // The smaller net stays the root, so constants remain their own.
+        self.parents[a.max(b)] = a.min(b);
+    }
+
+    fn instantiate(
+        &mut self,
+        chip: &Chip,
+        inputs: &[Vec<Net>],
+        outputs: &[Vec<Net>],
+        path: &str,
+    ) -> Result<()> {
+        let parts = match &chip.body {
+            Body::Builtin(name) => {
+                let kind = Kind::from_name(name).ok_or_else(|| {
+                    let message = format!("unknown built-in chip `{}`", name);
+                    Error::build(&chip.name, chip.position, message)
+                })?;
+                let component = match kind {
+                    Kind::Nand => Component::Nand {
+                        a: inputs[0][0],
+                        b: inputs[1][0],
+                        out: outputs[0][0],
+                    },
+                    _ => Component::Builtin {
+                        kind,
+                        inputs: inputs.to_vec(),
+                        outputs: outputs.to_vec(),
+                    },
+                };
+                self.components.push(component);
+                self.paths.push(path.to_string());
+                return Ok(());
+            }
+            Body::Parts(parts) => parts,
+        };
+        if self.stack.contains(&chip.name) {
+            let message = "chip contains itself";
+            return Err(Error::build(&chip.name, chip.position, message));
+        }
+        self.stack.push(chip.name.clone());
+
+        let mut wires: HashMap<&str, (Vec<Net>, Role)> = HashMap::new();
+        for (pin, nets) in chip.inputs.iter().zip(inputs) {
+            wires.insert(&pin.name, (nets.clone(), Role::Input));
+        }
+        for (pin, nets) in chip.outputs.iter().zip(outputs) {
+            wires.insert(&pin.name, (nets.clone(), Role::Output));
+        }
+
+        let definitions = self.library.parts(chip)?;
+        let internals = chip.internal_pins(&definitions);
+        for (name, width) in internals {
+            let nets = self.nets(width);
+            if self.stack.len() == 1 {
+                self.internals.push((name.to_string(), nets.clone()));
+            }
+            wires.insert(name, (nets, Role::Internal));
+        }
+
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for part in parts {
+            *counts.entry(&part.chip).or_default() += 1;
+        }
+        let mut seen: HashMap<&str, usize> = HashMap::new();
+        for (part, definition) in parts.iter().zip(definitions) {
+            let error = |position, message: String| Error::build(&chip.name, position, message);
+            let mut part_inputs: Vec<Vec<Net>> = definition
+                .inputs
+                .iter()
+                .map(|p| vec![FALSE; p.width])
+                .collect();
+            let part_outputs: Vec<Vec<Net>> = definition
+                .outputs
+                .iter()
+                .map(|p| self.nets(p.width))
+                .collect();
+            for connection in &part.connections {
+                let name = &connection.pin.name;
+                let (index, pin, is_input) = match (definition.input(name), definition.output(name))
+                {
+                    (Some((index, pin)), _) => (index, pin, true),
+                    (None, Some((index, pin))) => (index, pin, false),
+                    (None, None) => {
+                        let message = format!("chip `{}` has no pin `{}`", part.chip, name);
+                        return Err(error(connection.pin.position, message));
+                    }
+                };
+                let bits = connection.pin.bits(pin.width).ok_or_else(|| {
+                    let message = format!("`{}` is {} bits wide", name, pin.width);
+                    error(connection.pin.position, message)
+                })?;
+                let (nets, position) = match &connection.wire {
+                    Wire::Constant(_, position) if !is_input => {
+                        let message = "can't connect an output to a constant".to_string();
+                        return Err(error(*position, message));
+                    }
+                    Wire::Constant(value, position) => {
+                        let net = if *value { TRUE } else { FALSE };
+                        (vec![net; bits.len()], *position)
+                    }
+                    Wire::Bus(wire) => {
+                        let Some((nets, role)) = wires.get(&wire.name[..]) else {
+                            let message = format!("pin `{}` isn't driven by any part", wire.name);
+                            return Err(error(wire.position, message));
+                        };
+                        if is_input && *role == Role::Output {
+                            let message = format!("can't read output pin `{}`", wire.name);
+                            return Err(error(wire.position, message));
+                        }
+                        if !is_input && *role == Role::Input {
+                            let message = format!("can't drive input pin `{}`", wire.name);
+                            return Err(error(wire.position, message));
+                        }
+                        let wire_bits = wire.bits(nets.len()).ok_or_else(|| {
+                            let message = format!("`{}` is {} bits wide", wire.name, nets.len());
+                            error(wire.position, message)
+                        })?;
+                        (nets[wire_bits].to_vec(), wire.position)
+                    }
+                };
+                if nets.len() != bits.len() {
+                    let message = format!(
+                        "width mismatch: `{}` has {} bits, connected to {}",
+                        name,
+                        bits.len(),
+                        nets.len()
+                    );
+                    return Err(error(position, message));
+                }
+                if is_input {
+                    part_inputs[index][bits].copy_from_slice(&nets);
+                } else {
+                    for (bit, net) in bits.zip(nets) {
+                        self.connect(part_outputs[index][bit], net);
+                    }
+                }
+            }
+
+            let mut segment = part.chip.clone();
+            if counts[&part.chip[..]] > 1 {
+                let n = seen.entry(&part.chip).or_default();
+                *n += 1;
+                segment = format!("{}#{}", segment, n);
+            }
+            let path = if path.is_empty() {
+                segment
+            } else {
+                format!("{}/{}", path, segment)
+            };
+            self.instantiate(&definition, &part_inputs, &part_outputs, &path)?;
+        }
+        self.stack.pop();
+        Ok(())
+    }
+}
+
+fn find(parents: &mut [Net], net: Net) -> Net {
+    let mut root = net;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    let mut net = net;
+    while parents[net] != root {
+        let next = parents[net];
+        parents[net] = root;
+        net = next;
+    }
+    root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn build(chip: &str) -> Result<Netlist> {
+        let project = Path::new(env!("CARGO_MANIFEST_DIR")).join("..").join("01");
+        Netlist::build(&mut Library::new(vec![project]), chip)
+    }
+
+    fn nands(netlist: &Netlist) -> usize {
+        let nand = |c: &&Component| matches!(c, Component::Nand { .. });
+        netlist.components.iter().filter(nand).count()
+    }
+
+    #[test]
+    fn test_flatten() {
+        let netlist = build("Not").unwrap();
+        assert_eq!(nands(&netlist), 1);
+        let netlist = build("Mux16").unwrap();
+        assert_eq!(netlist.components.len(), nands(&netlist));
+        assert_eq!(netlist.inputs[0].1.len(), 16);
+        assert!(netlist.paths.iter().all(|path| path.starts_with("Mux#")));
+        assert!(netlist.paths.iter().any(|path| path.starts_with("Mux#16/")));
+    }
+
+    #[test]
+    fn test_builtins() {
+        // Not in 01, so the built-in chip is used.
+        let netlist = build("ALU").unwrap();
+        assert!(matches!(
+            netlist.components[..],
+            [Component::Builtin {
+                kind: Kind::ALU,
+                ..
+            }]
+        ));
+    }
+
+    fn build_source(source: &str) -> Result<Netlist> {
+        let chip = crate::parser::parse(source).unwrap();
+        let name = chip.name.clone();
+        let mut library = Library::new(vec![]);
+        library.add(chip);
+        Netlist::build(&mut library, &name)
+    }
+
+    fn build_error(parts: &str) -> String {
+        let source = format!(
+            "CHIP Foo {{ IN a, b[4]; OUT out, out4[4]; PARTS: {} }}",
+            parts
+        );
+        build_source(&source).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_sub_buses() {
+        let netlist = build_source(
+            "CHIP Foo {
+                IN a[4];
+                OUT out[2];
+                PARTS:
+                Not16(in[0..3]=a, in[4]=true, out[1..2]=out, out[3..5]=x);
+                Not(in=x[2], out=y);
+            }",
+        )
+        .unwrap();
+        let Component::Builtin {
+            inputs, outputs, ..
+        } = &netlist.components[0]
+        else {
+            panic!("expect Not16");
+        };
+        assert_eq!(inputs[0][..4], netlist.inputs[0].1[..]);
+        assert_eq!(inputs[0][4..6], [TRUE, FALSE]);
+        assert_eq!(outputs[0][1..3], netlist.outputs[0].1[..]);
+        assert_eq!(netlist.internals[0].1[..], outputs[0][3..6]);
+        let Component::Builtin { inputs, .. } = &netlist.components[1] else {
+            panic!("expect Not");
+        };
+        assert_eq!(inputs[0][0], outputs_of(&netlist, 0)[5]);
+    }
+
+    fn outputs_of(netlist: &Netlist, i: usize) -> Vec<Net> {
+        netlist.components[i].outputs().collect()
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            build_error("Not(in=b, out=out);"),
+            "[line 1] build error in chip `Foo`: width mismatch: `in` has 1 bits, connected to 4"
+        );
+        assert_eq!(
+            build_error("Not(in=x, out=out);"),
+            "[line 1] build error in chip `Foo`: pin `x` isn't driven by any part"
+        );
+        assert_eq!(
+            build_error("Not(in=a, out=a);"),
+            "[line 1] build error in chip `Foo`: can't drive input pin `a`"
+        );
+        assert_eq!(
+            build_error("Not(in=a, out=out); Not(in=out, out=x);"),
+            "[line 1] build error in chip `Foo`: can't read output pin `out`"
+        );
+        assert_eq!(
+            build_error("Not(in=a, out=out, foo=a);"),
+            "[line 1] build error in chip `Foo`: chip `Not` has no pin `foo`"
+        );
+        assert_eq!(
+            build_error("Not(in=b[4], out=out);"),
+            "[line 1] build error in chip `Foo`: `b` is 4 bits wide"
+        );
+        assert_eq!(
+            build_error("Not(in=a, out=out); Not(in=a, out=out);"),
+            "build error in chip `Foo`: `Not#1` and `Not#2` drive the same pin"
+        );
+        assert_eq!(
+            build_error("Not(in=y, out=x); Not(in=x, out=y);"),
+            "build error in chip `Foo`: combinational loop through `Not#2` -> `Not#1`"
+        );
+        // Loops through a DFF are fine.
+        assert!(build_source(
+            "CHIP Foo { IN a; OUT out; PARTS: Not(in=y, out=x); DFF(in=x, out=y, out=out); }"
+        )
+        .is_ok());
+        assert_eq!(
+            build_error("Bar(in=a);"),
+            "[line 1] build error in chip `Foo`: unknown chip `Bar`"
+        );
+    }
+
+    #[test]
+    fn test_unknown_chip() {
+        let error = build("Foo").unwrap_err();
+        assert_eq!(error.to_string(), "build error in chip `Foo`: unknown chip");
+    }
+}
@@ -0,0 +1,309 @@
+use crate::chip::{Body, Bus, Chip, Connection, Part, Pin, Position, Wire};
+use crate::error::{Error, Result};
+use crate::scanner::Scanner;
+use crate::token::Kind::*;
+use crate::token::{Kind, Token};
+
+/// Scans and parses the source of one `.hdl` file.
+pub fn parse(source: &str) -> Result<Chip> {
+    let mut scanner = Scanner::new(source);
+    let tokens = scanner.scan_tokens()?;
+    Parser::new(tokens).chip()
+}
+
+pub struct Parser<'s> {
+    tokens: &'s [Token<'s>],
+    current: usize,
+}
+
+impl<'s> Parser<'s> {
+    pub fn new(tokens: &'s [Token<'s>]) -> Self {
+        Self { tokens, current: 0 }
+    }
+
+    pub fn chip(&mut self) -> Result<Chip> {
+        self.keyword("CHIP")?;
+        let (name, position) = self.identifier("expect chip name")?;
+        self.consume(LeftBrace, "expect '{' after chip name")?;
+        let inputs = self.pins("IN")?;
+        let outputs = self.pins("OUT")?;
+        let mut clocked = vec![];
+        let body = match self.peek().kind {
+            Identifier("PARTS") => {
+                self.advance();
+                self.consume(Colon, "expect ':' after PARTS")?;
+                let mut parts = vec![];
+                while let Identifier(_) = self.peek().kind {
+                    parts.push(self.part()?);
+                }
+                Body::Parts(parts)
+            }
+            Identifier("BUILTIN") => {
+                self.advance();
+                let (builtin, _) = self.identifier("expect built-in chip name")?;
+                self.consume(Semicolon, "expect ';' after built-in chip name")?;
+                if self.peek().kind == Identifier("CLOCKED") {
+                    self.advance();
+                    clocked = self.names()?;
+                    self.consume(Semicolon, "expect ';' after clocked pins")?;
+                }
+                Body::Builtin(builtin)
+            }
+            _ => return Err(self.error(self.peek(), "expect PARTS or BUILTIN")),
+        };
+        self.consume(RightBrace, "expect '}' after chip body")?;
+        if self.peek().kind != Eof {
+            return Err(self.error(self.peek(), "expect end of file after chip"));
+        }
+        Ok(Chip {
+            name,
+            position,
+            inputs,
+            outputs,
+            body,
+            clocked,
+        })
+    }
+
+    /// An optional `IN` or `OUT` declaration.
+    fn pins(&mut self, keyword: &str) -> Result<Vec<Pin>> {
+        let mut pins = vec![];
+        if self.peek().kind != Identifier(keyword) {
+            return Ok(pins);
+        }
+        self.advance();
+        loop {
+            let (name, position) = self.identifier("expect pin name")?;
+            let mut width = 1;
+            if self.peek().kind == LeftBracket {
+                self.advance();
+                width = self.number("expect pin width")?;
+                if width == 0 || width > 16 {
+                    return Err(self.error(self.previous(), "expect width from 1 to 16"));
+                }
+                self.consume(RightBracket, "expect ']' after pin width")?;
+            }
+            pins.push(Pin {
+                name,
+                width,
+                position,
+            });
+            if self.peek().kind != Comma {
+                break;
+            }
+            self.advance();
+        }
+        self.consume(Semicolon, "expect ';' after pins")?;
+        Ok(pins)
+    }
+
+    fn names(&mut self) -> Result<Vec<String>> {
+        let mut names = vec![self.identifier("expect pin name")?.0];
+        while self.peek().kind == Comma {
+            self.advance();
+            names.push(self.identifier("expect pin name")?.0);
+        }
+        Ok(names)
+    }
+
+    fn part(&mut self) -> Result<Part> {
+        let (chip, position) = self.identifier("expect part name")?;
+        self.consume(LeftParen, "expect '(' after part name")?;
+        let mut connections = vec![];
+        loop {
+            let pin = self.bus()?;
+            self.consume(Equal, "expect '=' after pin")?;
+            let wire = match self.peek().kind {
+                Identifier(constant @ ("true" | "false")) => {
+                    let token = self.advance();
+                    Wire::Constant(constant == "true", position_of(token))
+                }
+                _ => Wire::Bus(self.bus()?),
+            };
+            connections.push(Connection { pin, wire });
+            if self.peek().kind != Comma {
+                break;
+            }
+            self.advance();
+        }
+        self.consume(RightParen, "expect ')' after connections")?;
+        self.consume(Semicolon, "expect ';' after part")?;
+        Ok(Part {
+            chip,
+            position,
+            connections,
+        })
+    }
+
+    fn bus(&mut self) -> Result<Bus> {
+        let (name, position) = self.identifier("expect pin name")?;
+        let mut range = None;
+        if self.peek().kind == LeftBracket {
+            self.advance();
+            let lo = self.number("expect bit index")?;
+            let mut hi = lo;
+            if self.peek().kind == DotDot {
+                self.advance();
+                hi = self.number("expect bit index")?;
+                if hi < lo {
+                    return Err(self.error(self.previous(), "expect a range from low to high"));
+                }
+            }
+            self.consume(RightBracket, "expect ']' after sub-bus")?;
+            range = Some((lo, hi));
+        }
+        Ok(Bus {
+            name,
+            range,
+            position,
+        })
+    }
+
+    fn keyword(&mut self, keyword: &str) -> Result<()> {
+        if self.peek().kind != Identifier(keyword) {
+            return Err(self.error(self.peek(), &format!("expect {}", keyword)));
+        }
+        self.advance();
+        Ok(())
+    }
+
+    fn identifier(&mut self, message: &str) -> Result<(String, Position)> {
+        let token = self.advance();
+        match token.kind {
+            Identifier(name) => Ok((name.to_string(), position_of(token))),
+            _ => Err(self.error(token, message)),
+        }
+    }
+
+    fn number(&mut self, message: &str) -> Result<usize> {
+        let token = self.advance();
+        match token.kind {
+            Number(n) => Ok(n),
+            _ => Err(self.error(token, message)),
+        }
+    }
+
+    fn consume(&mut self, kind: Kind, message: &str) -> Result<()> {
+        let token = self.advance();
+        if token.kind != kind {
+            return Err(self.error(token, message));
+        }
+        Ok(())
+    }
+
+    fn error(&self, token: &Token, message: &str) -> Error {
+        Error::Parse {
+            position: position_of(token),
+            lexeme: token.lexeme.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    fn is_at_end(&self) -> bool {
+        matches!(self.peek().kind, Eof)
+    }
+
+    fn advance(&mut self) -> &'s Token<'s> {
+        if !self.is_at_end() {
+            self.current += 1;
+        }
+        self.previous()
+    }
+
+    fn previous(&self) -> &'s Token<'s> {
+        &self.tokens[self.current - 1]
+    }
+
+    fn peek(&self) -> &'s Token<'s> {
+        &self.tokens[self.current]
+    }
+}
+
+fn position_of(token: &Token) -> Position {
+    Position {
+        line: token.line,
+        column: token.column,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn test_parts() {
+        let chip = parse(
+            "CHIP Foo {
+                IN a[16], sel;
+                OUT out[8], zr;
+                PARTS:
+                Mux16(a=a, b=false, sel=sel, out[0..7]=out, out[15]=zr);
+            }",
+        )
+        .unwrap();
+        assert_eq!(chip.name, "Foo");
+        let pins: Vec<_> = chip.inputs.iter().map(|p| (&p.name[..], p.width)).collect();
+        assert_eq!(pins, [("a", 16), ("sel", 1)]);
+        let Body::Parts(parts) = &chip.body else {
+            panic!("expect parts");
+        };
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].chip, "Mux16");
+        assert_eq!(
+            parts[0].position,
+            Position {
+                line: 5,
+                column: 17
+            }
+        );
+        let connections = &parts[0].connections;
+        assert!(matches!(connections[1].wire, Wire::Constant(false, _)));
+        assert_eq!(connections[3].pin.range, Some((0, 7)));
+        assert_eq!(connections[4].pin.range, Some((15, 15)));
+    }
+
+    #[test]
+    fn test_builtin() {
+        let chip = parse("CHIP DFF { IN in; OUT out; BUILTIN DFF; CLOCKED in; }").unwrap();
+        assert!(matches!(chip.body, Body::Builtin(ref name) if name == "DFF"));
+        assert!(chip.is_clocked("in"));
+        let chip = parse("CHIP Keyboard { OUT out[16]; BUILTIN Keyboard; }").unwrap();
+        assert!(chip.inputs.is_empty());
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = parse("CHIP Foo { IN a; PARTS: Not(in=a out=b); }").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "[line 1] parse error at `out`: expect ')' after connections"
+        );
+        let error = parse("CHIP Foo { IN a[17]; PARTS: }").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "[line 1] parse error at `17`: expect width from 1 to 16"
+        );
+        let error = parse("CHIP Foo { IN a; PARTS: Not(in=a[3..1]); }").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "[line 1] parse error at `1`: expect a range from low to high"
+        );
+    }
+
+    #[test]
+    fn test_project_chips() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+        for project in ["01", "02", "03/a", "03/b", "05"] {
+            for entry in fs::read_dir(root.join(project)).unwrap() {
+                let path = entry.unwrap().path();
+                if path.extension().is_some_and(|ext| ext == "hdl") {
+                    let source = fs::read_to_string(&path).unwrap();
+                    let chip = parse(&source).unwrap_or_else(|e| panic!("{:?}: {}", path, e));
+                    assert_eq!(chip.name, path.file_stem().unwrap().to_str().unwrap());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use crate::chip::Position;
+use crate::error::{Error, Result};
+use crate::token::{Kind, Token};
+
+pub struct Scanner<'s> {
+    source: &'s str,
+    current: usize,
+    start: usize,
+    line: usize,
+    line_start: usize,
+    tokens: Vec<Token<'s>>,
+}
+
+impl<'s> Scanner<'s> {
+    pub fn new(source: &'s str) -> Self {
+        Self {
+            source,
+            current: 0,
+            start: 0,
+            line: 1,
+            line_start: 0,
+            tokens: vec![],
+        }
+    }
+
+    pub fn scan_tokens(&mut self) -> Result<&[Token<'s>]> {
+        while !self.is_at_end() {
+            self.start = self.current;
+            self.scan_token()?;
+        }
+        self.start = self.current;
+        let eof = Token::new(Kind::Eof, self.line, self.column(), "");
+        self.tokens.push(eof);
+        Ok(&self.tokens)
+    }
+
+    fn scan_token(&mut self) -> Result<()> {
+        let c = self.advance();
+        use Kind::*;
+        match c {
+            '\n' => self.line_break(),
+            '{' => self.add_token(LeftBrace),
+            '}' => self.add_token(RightBrace),
+            '(' => self.add_token(LeftParen),
+            ')' => self.add_token(RightParen),
+            '[' => self.add_token(LeftBracket),
+            ']' => self.add_token(RightBracket),
+            ',' => self.add_token(Comma),
+            ';' => self.add_token(Semicolon),
+            ':' => self.add_token(Colon),
+            '=' => self.add_token(Equal),
+            '.' if self.peek() == Some('.') => {
+                self.advance();
+                self.add_token(DotDot);
+            }
+            '/' => match self.peek() {
+                Some('/') => {
+                    while !self.is_at_end() && self.peek() != Some('\n') {
+                        self.advance();
+                    }
+                }
+                Some('*') => self.block_comment()?,
+                _ => return Err(self.error("expect '//' or '/*'")),
+            },
+            '0'..='9' => self.number()?,
+            'a'..='z' | 'A'..='Z' | '_' => self.identifier(),
+            ' ' | '\r' | '\t' => (),
+            _ => return Err(self.error("unexpected character")),
+        }
+        Ok(())
+    }
+
+    fn block_comment(&mut self) -> Result<()> {
+        let (position, start) = (self.position(), self.start);
+        self.advance(); // The asterisk
+        loop {
+            match self.peek() {
+                None => {
+                    return Err(Error::Scan {
+                        position,
+                        lexeme: self.source[start..start + 2].to_string(),
+                        message: "unterminated block comment".to_string(),
+                    });
+                }
+                Some('*') if self.source[self.current..].starts_with("*/") => {
+                    self.advance();
+                    self.advance();
+                    return Ok(());
+                }
+                Some('\n') => {
+                    self.advance();
+                    self.line_break();
+                }
+                Some(_) => {
+                    self.advance();
+                }
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<()> {
+        while matches!(self.peek(), Some('0'..='9')) {
+            self.advance();
+        }
+        let n = self
+            .lexeme()
+            .parse()
+            .map_err(|_| self.error("invalid number"))?;
+        self.add_token(Kind::Number(n));
+        Ok(())
+    }
+
+    fn identifier(&mut self) {
+        while matches!(self.peek(), Some('0'..='9' | 'a'..='z' | 'A'..='Z' | '_')) {
+            self.advance();
+        }
+        self.add_token(Kind::Identifier(self.lexeme()));
+    }
+
+    fn line_break(&mut self) {
+        self.line += 1;
+        self.line_start = self.current;
+    }
+
+    fn add_token(&mut self, kind: Kind<'s>) {
+        let token = Token::new(kind, self.line, self.column(), self.lexeme());
+        self.tokens.push(token);
+    }
+
+    fn error(&self, message: &str) -> Error {
+        Error::Scan {
+            position: self.position(),
+            lexeme: self.lexeme().to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    fn position(&self) -> Position {
+        Position {
+            line: self.line,
+            column: self.column(),
+        }
+    }
+
+    /// The column where the current lexeme starts, counting from 1.
+    fn column(&self) -> usize {
+        self.source[self.line_start..self.start].chars().count() + 1
+    }
+
+    fn advance(&mut self) -> char {
+        let c = self.source[self.current..]
+            .chars()
+            .next()
+            .expect("should have next char");
+        self.current += c.len_utf8();
+        c
+    }
+
+    fn lexeme(&self) -> &'s str {
+        &self.source[self.start..self.current]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.source[self.current..].chars().next()
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.current >= self.source.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<String> {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().unwrap();
+        tokens.iter().map(|t| format!("{:?}", t.kind)).collect()
+    }
+
+    #[test]
+    fn test_tokens() {
+        assert_eq!(
+            kinds("Not16(in=a[0..7]);"),
+            [
+                "Identifier(\"Not16\")",
+                "LeftParen",
+                "Identifier(\"in\")",
+                "Equal",
+                "Identifier(\"a\")",
+                "LeftBracket",
+                "Number(0)",
+                "DotDot",
+                "Number(7)",
+                "RightBracket",
+                "RightParen",
+                "Semicolon",
+                "Eof"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_comments() {
+        assert_eq!(
+            kinds("/** doc\n * more */ IN // in\n"),
+            ["Identifier(\"IN\")", "Eof"]
+        );
+    }
+
+    #[test]
+    fn test_positions() {
+        let mut scanner = Scanner::new("CHIP A {\n  /* x\n */ IN a;");
+        let tokens = scanner.scan_tokens().unwrap();
+        let positions: Vec<_> = tokens.iter().map(|t| (t.line, t.column)).collect();
+        assert_eq!(
+            positions,
+            [(1, 1), (1, 6), (1, 8), (3, 5), (3, 8), (3, 9), (3, 10)]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut scanner = Scanner::new("a.b");
+        let error = scanner.scan_tokens().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "[line 1] scan error at `.`: unexpected character"
+        );
+        let mut scanner = Scanner::new("\n/* a");
+        let error = scanner.scan_tokens().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "[line 2] scan error at `/*`: unterminated block comment"
+        );
+    }
+}
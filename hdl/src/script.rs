@@ -0,0 +1,226 @@
+use crate::library::Library;
+use crate::netlist::Netlist;
+use crate::simulator::Simulator;
+use assembler::check::parse_hack;
+use std::fs;
+use std::path::{Path, PathBuf};
+use test_script::output::Value;
+use test_script::runner;
+
+/// The hardware simulator dialect of test scripts: loads a chip, sets its
+/// inputs, and reads its pins after `eval`, `tick` or `tock`.
+#[derive(Default)]
+pub struct HardwareSimulator {
+    pub simulator: Option<Simulator>,
+    dir: PathBuf,
+    /// Clock cycles so far, and whether the current one is halfway through.
+    time: u64,
+    tick: bool,
+}
+
+impl HardwareSimulator {
+    fn simulator(&self) -> Result<&Simulator, String> {
+        self.simulator
+            .as_ref()
+            .ok_or_else(|| "no chip loaded".to_string())
+    }
+
+    fn simulator_mut(&mut self) -> Result<&mut Simulator, String> {
+        self.simulator
+            .as_mut()
+            .ok_or_else(|| "no chip loaded".to_string())
+    }
+}
+
+impl runner::Simulator for HardwareSimulator {
+    fn load(&mut self, dir: &Path, file: Option<&str>) -> Result<(), String> {
+        let file = file.ok_or("expect a chip to load")?;
+        let name = file
+            .strip_suffix(".hdl")
+            .ok_or_else(|| format!("expect a .hdl file, found `{}`", file))?;
+        let mut library = Library::new(vec![dir.to_path_buf()]);
+        let netlist = Netlist::build(&mut library, name).map_err(|e| e.to_string())?;
+        self.simulator = Some(Simulator::new(netlist));
+        self.dir = dir.to_path_buf();
+        self.time = 0;
+        self.tick = false;
+        Ok(())
+    }
+
+    fn get(&self, name: &str) -> Result<Value, String> {
+        if name == "time" {
+            let plus = if self.tick { "+" } else { "" };
+            return Ok(Value::Text(format!("{}{}", self.time, plus)));
+        }
+        let word = match state(name)? {
+            Some((chip, index)) => self.simulator()?.state(chip, index)?,
+            None => self.simulator()?.get(name)?,
+        };
+        Ok(Value::Number(word as i16 as i64))
+    }
+
+    fn set(&mut self, name: &str, value: i64) -> Result<(), String> {
+        match state(name)? {
+            Some((chip, index)) => self.simulator_mut()?.set_state(chip, index, value as u16),
+            None => self.simulator_mut()?.set(name, value as u16),
+        }
+    }
+
+    fn command(&mut self, words: &[String]) -> Result<(), String> {
+        let words: Vec<&str> = words.iter().map(String::as_str).collect();
+        match words[..] {
+            ["eval"] => self.simulator_mut()?.eval(),
+            ["tick"] => {
+                self.simulator_mut()?.tick();
+                self.tick = true;
+            }
+            ["tock"] => {
+                self.simulator_mut()?.tock();
+                self.tick = false;
+                self.time += 1;
+            }
+            [chip, "load", file] => {
+                let path = self.dir.join(file);
+                let source = fs::read_to_string(&path)
+                    .map_err(|e| format!("can't read `{}`: {}", file, e))?;
+                let words = parse_hack(&source)?;
+                let memory = self.simulator_mut()?.memory_mut(chip)?;
+                if words.len() > memory.len() {
+                    return Err(format!("`{}` doesn't fit in {}", file, chip));
+                }
+                memory.fill(0);
+                memory[..words.len()].copy_from_slice(&words);
+            }
+            _ => return Err(format!("unknown command `{}`", words.join(" "))),
+        }
+        Ok(())
+    }
+}
+
+/// Splits `Chip[i]` or `Chip[]`, which name the state of a built-in part.
+fn state(name: &str) -> Result<Option<(&str, Option<usize>)>, String> {
+    let Some((chip, rest)) = name.split_once('[') else {
+        return Ok(None);
+    };
+    let index = rest
+        .strip_suffix(']')
+        .ok_or_else(|| format!("expect ']' in `{}`", name))?;
+    if index.is_empty() {
+        return Ok(Some((chip, None)));
+    }
+    let index = index
+        .parse()
+        .map_err(|_| format!("invalid index in `{}`", name))?;
+    Ok(Some((chip, Some(index))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use test_script::runner::Runner;
+
+    /// Holds down the keys that test scripts ask for, since nobody's there
+    /// to do it.
+    #[derive(Default)]
+    struct Typist(HardwareSimulator);
+
+    impl runner::Simulator for Typist {
+        fn load(&mut self, dir: &Path, file: Option<&str>) -> Result<(), String> {
+            self.0.load(dir, file)
+        }
+
+        fn get(&self, name: &str) -> Result<Value, String> {
+            self.0.get(name)
+        }
+
+        fn set(&mut self, name: &str, value: i64) -> Result<(), String> {
+            self.0.set(name, value)
+        }
+
+        fn command(&mut self, words: &[String]) -> Result<(), String> {
+            self.0.command(words)
+        }
+
+        fn echo(&mut self, message: &str) {
+            let key = message
+                .split('\'')
+                .nth(1)
+                .and_then(|key| key.chars().next());
+            if let Some(key) = key.filter(|_| message.to_lowercase().contains("hold down")) {
+                self.0.set("Keyboard[0]", key as i64).unwrap();
+            }
+        }
+    }
+
+    /// Runs every test script in a project directory against its `.cmp`.
+    fn run_project(project: &str) {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("..")
+            .join(project);
+        let mut scripts: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "tst"))
+            .collect();
+        scripts.sort();
+        assert!(!scripts.is_empty());
+        for path in scripts {
+            let script = fs::read_to_string(&path).unwrap();
+            let mut runner = Runner::new(Typist::default(), &dir);
+            if let Err(e) = runner.run(&script) {
+                panic!("{}: {}", path.display(), e);
+            }
+        }
+    }
+
+    #[test]
+    fn test_project_01() {
+        run_project("01");
+    }
+
+    #[test]
+    fn test_project_02() {
+        run_project("02");
+    }
+
+    #[test]
+    fn test_project_03_a() {
+        run_project("03/a");
+    }
+
+    #[test]
+    fn test_project_03_b() {
+        run_project("03/b");
+    }
+
+    #[test]
+    fn test_project_05() {
+        run_project("05");
+    }
+
+    #[test]
+    fn test_state() {
+        assert_eq!(state("out"), Ok(None));
+        assert_eq!(state("PC[]"), Ok(Some(("PC", None))));
+        assert_eq!(state("RAM16K[12]"), Ok(Some(("RAM16K", Some(12)))));
+        assert!(state("RAM16K[x]").is_err());
+    }
+
+    #[test]
+    fn test_mismatch() {
+        let dir = std::env::temp_dir().join(format!("hdl-mismatch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("Not.cmp"), "|in |out|\n| 0 | 1 |\n| 1 | 1 |\n").unwrap();
+        let script = "load Not.hdl, compare-to Not.cmp, output-list in%B1.1.1 out%B1.1.1;
+            set in 0, eval, output;
+            set in 1, eval, output;";
+        let mut runner = Runner::new(HardwareSimulator::default(), &dir);
+        let error = runner.run(script).unwrap_err();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            error.to_string(),
+            "comparison failure at line 3, column `out`: expected `| 1 | 1 |`, found `| 1 | 0 |`"
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use crate::builtin::Kind;
+use crate::netlist::{Component, Net, Netlist, TRUE};
+
+/// Evaluates a netlist, holding the value of every net and the state of
+/// every clocked built-in chip.
+pub struct Simulator {
+    pub netlist: Netlist,
+    values: Vec<bool>,
+    states: Vec<Vec<u16>>,
+    sequential: Vec<usize>,
+}
+
+impl Simulator {
+    pub fn new(netlist: Netlist) -> Self {
+        let mut values = vec![false; netlist.nets];
+        values[TRUE] = true;
+        let mut states = vec![];
+        let mut sequential = vec![];
+        for (i, component) in netlist.components.iter().enumerate() {
+            let kind = match component {
+                Component::Builtin { kind, .. } => Some(*kind),
+                Component::Nand { .. } => None,
+            };
+            states.push(vec![0; kind.map_or(0, Kind::state_size)]);
+            if kind.is_some_and(Kind::is_sequential) {
+                sequential.push(i);
+            }
+        }
+        let mut simulator = Self {
+            netlist,
+            values,
+            states,
+            sequential,
+        };
+        simulator.eval();
+        simulator
+    }
+
+    /// Propagates the inputs through every component.
+    pub fn eval(&mut self) {
+        let values = &mut self.values;
+        for &i in &self.netlist.order {
+            match &self.netlist.components[i] {
+                Component::Nand { a, b, out } => values[*out] = !(values[*a] && values[*b]),
+                Component::Builtin {
+                    kind,
+                    inputs,
+                    outputs,
+                } => {
+                    let mut ins = [0; 9];
+                    let ins = read_all(values, inputs, &mut ins);
+                    let mut outs = [0; 8];
+                    let outs = &mut outs[..outputs.len()];
+                    kind.eval(&self.states[i], ins, outs);
+                    for (&value, nets) in outs.iter().zip(outputs) {
+                        write(values, nets, value);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The rising edge of the clock: clocked chips sample their inputs, but
+    /// their outputs don't change until `tock`.
+    pub fn tick(&mut self) {
+        self.eval();
+        for &i in &self.sequential {
+            if let Component::Builtin { kind, inputs, .. } = &self.netlist.components[i] {
+                let mut ins = [0; 9];
+                let ins = read_all(&self.values, inputs, &mut ins);
+                kind.tick(&mut self.states[i], ins);
+            }
+        }
+    }
+
+    /// The falling edge of the clock: clocked chips show their new state.
+    pub fn tock(&mut self) {
+        for &i in &self.sequential {
+            if let Component::Builtin { kind, .. } = &self.netlist.components[i] {
+                kind.tock(&mut self.states[i]);
+            }
+        }
+        self.eval();
+    }
+
+    /// Reads the state of a built-in part as `Chip[i]`, like `RAM16K[3]` or
+    /// `PC[]`. The first part made of that chip is used.
+    pub fn state(&self, chip: &str, index: Option<usize>) -> Result<u16, String> {
+        let (kind, i) = self.builtin(chip)?;
+        kind.read_state(&self.states[i], index).ok_or_else(|| {
+            format!(
+                "no `{}[{}]`",
+                chip,
+                index.map_or(String::new(), |i| i.to_string())
+            )
+        })
+    }
+
+    pub fn set_state(
+        &mut self,
+        chip: &str,
+        index: Option<usize>,
+        value: u16,
+    ) -> Result<(), String> {
+        let (kind, i) = self.builtin(chip)?;
+        if kind.write_state(&mut self.states[i], index, value) {
+            Ok(())
+        } else {
+            Err(format!(
+                "no `{}[{}]`",
+                chip,
+                index.map_or(String::new(), |i| i.to_string())
+            ))
+        }
+    }
+
+    /// The whole state of a built-in part, like the contents of a ROM32K.
+    pub fn memory_mut(&mut self, chip: &str) -> Result<&mut [u16], String> {
+        let (_, i) = self.builtin(chip)?;
+        Ok(&mut self.states[i])
+    }
+
+    fn builtin(&self, chip: &str) -> Result<(Kind, usize), String> {
+        self.netlist
+            .components
+            .iter()
+            .enumerate()
+            .find_map(|(i, component)| match component {
+                Component::Builtin { kind, .. } if format!("{:?}", kind) == chip => {
+                    Some((*kind, i))
+                }
+                _ => None,
+            })
+            .ok_or_else(|| format!("no built-in `{}` part", chip))
+    }
+
+    /// Sets an input pin. Takes effect on the next `eval`.
+    pub fn set(&mut self, name: &str, value: u16) -> Result<(), String> {
+        let (_, nets) = self
+            .netlist
+            .inputs
+            .iter()
+            .find(|(pin, _)| pin == name)
+            .ok_or_else(|| format!("unknown input pin `{}`", name))?;
+        write(&mut self.values, nets, value);
+        Ok(())
+    }
+
+    /// Reads an input, output or internal pin of the top-level chip.
+    pub fn get(&self, name: &str) -> Result<u16, String> {
+        let netlist = &self.netlist;
+        let (_, nets) = netlist
+            .inputs
+            .iter()
+            .chain(&netlist.outputs)
+            .chain(&netlist.internals)
+            .find(|(pin, _)| pin == name)
+            .ok_or_else(|| format!("unknown pin `{}`", name))?;
+        Ok(read(&self.values, nets))
+    }
+
+    pub fn width(&self, name: &str) -> Option<usize> {
+        let netlist = &self.netlist;
+        netlist
+            .inputs
+            .iter()
+            .chain(&netlist.outputs)
+            .chain(&netlist.internals)
+            .find(|(pin, _)| pin == name)
+            .map(|(_, nets)| nets.len())
+    }
+}
+
+fn read_all<'w>(values: &[bool], pins: &[Vec<Net>], words: &'w mut [u16; 9]) -> &'w [u16] {
+    for (word, nets) in words.iter_mut().zip(pins) {
+        *word = read(values, nets);
+    }
+    &words[..pins.len()]
+}
+
+fn read(values: &[bool], nets: &[Net]) -> u16 {
+    nets.iter()
+        .enumerate()
+        .fold(0, |word, (i, &net)| word | (values[net] as u16) << i)
+}
+
+fn write(values: &mut [bool], nets: &[Net], word: u16) {
+    for (i, &net) in nets.iter().enumerate() {
+        values[net] = word & (1 << i) != 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::Library;
+    use std::path::Path;
+
+    fn simulate(project: &str, chip: &str) -> Simulator {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("..")
+            .join(project);
+        let netlist = Netlist::build(&mut Library::new(vec![dir]), chip).unwrap();
+        Simulator::new(netlist)
+    }
+
+    #[test]
+    fn test_xor() {
+        let mut xor = simulate("01", "Xor");
+        for (a, b) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+            xor.set("a", a).unwrap();
+            xor.set("b", b).unwrap();
+            xor.eval();
+            assert_eq!(xor.get("out").unwrap(), a ^ b);
+        }
+    }
+
+    #[test]
+    fn test_mux8way16() {
+        let mut mux = simulate("01", "Mux8Way16");
+        for (i, pin) in ["a", "b", "c", "d", "e", "f", "g", "h"].iter().enumerate() {
+            mux.set(pin, 1000 + i as u16).unwrap();
+        }
+        for sel in 0..8 {
+            mux.set("sel", sel).unwrap();
+            mux.eval();
+            assert_eq!(mux.get("out").unwrap(), 1000 + sel);
+        }
+    }
+
+    #[test]
+    fn test_alu() {
+        let mut alu = simulate("02", "ALU");
+        let inputs = [("x", 7), ("y", 3), ("zx", 0), ("nx", 1)];
+        let inputs = inputs
+            .into_iter()
+            .chain([("zy", 0), ("ny", 0), ("f", 1), ("no", 1)]);
+        for (pin, value) in inputs {
+            alu.set(pin, value).unwrap();
+        }
+        alu.eval();
+        assert_eq!(alu.get("out").unwrap(), 4);
+        assert_eq!(alu.get("zr").unwrap(), 0);
+        assert_eq!(alu.get("ng").unwrap(), 0);
+        alu.set("y", 9).unwrap();
+        alu.eval();
+        assert_eq!(alu.get("out").unwrap() as i16, -2);
+        assert_eq!(alu.get("ng").unwrap(), 1);
+        assert_eq!(alu.width("out"), Some(16));
+    }
+
+    #[test]
+    fn test_bit() {
+        let mut bit = simulate("03/a", "Bit");
+        bit.set("in", 1).unwrap();
+        bit.set("load", 1).unwrap();
+        bit.tick();
+        assert_eq!(bit.get("out").unwrap(), 0);
+        bit.tock();
+        assert_eq!(bit.get("out").unwrap(), 1);
+        bit.set("in", 0).unwrap();
+        bit.set("load", 0).unwrap();
+        bit.tick();
+        bit.tock();
+        assert_eq!(bit.get("out").unwrap(), 1);
+        assert_eq!(bit.state("DFF", None), Ok(1));
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut not = simulate("01", "Not");
+        assert_eq!(
+            not.set("out", 1),
+            Err("unknown input pin `out`".to_string())
+        );
+        assert_eq!(not.get("foo"), Err("unknown pin `foo`".to_string()));
+    }
+}
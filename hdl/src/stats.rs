@@ -0,0 +1,421 @@
+use crate::builtin::Kind;
+use crate::chip::{Body, Chip, Position, Wire};
+use crate::error::{Error, Result};
+use crate::library::Library;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+/// What a chip costs once every part is expanded down to `Nand` gates.
+#[derive(Debug)]
+pub struct Stats {
+    pub nands: usize,
+    /// Parts without HDL other than `Nand`, such as `DFF`, by name.
+    pub builtins: BTreeMap<String, usize>,
+    /// The longest combinational path in gate delays, counting paths that
+    /// start or end at the chip's pins or at its flip-flops.
+    pub depth: usize,
+    timing: Timing,
+}
+
+/// Gate delays between the bits of a chip's pins, numbered in the order the
+/// pins are declared. Bits of built-in chips other than `Nand` count as no
+/// delay, since nothing is known about how they're built.
+#[derive(Debug)]
+struct Timing {
+    /// For each output bit, the longest path from each input bit, followed
+    /// by the longest path from inside a flip-flop.
+    delays: Vec<Vec<Option<usize>>>,
+    /// For each input bit, the longest path into a flip-flop.
+    to_state: Vec<Option<usize>>,
+    /// The longest path from a flip-flop to a flip-flop.
+    internal: Option<usize>,
+}
+
+impl Timing {
+    fn depth(&self) -> usize {
+        let delays = self.delays.iter().flatten();
+        let paths = delays.chain(&self.to_state).chain([&self.internal]);
+        paths.flatten().copied().max().unwrap_or(0)
+    }
+}
+
+/// Computes [`Stats`] chip by chip, reusing the results for every part
+/// using the same chip, so even `Computer` is quick to analyze.
+pub struct Analyzer<'l> {
+    library: &'l mut Library,
+    stats: HashMap<String, Rc<Stats>>,
+    /// Chips being analyzed, to catch chips that contain themselves.
+    stack: Vec<String>,
+}
+
+impl<'l> Analyzer<'l> {
+    pub fn new(library: &'l mut Library) -> Self {
+        Self {
+            library,
+            stats: HashMap::new(),
+            stack: vec![],
+        }
+    }
+
+    pub fn stats(&mut self, name: &str) -> Result<Rc<Stats>> {
+        if let Some(stats) = self.stats.get(name) {
+            return Ok(Rc::clone(stats));
+        }
+        let chip = self
+            .library
+            .chip(name)?
+            .ok_or_else(|| Error::build(name, Position::default(), "unknown chip"))?;
+        let stats = match &chip.body {
+            // The registers of the CPU are plain registers with a different
+            // look in the official simulator, so count them as such.
+            Body::Builtin(builtin) if matches!(&builtin[..], "ARegister" | "DRegister") => {
+                match self.library.chip("Register")? {
+                    Some(register) if matches!(register.body, Body::Parts(_)) => {
+                        self.stats("Register")?
+                    }
+                    _ => Rc::new(builtin_stats(&chip, builtin)?),
+                }
+            }
+            Body::Builtin(builtin) => Rc::new(builtin_stats(&chip, builtin)?),
+            Body::Parts(_) => {
+                if self.stack.contains(&chip.name) {
+                    let message = "chip contains itself";
+                    return Err(Error::build(&chip.name, chip.position, message));
+                }
+                self.stack.push(chip.name.clone());
+                let stats = self.compose(&chip)?;
+                self.stack.pop();
+                Rc::new(stats)
+            }
+        };
+        self.stats.insert(name.to_string(), Rc::clone(&stats));
+        Ok(stats)
+    }
+
+    /// Adds up the parts, and follows the paths through them bit by bit.
+    fn compose(&mut self, chip: &Chip) -> Result<Stats> {
+        let definitions = self.library.parts(chip)?;
+        let mut parts = vec![];
+        for definition in &definitions {
+            parts.push(self.stats(&definition.name)?);
+        }
+        let error = |position, message: String| Error::build(&chip.name, position, message);
+
+        // Every bit driving something is a node: the chip's input bits,
+        // then the output bits of each part.
+        let input_bits: usize = chip.inputs.iter().map(|pin| pin.width).sum();
+        let mut nodes = 0;
+        let mut wires: HashMap<&str, Vec<Option<usize>>> = HashMap::new();
+        for pin in &chip.inputs {
+            wires.insert(&pin.name, (nodes..nodes + pin.width).map(Some).collect());
+            nodes += pin.width;
+        }
+        for pin in &chip.outputs {
+            wires.insert(&pin.name, vec![None; pin.width]);
+        }
+        for (name, width) in chip.internal_pins(&definitions) {
+            wires.insert(name, vec![None; width]);
+        }
+        let mut first_outputs = vec![];
+        for (part, definition) in chip.parts().iter().zip(&definitions) {
+            first_outputs.push(nodes);
+            let offsets = offsets(
+                &definition
+                    .outputs
+                    .iter()
+                    .map(|p| p.width)
+                    .collect::<Vec<_>>(),
+            );
+            for connection in &part.connections {
+                let Some((index, pin)) = definition.output(&connection.pin.name) else {
+                    continue;
+                };
+                let Wire::Bus(wire) = &connection.wire else {
+                    continue;
+                };
+                let (Some(bits), Some(driven)) = (
+                    connection.pin.bits(pin.width),
+                    wires.get_mut(&wire.name[..]),
+                ) else {
+                    let message = format!("can't connect `{}`", connection.pin.name);
+                    return Err(error(connection.pin.position, message));
+                };
+                let Some(wire_bits) = wire.bits(driven.len()) else {
+                    let message = format!("`{}` is {} bits wide", wire.name, driven.len());
+                    return Err(error(wire.position, message));
+                };
+                for (bit, wire_bit) in bits.zip(wire_bits) {
+                    driven[wire_bit] = Some(nodes + offsets[index] + bit);
+                }
+            }
+            nodes += definition.outputs.iter().map(|p| p.width).sum::<usize>();
+        }
+
+        // The node driving each input bit of each part.
+        let mut sources: Vec<Vec<Option<usize>>> = vec![];
+        for (part, definition) in chip.parts().iter().zip(&definitions) {
+            let offsets = offsets(
+                &definition
+                    .inputs
+                    .iter()
+                    .map(|p| p.width)
+                    .collect::<Vec<_>>(),
+            );
+            let width = definition.inputs.iter().map(|p| p.width).sum();
+            let mut part_sources = vec![None; width];
+            for connection in &part.connections {
+                let Some((index, pin)) = definition.input(&connection.pin.name) else {
+                    continue;
+                };
+                let Wire::Bus(wire) = &connection.wire else {
+                    continue;
+                };
+                let Some(driven) = wires.get(&wire.name[..]) else {
+                    let message = format!("pin `{}` isn't driven by any part", wire.name);
+                    return Err(error(wire.position, message));
+                };
+                let (Some(bits), Some(wire_bits)) =
+                    (connection.pin.bits(pin.width), wire.bits(driven.len()))
+                else {
+                    let message = format!("can't connect `{}`", connection.pin.name);
+                    return Err(error(connection.pin.position, message));
+                };
+                for (bit, wire_bit) in bits.zip(wire_bits) {
+                    part_sources[offsets[index] + bit] = driven[wire_bit];
+                }
+            }
+            sources.push(part_sources);
+        }
+
+        // Edges from the bits read by a part to the bits it outputs.
+        let mut edges: Vec<Vec<(usize, usize)>> = vec![vec![]; nodes];
+        let mut pending = vec![0; nodes];
+        let mut owners = vec![None; nodes];
+        for (p, stats) in parts.iter().enumerate() {
+            for (o, delays) in stats.timing.delays.iter().enumerate() {
+                let node = first_outputs[p] + o;
+                owners[node] = Some(p);
+                for (i, delay) in delays[..delays.len() - 1].iter().enumerate() {
+                    if let (Some(delay), Some(source)) = (delay, sources[p][i]) {
+                        edges[source].push((node, *delay));
+                        pending[node] += 1;
+                    }
+                }
+            }
+        }
+
+        // Longest paths from each input bit, and from flip-flops, in the
+        // order the bits settle in.
+        let width = input_bits + 1;
+        let mut arrivals = vec![vec![None; width]; nodes];
+        for (i, arrival) in arrivals.iter_mut().enumerate().take(input_bits) {
+            arrival[i] = Some(0);
+        }
+        for (p, stats) in parts.iter().enumerate() {
+            for (o, delays) in stats.timing.delays.iter().enumerate() {
+                arrivals[first_outputs[p] + o][input_bits] = delays[delays.len() - 1];
+            }
+        }
+        let mut ready: Vec<usize> = (0..nodes).filter(|&n| pending[n] == 0).collect();
+        let mut settled = 0;
+        while let Some(node) = ready.pop() {
+            settled += 1;
+            let settled_arrivals = arrivals[node].clone();
+            for &(next, delay) in &edges[node] {
+                for (later, arrival) in arrivals[next].iter_mut().zip(&settled_arrivals) {
+                    if let Some(arrival) = arrival {
+                        *later = (*later).max(Some(arrival + delay));
+                    }
+                }
+                pending[next] -= 1;
+                if pending[next] == 0 {
+                    ready.push(next);
+                }
+            }
+        }
+        if settled < nodes {
+            let node = (0..nodes).find(|&n| pending[n] > 0).unwrap();
+            let part = &chip.parts()[owners[node].unwrap()];
+            let message = format!("combinational loop through `{}`", part.chip);
+            return Err(error(part.position, message));
+        }
+
+        let mut to_state = vec![None; input_bits];
+        let mut internal = None;
+        for (p, stats) in parts.iter().enumerate() {
+            internal = internal.max(stats.timing.internal);
+            for (i, delay) in stats.timing.to_state.iter().enumerate() {
+                let (Some(delay), Some(source)) = (delay, sources[p][i]) else {
+                    continue;
+                };
+                for (k, arrival) in arrivals[source].iter().enumerate() {
+                    let Some(arrival) = arrival else {
+                        continue;
+                    };
+                    match to_state.get_mut(k) {
+                        Some(path) => *path = (*path).max(Some(arrival + delay)),
+                        None => internal = internal.max(Some(arrival + delay)),
+                    }
+                }
+            }
+        }
+        let mut delays = vec![];
+        for pin in &chip.outputs {
+            for node in &wires[&pin.name[..]] {
+                delays.push(match node {
+                    Some(node) => arrivals[*node].clone(),
+                    None => vec![None; width],
+                });
+            }
+        }
+
+        let mut builtins = BTreeMap::new();
+        for stats in &parts {
+            for (name, count) in &stats.builtins {
+                *builtins.entry(name.clone()).or_default() += count;
+            }
+        }
+        let timing = Timing {
+            delays,
+            to_state,
+            internal,
+        };
+        Ok(Stats {
+            nands: parts.iter().map(|stats| stats.nands).sum(),
+            builtins,
+            depth: timing.depth(),
+            timing,
+        })
+    }
+}
+
+/// Where each pin starts, counting bits.
+fn offsets(widths: &[usize]) -> Vec<usize> {
+    let mut offset = 0;
+    widths
+        .iter()
+        .map(|width| {
+            offset += width;
+            offset - width
+        })
+        .collect()
+}
+
+fn builtin_stats(chip: &Chip, builtin: &str) -> Result<Stats> {
+    let kind = Kind::from_name(builtin).ok_or_else(|| {
+        let message = format!("unknown built-in chip `{}`", builtin);
+        Error::build(&chip.name, chip.position, message)
+    })?;
+    let input_bits: usize = chip.inputs.iter().map(|pin| pin.width).sum();
+    let output_bits: usize = chip.outputs.iter().map(|pin| pin.width).sum();
+    let delay = if kind == Kind::Nand { 1 } else { 0 };
+    let mut combinational = vec![];
+    for (i, pin) in chip.inputs.iter().enumerate() {
+        combinational.extend(vec![!kind.is_clocked(i); pin.width]);
+    }
+    let mut output = combinational
+        .iter()
+        .map(|&c| c.then_some(delay))
+        .collect::<Vec<_>>();
+    output.push(kind.is_sequential().then_some(0));
+    let sequential = kind.is_sequential().then_some(0);
+    let timing = Timing {
+        delays: vec![output; output_bits],
+        to_state: vec![sequential; input_bits],
+        internal: sequential,
+    };
+    let mut builtins = BTreeMap::new();
+    if kind != Kind::Nand {
+        builtins.insert(chip.name.clone(), 1);
+    }
+    Ok(Stats {
+        nands: (kind == Kind::Nand) as usize,
+        builtins,
+        depth: timing.depth(),
+        timing,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use std::path::Path;
+
+    fn analyze(dirs: &[&str], chip: &str) -> Rc<Stats> {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+        let mut library = Library::new(dirs.iter().map(|dir| root.join(dir)).collect());
+        Analyzer::new(&mut library).stats(chip).unwrap()
+    }
+
+    #[test]
+    fn test_gates() {
+        let stats = analyze(&["01"], "Not");
+        assert_eq!((stats.nands, stats.depth), (1, 1));
+        let stats = analyze(&["01"], "And");
+        assert_eq!((stats.nands, stats.depth), (2, 2));
+        let not16 = analyze(&["01"], "Not16");
+        assert_eq!((not16.nands, not16.depth), (16, 1));
+        assert!(not16.builtins.is_empty());
+    }
+
+    #[test]
+    fn test_paths() {
+        let mut library = Library::new(vec![]);
+        // The long path goes through both Nands, the short one only the last.
+        library.add(
+            parse(
+                "CHIP Foo {
+                    IN a, b;
+                    OUT out, c;
+                    PARTS:
+                    Nand(a=a, b=b, out=x);
+                    Nand(a=x, b=b, out=out);
+                    Not16(in[0]=x, out[0]=c);
+                }",
+            )
+            .unwrap(),
+        );
+        let stats = Analyzer::new(&mut library).stats("Foo").unwrap();
+        assert_eq!(stats.nands, 2);
+        assert_eq!(stats.builtins, BTreeMap::from([("Not16".to_string(), 1)]));
+        assert_eq!(stats.depth, 2);
+        assert_eq!(
+            stats.timing.delays,
+            [vec![Some(2), Some(2), None], vec![Some(1), Some(1), None]]
+        );
+    }
+
+    #[test]
+    fn test_sequential() {
+        let bit = analyze(&["03/a", "01"], "Bit");
+        assert_eq!(bit.builtins, BTreeMap::from([("DFF".to_string(), 1)]));
+        // From the flip-flop back into it through the Mux, where the And is
+        // 2 deep and the Or 4 in 01.
+        assert_eq!(bit.timing.internal, Some(6));
+        assert_eq!(bit.timing.delays, [vec![None, None, Some(0)]]);
+        let pc = analyze(&["03/a", "02", "01"], "PC");
+        assert_eq!(pc.builtins, BTreeMap::from([("DFF".to_string(), 16)]));
+        let inc16 = analyze(&["02", "01"], "Inc16");
+        assert!(pc.timing.internal > Some(inc16.depth));
+    }
+
+    #[test]
+    fn test_cpu() {
+        let alu = analyze(&["02", "01"], "ALU");
+        assert!(alu.builtins.is_empty());
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+        let mut library = Library::new(vec![root.join("02"), root.join("01")]);
+        let netlist = crate::netlist::Netlist::build(&mut library, "ALU").unwrap();
+        assert_eq!(alu.nands, netlist.components.len());
+        let cpu = analyze(&["05", "03/a", "02", "01"], "CPU");
+        assert_eq!(cpu.builtins, BTreeMap::from([("DFF".to_string(), 48)]));
+        assert!(cpu.nands > alu.nands);
+        assert!(cpu.depth > alu.depth);
+        // Memory is built from RAM16K, Screen and Keyboard, which stay
+        // built-in without 03/b.
+        let computer = analyze(&["05", "03/a", "02", "01"], "Computer");
+        assert!(computer.builtins.contains_key("RAM16K"));
+        assert!(computer.builtins.contains_key("ROM32K"));
+    }
+}
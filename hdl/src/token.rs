@@ -0,0 +1,29 @@
+#[derive(Debug)]
+pub struct Token<'s> {
+    pub kind: Kind<'s>,
+    pub line: usize,
+    pub column: usize,
+    pub lexeme: &'s str,
+}
+
+impl<'s> Token<'s> {
+    pub fn new(kind: Kind<'s>, line: usize, column: usize, lexeme: &'s str) -> Self {
+        Self {
+            kind,
+            line,
+            column,
+            lexeme,
+        }
+    }
+}
+
+#[rustfmt::skip]
+#[derive(Debug, PartialEq)]
+pub enum Kind<'s> {
+    Number(usize), Identifier(&'s str),
+    LeftBrace, RightBrace,
+    LeftParen, RightParen,
+    LeftBracket, RightBracket,
+    Comma, Semicolon, Colon, Equal, DotDot,
+    Eof,
+}
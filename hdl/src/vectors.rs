@@ -0,0 +1,304 @@
+use crate::builtin::Kind;
+use crate::chip::{Chip, Pin, Position};
+use crate::error::{Error, Result};
+use crate::netlist::{Component, Netlist};
+use crate::parser::parse;
+use crate::simulator::Simulator;
+use std::fmt::Write;
+use test_script::output::{line, Column, Value};
+
+/// Chips with at most this many input bits get every combination tested.
+pub const EXHAUSTIVE_BITS: usize = 10;
+
+/// A test script for a combinational chip, and the comparison file it
+/// should produce, in the format of the project's `.tst` and `.cmp` files.
+pub struct Vectors {
+    pub tst: String,
+    pub cmp: String,
+}
+
+/// Computes a chip's outputs from its inputs, both in the order of its pins.
+pub type Reference = Box<dyn FnMut(&[u16]) -> Vec<u16>>;
+
+/// Tests `chip` on every combination of inputs, or on `samples` of them
+/// when there are too many, with the outputs `reference` gives for them.
+pub fn generate(chip: &Chip, samples: usize, mut reference: Reference) -> Vectors {
+    let columns: Vec<Column> = chip
+        .inputs
+        .iter()
+        .chain(&chip.outputs)
+        .map(column)
+        .collect();
+    let specs: Vec<String> = columns.iter().map(spec).collect();
+    let mut tst = String::new();
+    writeln!(tst, "// Test vectors for {}.\n", chip.name).unwrap();
+    writeln!(tst, "load {}.hdl,", chip.name).unwrap();
+    writeln!(tst, "output-file {}.out,", chip.name).unwrap();
+    writeln!(tst, "compare-to {}.cmp,", chip.name).unwrap();
+    writeln!(tst, "output-list {};", specs.join(" ")).unwrap();
+    let mut cmp = line(columns.iter().map(Column::header)) + "\n";
+
+    for inputs in rows(&chip.inputs, samples) {
+        let outputs = reference(&inputs);
+        writeln!(tst).unwrap();
+        for (pin, value) in chip.inputs.iter().zip(&inputs) {
+            match pin.width {
+                1 => writeln!(tst, "set {} {},", pin.name, value),
+                width => writeln!(tst, "set {} %B{:0>width$b},", pin.name, value),
+            }
+            .unwrap();
+        }
+        tst += "eval,\noutput;\n";
+        let values = inputs.iter().chain(&outputs);
+        let cells = columns
+            .iter()
+            .zip(values)
+            .map(|(column, value)| column.cell(&Value::Number(*value as i64)));
+        cmp += &line(cells);
+        cmp += "\n";
+    }
+    Vectors { tst, cmp }
+}
+
+/// Every combination of input values, with the first pin changing slowest,
+/// or corner cases followed by pseudo-random values if there are too many.
+fn rows(inputs: &[Pin], samples: usize) -> Vec<Vec<u16>> {
+    let bits: usize = inputs.iter().map(|pin| pin.width).sum();
+    let mask = |pin: &Pin| ((1u32 << pin.width) - 1) as u16;
+    if bits <= EXHAUSTIVE_BITS {
+        return (0u32..1 << bits)
+            .map(|mut combination| {
+                let mut row: Vec<u16> = inputs
+                    .iter()
+                    .rev()
+                    .map(|pin| {
+                        let value = combination as u16 & mask(pin);
+                        combination >>= pin.width;
+                        value
+                    })
+                    .collect();
+                row.reverse();
+                row
+            })
+            .collect();
+    }
+    let corners = [0x0000, 0xffff, 0xaaaa, 0x5555];
+    // Each pin starts on a different corner, so that they can be told apart.
+    let mut rows: Vec<Vec<u16>> = (0..corners.len())
+        .map(|row| {
+            let corner = |(i, pin)| corners[(row + i) % corners.len()] & mask(pin);
+            inputs.iter().enumerate().map(corner).collect()
+        })
+        .collect();
+    // A fixed seed, so that generating again gives the same file.
+    let mut state: u32 = 0x2545f491;
+    let mut random = || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state as u16
+    };
+    while rows.len() < samples {
+        rows.push(inputs.iter().map(|pin| random() & mask(pin)).collect());
+    }
+    rows.truncate(samples);
+    rows
+}
+
+/// A binary column wide enough for the pin and its name, like the ones in
+/// the project's test scripts.
+fn column(pin: &Pin) -> Column {
+    let padding = match pin.width {
+        1 => 3,
+        16 => 1,
+        _ => 2,
+    };
+    let short = (pin.name.len() + 1).saturating_sub(pin.width + 2 * padding) / 2;
+    Column {
+        name: pin.name.clone(),
+        format: test_script::output::Format::Binary,
+        left: padding + short,
+        width: pin.width,
+        right: padding + short,
+    }
+}
+
+fn spec(column: &Column) -> String {
+    let (left, width, right) = (column.left, column.width, column.right);
+    format!("{}%B{}.{}.{}", column.name, left, width, right)
+}
+
+/// A reference running a known-good chip, gate by gate. Its pins must
+/// match those of `chip`, in any order.
+pub fn simulated(chip: &Chip, netlist: Netlist) -> Result<Reference> {
+    let clocked = netlist.components.iter().any(|component| match component {
+        Component::Builtin { kind, .. } => kind.state_size() > 0,
+        Component::Nand { .. } => false,
+    });
+    if clocked {
+        let message = "only combinational chips can be tested exhaustively";
+        return Err(Error::build(&netlist.chip, Position::default(), message));
+    }
+    let widths = |pins: &[(String, Vec<usize>)]| -> Vec<(String, usize)> {
+        pins.iter()
+            .map(|(name, nets)| (name.clone(), nets.len()))
+            .collect()
+    };
+    let reference_inputs = widths(&netlist.inputs);
+    let reference_outputs = widths(&netlist.outputs);
+    check_pins(chip, &netlist.chip, &reference_inputs, &reference_outputs)?;
+    let inputs: Vec<String> = chip.inputs.iter().map(|pin| pin.name.clone()).collect();
+    let outputs: Vec<String> = chip.outputs.iter().map(|pin| pin.name.clone()).collect();
+    let mut simulator = Simulator::new(netlist);
+    Ok(Box::new(move |values| {
+        for (name, value) in inputs.iter().zip(values) {
+            simulator.set(name, *value).expect("pins were checked");
+        }
+        simulator.eval();
+        let get = |name: &String| simulator.get(name).expect("pins were checked");
+        outputs.iter().map(get).collect()
+    }))
+}
+
+/// A reference using the simulator's own model of the built-in chip with
+/// the same name as `chip`.
+pub fn builtin(chip: &Chip) -> Result<Reference> {
+    let error = |message: String| Error::build(&chip.name, chip.position, message);
+    let kind = Kind::from_name(&chip.name)
+        .ok_or_else(|| error(format!("no built-in chip `{}`", chip.name)))?;
+    if kind.state_size() > 0 {
+        let message = "only combinational chips can be tested exhaustively".to_string();
+        return Err(error(message));
+    }
+    let interface = parse(kind.interface()).expect("built-in interfaces parse");
+    let pins = |pins: &[Pin]| -> Vec<(String, usize)> {
+        pins.iter()
+            .map(|pin| (pin.name.clone(), pin.width))
+            .collect()
+    };
+    check_pins(
+        chip,
+        &interface.name,
+        &pins(&interface.inputs),
+        &pins(&interface.outputs),
+    )?;
+    // Where each of the built-in chip's pins is among those of `chip`.
+    let input_order: Vec<usize> = interface
+        .inputs
+        .iter()
+        .map(|pin| chip.input(&pin.name).unwrap().0)
+        .collect();
+    let outputs: Vec<(usize, u16)> = chip
+        .outputs
+        .iter()
+        .map(|pin| {
+            let (index, _) = interface.output(&pin.name).unwrap();
+            (index, ((1u32 << pin.width) - 1) as u16)
+        })
+        .collect();
+    let output_count = interface.outputs.len();
+    Ok(Box::new(move |values| {
+        let inputs: Vec<u16> = input_order.iter().map(|&i| values[i]).collect();
+        let mut results = vec![0; output_count];
+        kind.eval(&[], &inputs, &mut results);
+        outputs.iter().map(|&(i, mask)| results[i] & mask).collect()
+    }))
+}
+
+fn check_pins(
+    chip: &Chip,
+    reference: &str,
+    inputs: &[(String, usize)],
+    outputs: &[(String, usize)],
+) -> Result<()> {
+    let same = |pins: &[Pin], others: &[(String, usize)]| {
+        pins.len() == others.len()
+            && pins
+                .iter()
+                .all(|pin| others.contains(&(pin.name.clone(), pin.width)))
+    };
+    if same(&chip.inputs, inputs) && same(&chip.outputs, outputs) {
+        Ok(())
+    } else {
+        let message = format!("pins don't match those of the reference `{}`", reference);
+        Err(Error::build(&chip.name, chip.position, message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::Library;
+    use crate::script::HardwareSimulator;
+    use std::fs;
+    use std::path::Path;
+    use test_script::runner::Runner;
+
+    fn project(path: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("..").join(path)
+    }
+
+    fn chip(path: &str) -> Chip {
+        parse(&fs::read_to_string(project(path)).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_builtin() {
+        for name in ["01/Mux", "01/DMux", "02/FullAdder"] {
+            let chip = chip(&format!("{}.hdl", name));
+            let vectors = generate(&chip, 0, builtin(&chip).unwrap());
+            let expected = fs::read_to_string(project(&format!("{}.cmp", name))).unwrap();
+            assert_eq!(vectors.cmp, expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_samples() {
+        let chip = chip("02/ALU.hdl");
+        let vectors = generate(&chip, 20, builtin(&chip).unwrap());
+        assert_eq!(vectors.cmp.lines().count(), 21);
+        let mut lines = vectors.cmp.lines();
+        assert_eq!(
+            lines.next().unwrap(),
+            "|        x         |        y         |  zx   |  nx   |  zy   |  ny   \
+             |   f   |  no   |       out        |  zr   |  ng   |"
+        );
+        assert_eq!(
+            lines.nth(1).unwrap(),
+            "| 1111111111111111 | 1010101010101010 |   1   |   0   |   1   |   0   \
+             |   1   |   0   | 0000000000000000 |   1   |   0   |"
+        );
+        assert!(vectors.tst.contains("set x %B0000000000000000,\n"));
+    }
+
+    #[test]
+    fn test_simulated() {
+        // Vectors from the built-in Xor pass on the Xor of project 1, and
+        // the other way around.
+        let xor = chip("01/Xor.hdl");
+        let mut library = Library::new(vec![project("01")]);
+        let netlist = Netlist::build(&mut library, "Xor").unwrap();
+        let from_hdl = generate(&xor, 0, simulated(&xor, netlist).unwrap());
+        let from_builtin = generate(&xor, 0, builtin(&xor).unwrap());
+        assert_eq!(from_hdl.cmp, from_builtin.cmp);
+
+        let dir = std::env::temp_dir().join(format!("hdl-vectors-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["Xor", "And", "Or", "Not"] {
+            let file = format!("{}.hdl", name);
+            fs::copy(project("01").join(&file), dir.join(&file)).unwrap();
+        }
+        fs::write(dir.join("Xor.cmp"), &from_builtin.cmp).unwrap();
+        let mut runner = Runner::new(HardwareSimulator::default(), &dir);
+        runner.run(&from_builtin.tst).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let not = chip("01/Not.hdl");
+        let mut library = Library::new(vec![project("03/a")]);
+        let netlist = Netlist::build(&mut library, "Bit").unwrap();
+        assert!(simulated(&not, netlist).is_err());
+        let mut library = Library::new(vec![]);
+        let netlist = Netlist::build(&mut library, "And").unwrap();
+        assert!(simulated(&not, netlist).is_err());
+    }
+}
@@ -0,0 +1,504 @@
+use crate::builtin::Kind;
+use crate::chip::{Body, Chip, Pin, Wire};
+use crate::error::Result;
+use crate::library::Library;
+use crate::netlist::Netlist;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::rc::Rc;
+
+/// Translates a chip and every chip it uses into Verilog modules. Chips
+/// with clocked parts get a `clk` input, passed down to those parts.
+pub fn export(library: &mut Library, top: &str) -> Result<String> {
+    // Building the netlist catches every wiring error up front.
+    Netlist::build(library, top)?;
+    let mut exporter = Exporter {
+        library,
+        clocked: HashMap::new(),
+        done: HashSet::new(),
+        verilog: String::new(),
+    };
+    exporter.chip(top)?;
+    Ok(exporter.verilog)
+}
+
+struct Exporter<'l> {
+    library: &'l mut Library,
+    /// Whether each chip needs a clock, once known.
+    clocked: HashMap<String, bool>,
+    done: HashSet<String>,
+    verilog: String,
+}
+
+impl Exporter<'_> {
+    fn chip(&mut self, name: &str) -> Result<()> {
+        if !self.done.insert(name.to_string()) {
+            return Ok(());
+        }
+        let chip = self.library.chip(name)?.expect("netlist has every chip");
+        let definitions = self.library.parts(&chip)?;
+        let module = match &chip.body {
+            Body::Builtin(builtin) => {
+                let kind = Kind::from_name(builtin).expect("netlist has every built-in chip");
+                builtin_module(&chip, kind)
+            }
+            Body::Parts(_) => self.module(&chip, &definitions)?,
+        };
+        self.verilog += &module;
+        for definition in &definitions {
+            self.chip(&definition.name)?;
+        }
+        Ok(())
+    }
+
+    fn is_clocked(&mut self, chip: &Chip) -> Result<bool> {
+        if let Some(&clocked) = self.clocked.get(&chip.name) {
+            return Ok(clocked);
+        }
+        let clocked = match &chip.body {
+            Body::Builtin(builtin) => Kind::from_name(builtin).is_some_and(Kind::is_sequential),
+            Body::Parts(_) => {
+                let mut clocked = false;
+                for definition in self.library.parts(chip)? {
+                    clocked |= self.is_clocked(&definition)?;
+                }
+                clocked
+            }
+        };
+        self.clocked.insert(chip.name.clone(), clocked);
+        Ok(clocked)
+    }
+
+    fn module(&mut self, chip: &Chip, definitions: &[Rc<Chip>]) -> Result<String> {
+        let internals = chip.internal_pins(definitions);
+        let widths: HashMap<&str, usize> = chip
+            .inputs
+            .iter()
+            .chain(&chip.outputs)
+            .map(|pin| (&pin.name[..], pin.width))
+            .chain(internals.iter().copied())
+            .collect();
+        let mut module = header(chip, self.is_clocked(chip)?);
+        for (name, width) in &internals {
+            writeln!(module, "  wire{} {};", range(*width), identifier(name)).unwrap();
+        }
+        // Which bits of the chip's outputs the parts drive. Like the HDL
+        // simulator, the others are tied to 0.
+        let mut driven: HashMap<&str, Vec<bool>> = chip
+            .outputs
+            .iter()
+            .map(|pin| (&pin.name[..], vec![false; pin.width]))
+            .collect();
+        for (i, (part, definition)) in chip.parts().iter().zip(definitions).enumerate() {
+            let instance = format!("{}_{}", part.chip.to_lowercase(), i);
+            let mut ports = vec![];
+            if self.is_clocked(definition)? {
+                ports.push(".clk(clk)".to_string());
+            }
+            for pin in &definition.inputs {
+                let mut sources = vec![Source::Constant(false); pin.width];
+                for connection in part.connections.iter() {
+                    if connection.pin.name != pin.name {
+                        continue;
+                    }
+                    let bits = connection
+                        .pin
+                        .bits(pin.width)
+                        .expect("netlist checks ranges");
+                    for (n, bit) in bits.enumerate() {
+                        sources[bit] = match &connection.wire {
+                            Wire::Constant(value, _) => Source::Constant(*value),
+                            Wire::Bus(wire) => {
+                                let lo = wire.range.map_or(0, |(lo, _)| lo);
+                                Source::Wire(&wire.name, lo + n, widths[&wire.name[..]])
+                            }
+                        };
+                    }
+                }
+                let port = identifier(&pin.name);
+                ports.push(format!(".{}({})", port, concatenation(&sources)));
+            }
+            let mut assignments = vec![];
+            for pin in &definition.outputs {
+                let output = format!("{}_{}", instance, pin.name);
+                writeln!(module, "  wire{} {};", range(pin.width), output).unwrap();
+                ports.push(format!(".{}({})", identifier(&pin.name), output));
+                for connection in part.connections.iter() {
+                    let Wire::Bus(wire) = &connection.wire else {
+                        continue;
+                    };
+                    if connection.pin.name != pin.name {
+                        continue;
+                    }
+                    let bits = connection
+                        .pin
+                        .bits(pin.width)
+                        .expect("netlist checks ranges");
+                    let width = widths[&wire.name[..]];
+                    let wire_bits = wire.bits(width).expect("netlist checks ranges");
+                    if let Some(bits) = driven.get_mut(&wire.name[..]) {
+                        bits[wire_bits.clone()].fill(true);
+                    }
+                    assignments.push(format!(
+                        "  assign {} = {};",
+                        slice(&wire.name, width, wire_bits.start, wire_bits.end),
+                        slice(&output, pin.width, bits.start, bits.end)
+                    ));
+                }
+            }
+            writeln!(
+                module,
+                "  {} {} ({});",
+                part.chip,
+                instance,
+                ports.join(", ")
+            )
+            .unwrap();
+            for assignment in assignments {
+                writeln!(module, "{}", assignment).unwrap();
+            }
+        }
+        for pin in &chip.outputs {
+            let bits = &driven[&pin.name[..]];
+            let mut start = 0;
+            while let Some(n) = bits[start..].iter().position(|&bit| !bit) {
+                let lo = start + n;
+                let hi = bits[lo..]
+                    .iter()
+                    .position(|&bit| bit)
+                    .map_or(pin.width, |n| lo + n);
+                let output = slice(&pin.name, pin.width, lo, hi);
+                writeln!(module, "  assign {} = 0;", output).unwrap();
+                start = hi;
+            }
+        }
+        module += "endmodule\n\n";
+        Ok(module)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Source<'c> {
+    Constant(bool),
+    /// A bit of a wire, along with the wire's width.
+    Wire(&'c str, usize, usize),
+}
+
+/// The Verilog expression for a bus, from its bits, least significant first.
+fn concatenation(sources: &[Source]) -> String {
+    let mut runs: Vec<(Source, usize)> = vec![];
+    for &source in sources {
+        match (runs.last_mut(), source) {
+            (Some((Source::Constant(a), n)), Source::Constant(b)) if *a == b => *n += 1,
+            (Some((Source::Wire(a, lo, _), n)), Source::Wire(b, bit, _))
+                if *a == b && *lo + *n == bit =>
+            {
+                *n += 1
+            }
+            _ => runs.push((source, 1)),
+        }
+    }
+    let pieces: Vec<String> = runs
+        .iter()
+        .rev()
+        .map(|&(source, n)| match source {
+            Source::Constant(value) if n == 1 => format!("1'b{}", value as u8),
+            Source::Constant(value) => format!("{{{}{{1'b{}}}}}", n, value as u8),
+            Source::Wire(name, lo, width) => slice(name, width, lo, lo + n),
+        })
+        .collect();
+    match &pieces[..] {
+        [piece] => piece.clone(),
+        _ => format!("{{{}}}", pieces.join(", ")),
+    }
+}
+
+/// Bits `start..end` of a wire `width` bits wide.
+fn slice(name: &str, width: usize, start: usize, end: usize) -> String {
+    let name = identifier(name);
+    if start == 0 && end == width {
+        name
+    } else if end - start == 1 {
+        format!("{}[{}]", name, start)
+    } else {
+        format!("{}[{}:{}]", name, end - 1, start)
+    }
+}
+
+fn range(width: usize) -> String {
+    if width == 1 {
+        String::new()
+    } else {
+        format!(" [{}:0]", width - 1)
+    }
+}
+
+/// Pin names that happen to be Verilog keywords get an underscore.
+fn identifier(name: &str) -> String {
+    const KEYWORDS: &[&str] = &[
+        "always",
+        "and",
+        "assign",
+        "begin",
+        "buf",
+        "case",
+        "else",
+        "end",
+        "endcase",
+        "endmodule",
+        "for",
+        "function",
+        "if",
+        "initial",
+        "inout",
+        "input",
+        "integer",
+        "module",
+        "nand",
+        "negedge",
+        "nor",
+        "not",
+        "or",
+        "output",
+        "parameter",
+        "posedge",
+        "reg",
+        "wire",
+        "xnor",
+        "xor",
+    ];
+    if KEYWORDS.contains(&name) {
+        format!("{}_", name)
+    } else {
+        name.to_string()
+    }
+}
+
+fn header(chip: &Chip, clocked: bool) -> String {
+    let port = |direction: &str, pin: &Pin| {
+        format!(
+            "{}{} {}",
+            direction,
+            range(pin.width),
+            identifier(&pin.name)
+        )
+    };
+    let clock = clocked.then(|| "input clk".to_string());
+    let inputs = chip.inputs.iter().map(|pin| port("input", pin));
+    let outputs = chip.outputs.iter().map(|pin| port("output", pin));
+    let ports: Vec<String> = clock.into_iter().chain(inputs).chain(outputs).collect();
+    format!("module {}({});\n", chip.name, ports.join(", "))
+}
+
+/// Behavioural Verilog for a built-in chip. Memories are registers that
+/// synthesis tools can map to block RAM.
+fn builtin_module(chip: &Chip, kind: Kind) -> String {
+    use Kind::*;
+    let body = match kind {
+        Nand => "  nand g(out, a, b);\n".to_string(),
+        Not | Not16 => "  assign out = ~in;\n".to_string(),
+        And | And16 => "  assign out = a & b;\n".to_string(),
+        Or | Or16 => "  assign out = a | b;\n".to_string(),
+        Xor => "  assign out = a ^ b;\n".to_string(),
+        Mux | Mux16 => "  assign out = sel ? b : a;\n".to_string(),
+        Mux4Way16 | Mux8Way16 => {
+            let inputs = &chip.inputs[..chip.inputs.len() - 1];
+            let cases: Vec<String> = inputs
+                .iter()
+                .enumerate()
+                .take(inputs.len() - 1)
+                .map(|(i, pin)| format!("sel == {} ? {} : ", i, pin.name))
+                .collect();
+            let last = &inputs[inputs.len() - 1].name;
+            format!("  assign out = {}{};\n", cases.concat(), last)
+        }
+        DMux | DMux4Way | DMux8Way => chip
+            .outputs
+            .iter()
+            .enumerate()
+            .map(|(i, pin)| format!("  assign {} = sel == {} ? in : 1'b0;\n", pin.name, i))
+            .collect(),
+        Or8Way => "  assign out = |in;\n".to_string(),
+        HalfAdder => "  assign {carry, sum} = a + b;\n".to_string(),
+        FullAdder => "  assign {carry, sum} = a + b + c;\n".to_string(),
+        Add16 => "  assign out = a + b;\n".to_string(),
+        Inc16 => "  assign out = in + 16'd1;\n".to_string(),
+        ALU => "  wire [15:0] zx_out = zx ? 16'd0 : x;
+  wire [15:0] nx_out = nx ? ~zx_out : zx_out;
+  wire [15:0] zy_out = zy ? 16'd0 : y;
+  wire [15:0] ny_out = ny ? ~zy_out : zy_out;
+  wire [15:0] f_out = f ? nx_out + ny_out : nx_out & ny_out;
+  assign out = no ? ~f_out : f_out;
+  assign zr = out == 16'd0;
+  assign ng = out[15];
+"
+        .to_string(),
+        DFF => "  reg value = 1'b0;
+  assign out = value;
+  always @(posedge clk) value <= in;
+"
+        .to_string(),
+        Bit | Register | ARegister | DRegister => format!(
+            "  reg{} value = 0;
+  assign out = value;
+  always @(posedge clk) if (load) value <= in;
+",
+            range(chip.inputs[0].width)
+        ),
+        PC => "  reg [15:0] value = 16'd0;
+  assign out = value;
+  always @(posedge clk)
+    if (reset) value <= 16'd0;
+    else if (load) value <= in;
+    else if (inc) value <= value + 16'd1;
+"
+        .to_string(),
+        RAM8 | RAM64 | RAM512 | RAM4K | RAM16K | Screen => format!(
+            "  reg [15:0] memory [0:{}];
+  assign out = memory[address];
+  always @(posedge clk) if (load) memory[address] <= in;
+",
+            kind.state_size() - 1
+        ),
+        ROM32K => "  parameter FILE = \"program.hack\";
+  reg [15:0] memory [0:32767];
+  initial $readmemb(FILE, memory);
+  assign out = memory[address];
+"
+        .to_string(),
+        Keyboard => "  // Set from a testbench, or replace with a real keyboard.
+  reg [15:0] key = 16'd0;
+  assign out = key;
+"
+        .to_string(),
+    };
+    format!(
+        "{}{}endmodule\n\n",
+        header(chip, kind.is_sequential()),
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use std::path::Path;
+    use std::{env, fs};
+
+    fn export_source(sources: &[&str]) -> String {
+        let mut library = Library::new(vec![]);
+        let top = parse(sources[0]).unwrap().name;
+        for source in sources {
+            library.add(parse(source).unwrap());
+        }
+        export(&mut library, &top).unwrap()
+    }
+
+    #[test]
+    fn test_module() {
+        let verilog = export_source(&["CHIP Foo {
+                IN a[4], sel;
+                OUT out[2], and;
+                PARTS:
+                Not16(in[0..3]=a, in[4]=true, in[5..6]=a[1..2], out[1..2]=out, out[3..5]=x);
+                And(a=x[2], b=sel, out=and);
+            }"]);
+        assert_eq!(
+            verilog,
+            "module Foo(input [3:0] a, input sel, output [1:0] out, output and_);
+  wire [2:0] x;
+  wire [15:0] not16_0_out;
+  Not16 not16_0 (.in({{9{1'b0}}, a[2:1], 1'b1, a}), .out(not16_0_out));
+  assign out = not16_0_out[2:1];
+  assign x = not16_0_out[5:3];
+  wire and_1_out;
+  And and_1 (.a(x[2]), .b(sel), .out(and_1_out));
+  assign and_ = and_1_out;
+endmodule
+
+module Not16(input [15:0] in, output [15:0] out);
+  assign out = ~in;
+endmodule
+
+module And(input a, input b, output out);
+  assign out = a & b;
+endmodule
+
+"
+        );
+    }
+
+    #[test]
+    fn test_undriven_outputs() {
+        let verilog = export_source(&["CHIP Foo {
+                IN a;
+                OUT out[4], x, y;
+                PARTS:
+                Not(in=a, out=out[1], out=y);
+            }"]);
+        assert!(verilog.contains(
+            "  assign y = not_0_out;
+  assign out[0] = 0;
+  assign out[3:2] = 0;
+  assign x = 0;
+endmodule"
+        ));
+    }
+
+    #[test]
+    fn test_clock() {
+        let verilog = export_source(&[
+            "CHIP Foo { IN in; OUT out; PARTS: Bar(in=in, out=out); }",
+            "CHIP Bar { IN in; OUT out; PARTS: DFF(in=in, out=out); }",
+        ]);
+        assert!(verilog.contains("module Foo(input clk, input in, output out);"));
+        assert!(verilog.contains("Bar bar_0 (.clk(clk), .in(in), .out(bar_0_out));"));
+        assert!(verilog.contains("always @(posedge clk) value <= in;"));
+    }
+
+    /// The modules of the ALU and the chips it's made of, as a whole.
+    #[test]
+    fn test_alu() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+        let mut library = Library::new(vec![root.join("02"), root.join("01")]);
+        let verilog = export(&mut library, "ALU").unwrap();
+        let golden = root.join("hdl/testdata/ALU.v");
+        if env::var_os("UPDATE_GOLDEN").is_some() {
+            fs::write(&golden, &verilog).unwrap();
+        }
+        assert_eq!(verilog, fs::read_to_string(golden).unwrap());
+    }
+
+    #[test]
+    fn test_computer() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+        // Without 03/a the memories below RAM512 are built-in, which keeps
+        // the netlist built for validation small.
+        let dirs = ["05", "03/b", "02", "01"];
+        let mut library = Library::new(dirs.iter().map(|dir| root.join(dir)).collect());
+        let verilog = export(&mut library, "Computer").unwrap();
+        let modules: Vec<&str> = verilog
+            .lines()
+            .filter_map(|line| line.strip_prefix("module "))
+            .map(|line| &line[..line.find('(').unwrap()])
+            .collect();
+        let unique: HashSet<&str> = modules.iter().copied().collect();
+        assert_eq!(modules.len(), unique.len());
+        for chip in [
+            "Computer", "CPU", "ALU", "Memory", "RAM16K", "RAM64", "Nand",
+        ] {
+            assert!(unique.contains(chip), "{}", chip);
+        }
+        assert!(verilog.contains(
+            "module RAM64(input clk, input [15:0] in, input load, input [5:0] address, \
+             output [15:0] out);\n  reg [15:0] memory [0:63];"
+        ));
+        assert!(verilog.contains("RAM64 ram64_1 (.clk(clk), "));
+        assert_eq!(
+            verilog.matches("module ").count(),
+            verilog.matches("endmodule").count()
+        );
+        assert!(verilog.contains("initial $readmemb(FILE, memory);"));
+    }
+}
@@ -0,0 +1,454 @@
+module ALU(input [15:0] x, input [15:0] y, input zx, input nx, input zy, input ny, input f, input no, output [15:0] out, output zr, output ng);
+  wire [15:0] xz;
+  wire [15:0] notXz;
+  wire [15:0] yz;
+  wire [15:0] notYz;
+  wire [15:0] xzn;
+  wire [15:0] yzn;
+  wire [15:0] xAndY;
+  wire [15:0] xPlusY;
+  wire [15:0] res;
+  wire [15:0] notRes;
+  wire [7:0] outRight;
+  wire [7:0] outLeft;
+  wire or0To7;
+  wire or8To15;
+  wire nonZero;
+  wire [15:0] mux16_0_out;
+  Mux16 mux16_0 (.a(x), .b({16{1'b0}}), .sel(zx), .out(mux16_0_out));
+  assign xz = mux16_0_out;
+  wire [15:0] not16_1_out;
+  Not16 not16_1 (.in(xz), .out(not16_1_out));
+  assign notXz = not16_1_out;
+  wire [15:0] mux16_2_out;
+  Mux16 mux16_2 (.a(y), .b({16{1'b0}}), .sel(zy), .out(mux16_2_out));
+  assign yz = mux16_2_out;
+  wire [15:0] not16_3_out;
+  Not16 not16_3 (.in(yz), .out(not16_3_out));
+  assign notYz = not16_3_out;
+  wire [15:0] mux16_4_out;
+  Mux16 mux16_4 (.a(xz), .b(notXz), .sel(nx), .out(mux16_4_out));
+  assign xzn = mux16_4_out;
+  wire [15:0] mux16_5_out;
+  Mux16 mux16_5 (.a(yz), .b(notYz), .sel(ny), .out(mux16_5_out));
+  assign yzn = mux16_5_out;
+  wire [15:0] and16_6_out;
+  And16 and16_6 (.a(xzn), .b(yzn), .out(and16_6_out));
+  assign xAndY = and16_6_out;
+  wire [15:0] add16_7_out;
+  Add16 add16_7 (.a(xzn), .b(yzn), .out(add16_7_out));
+  assign xPlusY = add16_7_out;
+  wire [15:0] mux16_8_out;
+  Mux16 mux16_8 (.a(xAndY), .b(xPlusY), .sel(f), .out(mux16_8_out));
+  assign res = mux16_8_out;
+  wire [15:0] not16_9_out;
+  Not16 not16_9 (.in(res), .out(not16_9_out));
+  assign notRes = not16_9_out;
+  wire [15:0] mux16_10_out;
+  Mux16 mux16_10 (.a(res), .b(notRes), .sel(no), .out(mux16_10_out));
+  assign outRight = mux16_10_out[7:0];
+  assign outLeft = mux16_10_out[15:8];
+  assign out = mux16_10_out;
+  assign ng = mux16_10_out[15];
+  wire or8way_11_out;
+  Or8Way or8way_11 (.in(outRight), .out(or8way_11_out));
+  assign or0To7 = or8way_11_out;
+  wire or8way_12_out;
+  Or8Way or8way_12 (.in(outLeft), .out(or8way_12_out));
+  assign or8To15 = or8way_12_out;
+  wire or_13_out;
+  Or or_13 (.a(or0To7), .b(or8To15), .out(or_13_out));
+  assign nonZero = or_13_out;
+  wire not_14_out;
+  Not not_14 (.in(nonZero), .out(not_14_out));
+  assign zr = not_14_out;
+endmodule
+
+module Mux16(input [15:0] a, input [15:0] b, input sel, output [15:0] out);
+  wire mux_0_out;
+  Mux mux_0 (.a(a[0]), .b(b[0]), .sel(sel), .out(mux_0_out));
+  assign out[0] = mux_0_out;
+  wire mux_1_out;
+  Mux mux_1 (.a(a[1]), .b(b[1]), .sel(sel), .out(mux_1_out));
+  assign out[1] = mux_1_out;
+  wire mux_2_out;
+  Mux mux_2 (.a(a[2]), .b(b[2]), .sel(sel), .out(mux_2_out));
+  assign out[2] = mux_2_out;
+  wire mux_3_out;
+  Mux mux_3 (.a(a[3]), .b(b[3]), .sel(sel), .out(mux_3_out));
+  assign out[3] = mux_3_out;
+  wire mux_4_out;
+  Mux mux_4 (.a(a[4]), .b(b[4]), .sel(sel), .out(mux_4_out));
+  assign out[4] = mux_4_out;
+  wire mux_5_out;
+  Mux mux_5 (.a(a[5]), .b(b[5]), .sel(sel), .out(mux_5_out));
+  assign out[5] = mux_5_out;
+  wire mux_6_out;
+  Mux mux_6 (.a(a[6]), .b(b[6]), .sel(sel), .out(mux_6_out));
+  assign out[6] = mux_6_out;
+  wire mux_7_out;
+  Mux mux_7 (.a(a[7]), .b(b[7]), .sel(sel), .out(mux_7_out));
+  assign out[7] = mux_7_out;
+  wire mux_8_out;
+  Mux mux_8 (.a(a[8]), .b(b[8]), .sel(sel), .out(mux_8_out));
+  assign out[8] = mux_8_out;
+  wire mux_9_out;
+  Mux mux_9 (.a(a[9]), .b(b[9]), .sel(sel), .out(mux_9_out));
+  assign out[9] = mux_9_out;
+  wire mux_10_out;
+  Mux mux_10 (.a(a[10]), .b(b[10]), .sel(sel), .out(mux_10_out));
+  assign out[10] = mux_10_out;
+  wire mux_11_out;
+  Mux mux_11 (.a(a[11]), .b(b[11]), .sel(sel), .out(mux_11_out));
+  assign out[11] = mux_11_out;
+  wire mux_12_out;
+  Mux mux_12 (.a(a[12]), .b(b[12]), .sel(sel), .out(mux_12_out));
+  assign out[12] = mux_12_out;
+  wire mux_13_out;
+  Mux mux_13 (.a(a[13]), .b(b[13]), .sel(sel), .out(mux_13_out));
+  assign out[13] = mux_13_out;
+  wire mux_14_out;
+  Mux mux_14 (.a(a[14]), .b(b[14]), .sel(sel), .out(mux_14_out));
+  assign out[14] = mux_14_out;
+  wire mux_15_out;
+  Mux mux_15 (.a(a[15]), .b(b[15]), .sel(sel), .out(mux_15_out));
+  assign out[15] = mux_15_out;
+endmodule
+
+module Mux(input a, input b, input sel, output out);
+  wire notSel;
+  wire selOff;
+  wire selOn;
+  wire not_0_out;
+  Not not_0 (.in(sel), .out(not_0_out));
+  assign notSel = not_0_out;
+  wire and_1_out;
+  And and_1 (.a(a), .b(notSel), .out(and_1_out));
+  assign selOff = and_1_out;
+  wire and_2_out;
+  And and_2 (.a(b), .b(sel), .out(and_2_out));
+  assign selOn = and_2_out;
+  wire or_3_out;
+  Or or_3 (.a(selOn), .b(selOff), .out(or_3_out));
+  assign out = or_3_out;
+endmodule
+
+module Not(input in, output out);
+  wire nand_0_out;
+  Nand nand_0 (.a(in), .b(in), .out(nand_0_out));
+  assign out = nand_0_out;
+endmodule
+
+module Nand(input a, input b, output out);
+  nand g(out, a, b);
+endmodule
+
+module And(input a, input b, output out);
+  wire nandOut;
+  wire nand_0_out;
+  Nand nand_0 (.a(a), .b(b), .out(nand_0_out));
+  assign nandOut = nand_0_out;
+  wire not_1_out;
+  Not not_1 (.in(nandOut), .out(not_1_out));
+  assign out = not_1_out;
+endmodule
+
+module Or(input a, input b, output out);
+  wire notA;
+  wire notB;
+  wire notAAndNotB;
+  wire not_0_out;
+  Not not_0 (.in(a), .out(not_0_out));
+  assign notA = not_0_out;
+  wire not_1_out;
+  Not not_1 (.in(b), .out(not_1_out));
+  assign notB = not_1_out;
+  wire and_2_out;
+  And and_2 (.a(notA), .b(notB), .out(and_2_out));
+  assign notAAndNotB = and_2_out;
+  wire not_3_out;
+  Not not_3 (.in(notAAndNotB), .out(not_3_out));
+  assign out = not_3_out;
+endmodule
+
+module Not16(input [15:0] in, output [15:0] out);
+  wire not_0_out;
+  Not not_0 (.in(in[0]), .out(not_0_out));
+  assign out[0] = not_0_out;
+  wire not_1_out;
+  Not not_1 (.in(in[1]), .out(not_1_out));
+  assign out[1] = not_1_out;
+  wire not_2_out;
+  Not not_2 (.in(in[2]), .out(not_2_out));
+  assign out[2] = not_2_out;
+  wire not_3_out;
+  Not not_3 (.in(in[3]), .out(not_3_out));
+  assign out[3] = not_3_out;
+  wire not_4_out;
+  Not not_4 (.in(in[4]), .out(not_4_out));
+  assign out[4] = not_4_out;
+  wire not_5_out;
+  Not not_5 (.in(in[5]), .out(not_5_out));
+  assign out[5] = not_5_out;
+  wire not_6_out;
+  Not not_6 (.in(in[6]), .out(not_6_out));
+  assign out[6] = not_6_out;
+  wire not_7_out;
+  Not not_7 (.in(in[7]), .out(not_7_out));
+  assign out[7] = not_7_out;
+  wire not_8_out;
+  Not not_8 (.in(in[8]), .out(not_8_out));
+  assign out[8] = not_8_out;
+  wire not_9_out;
+  Not not_9 (.in(in[9]), .out(not_9_out));
+  assign out[9] = not_9_out;
+  wire not_10_out;
+  Not not_10 (.in(in[10]), .out(not_10_out));
+  assign out[10] = not_10_out;
+  wire not_11_out;
+  Not not_11 (.in(in[11]), .out(not_11_out));
+  assign out[11] = not_11_out;
+  wire not_12_out;
+  Not not_12 (.in(in[12]), .out(not_12_out));
+  assign out[12] = not_12_out;
+  wire not_13_out;
+  Not not_13 (.in(in[13]), .out(not_13_out));
+  assign out[13] = not_13_out;
+  wire not_14_out;
+  Not not_14 (.in(in[14]), .out(not_14_out));
+  assign out[14] = not_14_out;
+  wire not_15_out;
+  Not not_15 (.in(in[15]), .out(not_15_out));
+  assign out[15] = not_15_out;
+endmodule
+
+module And16(input [15:0] a, input [15:0] b, output [15:0] out);
+  wire and_0_out;
+  And and_0 (.a(a[0]), .b(b[0]), .out(and_0_out));
+  assign out[0] = and_0_out;
+  wire and_1_out;
+  And and_1 (.a(a[1]), .b(b[1]), .out(and_1_out));
+  assign out[1] = and_1_out;
+  wire and_2_out;
+  And and_2 (.a(a[2]), .b(b[2]), .out(and_2_out));
+  assign out[2] = and_2_out;
+  wire and_3_out;
+  And and_3 (.a(a[3]), .b(b[3]), .out(and_3_out));
+  assign out[3] = and_3_out;
+  wire and_4_out;
+  And and_4 (.a(a[4]), .b(b[4]), .out(and_4_out));
+  assign out[4] = and_4_out;
+  wire and_5_out;
+  And and_5 (.a(a[5]), .b(b[5]), .out(and_5_out));
+  assign out[5] = and_5_out;
+  wire and_6_out;
+  And and_6 (.a(a[6]), .b(b[6]), .out(and_6_out));
+  assign out[6] = and_6_out;
+  wire and_7_out;
+  And and_7 (.a(a[7]), .b(b[7]), .out(and_7_out));
+  assign out[7] = and_7_out;
+  wire and_8_out;
+  And and_8 (.a(a[8]), .b(b[8]), .out(and_8_out));
+  assign out[8] = and_8_out;
+  wire and_9_out;
+  And and_9 (.a(a[9]), .b(b[9]), .out(and_9_out));
+  assign out[9] = and_9_out;
+  wire and_10_out;
+  And and_10 (.a(a[10]), .b(b[10]), .out(and_10_out));
+  assign out[10] = and_10_out;
+  wire and_11_out;
+  And and_11 (.a(a[11]), .b(b[11]), .out(and_11_out));
+  assign out[11] = and_11_out;
+  wire and_12_out;
+  And and_12 (.a(a[12]), .b(b[12]), .out(and_12_out));
+  assign out[12] = and_12_out;
+  wire and_13_out;
+  And and_13 (.a(a[13]), .b(b[13]), .out(and_13_out));
+  assign out[13] = and_13_out;
+  wire and_14_out;
+  And and_14 (.a(a[14]), .b(b[14]), .out(and_14_out));
+  assign out[14] = and_14_out;
+  wire and_15_out;
+  And and_15 (.a(a[15]), .b(b[15]), .out(and_15_out));
+  assign out[15] = and_15_out;
+endmodule
+
+module Add16(input [15:0] a, input [15:0] b, output [15:0] out);
+  wire carry0;
+  wire carry1;
+  wire carry2;
+  wire carry3;
+  wire carry4;
+  wire carry5;
+  wire carry6;
+  wire carry7;
+  wire carry8;
+  wire carry9;
+  wire carry10;
+  wire carry11;
+  wire carry12;
+  wire carry13;
+  wire carry14;
+  wire carry15;
+  wire fulladder_0_sum;
+  wire fulladder_0_carry;
+  FullAdder fulladder_0 (.a(a[0]), .b(b[0]), .c(1'b0), .sum(fulladder_0_sum), .carry(fulladder_0_carry));
+  assign out[0] = fulladder_0_sum;
+  assign carry0 = fulladder_0_carry;
+  wire fulladder_1_sum;
+  wire fulladder_1_carry;
+  FullAdder fulladder_1 (.a(a[1]), .b(b[1]), .c(carry0), .sum(fulladder_1_sum), .carry(fulladder_1_carry));
+  assign out[1] = fulladder_1_sum;
+  assign carry1 = fulladder_1_carry;
+  wire fulladder_2_sum;
+  wire fulladder_2_carry;
+  FullAdder fulladder_2 (.a(a[2]), .b(b[2]), .c(carry1), .sum(fulladder_2_sum), .carry(fulladder_2_carry));
+  assign out[2] = fulladder_2_sum;
+  assign carry2 = fulladder_2_carry;
+  wire fulladder_3_sum;
+  wire fulladder_3_carry;
+  FullAdder fulladder_3 (.a(a[3]), .b(b[3]), .c(carry2), .sum(fulladder_3_sum), .carry(fulladder_3_carry));
+  assign out[3] = fulladder_3_sum;
+  assign carry3 = fulladder_3_carry;
+  wire fulladder_4_sum;
+  wire fulladder_4_carry;
+  FullAdder fulladder_4 (.a(a[4]), .b(b[4]), .c(carry3), .sum(fulladder_4_sum), .carry(fulladder_4_carry));
+  assign out[4] = fulladder_4_sum;
+  assign carry4 = fulladder_4_carry;
+  wire fulladder_5_sum;
+  wire fulladder_5_carry;
+  FullAdder fulladder_5 (.a(a[5]), .b(b[5]), .c(carry4), .sum(fulladder_5_sum), .carry(fulladder_5_carry));
+  assign out[5] = fulladder_5_sum;
+  assign carry5 = fulladder_5_carry;
+  wire fulladder_6_sum;
+  wire fulladder_6_carry;
+  FullAdder fulladder_6 (.a(a[6]), .b(b[6]), .c(carry5), .sum(fulladder_6_sum), .carry(fulladder_6_carry));
+  assign out[6] = fulladder_6_sum;
+  assign carry6 = fulladder_6_carry;
+  wire fulladder_7_sum;
+  wire fulladder_7_carry;
+  FullAdder fulladder_7 (.a(a[7]), .b(b[7]), .c(carry6), .sum(fulladder_7_sum), .carry(fulladder_7_carry));
+  assign out[7] = fulladder_7_sum;
+  assign carry7 = fulladder_7_carry;
+  wire fulladder_8_sum;
+  wire fulladder_8_carry;
+  FullAdder fulladder_8 (.a(a[8]), .b(b[8]), .c(carry7), .sum(fulladder_8_sum), .carry(fulladder_8_carry));
+  assign out[8] = fulladder_8_sum;
+  assign carry8 = fulladder_8_carry;
+  wire fulladder_9_sum;
+  wire fulladder_9_carry;
+  FullAdder fulladder_9 (.a(a[9]), .b(b[9]), .c(carry8), .sum(fulladder_9_sum), .carry(fulladder_9_carry));
+  assign out[9] = fulladder_9_sum;
+  assign carry9 = fulladder_9_carry;
+  wire fulladder_10_sum;
+  wire fulladder_10_carry;
+  FullAdder fulladder_10 (.a(a[10]), .b(b[10]), .c(carry9), .sum(fulladder_10_sum), .carry(fulladder_10_carry));
+  assign out[10] = fulladder_10_sum;
+  assign carry10 = fulladder_10_carry;
+  wire fulladder_11_sum;
+  wire fulladder_11_carry;
+  FullAdder fulladder_11 (.a(a[11]), .b(b[11]), .c(carry10), .sum(fulladder_11_sum), .carry(fulladder_11_carry));
+  assign out[11] = fulladder_11_sum;
+  assign carry11 = fulladder_11_carry;
+  wire fulladder_12_sum;
+  wire fulladder_12_carry;
+  FullAdder fulladder_12 (.a(a[12]), .b(b[12]), .c(carry11), .sum(fulladder_12_sum), .carry(fulladder_12_carry));
+  assign out[12] = fulladder_12_sum;
+  assign carry12 = fulladder_12_carry;
+  wire fulladder_13_sum;
+  wire fulladder_13_carry;
+  FullAdder fulladder_13 (.a(a[13]), .b(b[13]), .c(carry12), .sum(fulladder_13_sum), .carry(fulladder_13_carry));
+  assign out[13] = fulladder_13_sum;
+  assign carry13 = fulladder_13_carry;
+  wire fulladder_14_sum;
+  wire fulladder_14_carry;
+  FullAdder fulladder_14 (.a(a[14]), .b(b[14]), .c(carry13), .sum(fulladder_14_sum), .carry(fulladder_14_carry));
+  assign out[14] = fulladder_14_sum;
+  assign carry14 = fulladder_14_carry;
+  wire fulladder_15_sum;
+  wire fulladder_15_carry;
+  FullAdder fulladder_15 (.a(a[15]), .b(b[15]), .c(carry14), .sum(fulladder_15_sum), .carry(fulladder_15_carry));
+  assign out[15] = fulladder_15_sum;
+  assign carry15 = fulladder_15_carry;
+endmodule
+
+module FullAdder(input a, input b, input c, output sum, output carry);
+  wire abSum;
+  wire abCarry;
+  wire abcCarry;
+  wire halfadder_0_sum;
+  wire halfadder_0_carry;
+  HalfAdder halfadder_0 (.a(a), .b(b), .sum(halfadder_0_sum), .carry(halfadder_0_carry));
+  assign abSum = halfadder_0_sum;
+  assign abCarry = halfadder_0_carry;
+  wire halfadder_1_sum;
+  wire halfadder_1_carry;
+  HalfAdder halfadder_1 (.a(abSum), .b(c), .sum(halfadder_1_sum), .carry(halfadder_1_carry));
+  assign sum = halfadder_1_sum;
+  assign abcCarry = halfadder_1_carry;
+  wire or_2_out;
+  Or or_2 (.a(abCarry), .b(abcCarry), .out(or_2_out));
+  assign carry = or_2_out;
+endmodule
+
+module HalfAdder(input a, input b, output sum, output carry);
+  wire xor_0_out;
+  Xor xor_0 (.a(a), .b(b), .out(xor_0_out));
+  assign sum = xor_0_out;
+  wire and_1_out;
+  And and_1 (.a(a), .b(b), .out(and_1_out));
+  assign carry = and_1_out;
+endmodule
+
+module Xor(input a, input b, output out);
+  wire notA;
+  wire notB;
+  wire aAndNotB;
+  wire bAndNotA;
+  wire not_0_out;
+  Not not_0 (.in(a), .out(not_0_out));
+  assign notA = not_0_out;
+  wire not_1_out;
+  Not not_1 (.in(b), .out(not_1_out));
+  assign notB = not_1_out;
+  wire and_2_out;
+  And and_2 (.a(a), .b(notB), .out(and_2_out));
+  assign aAndNotB = and_2_out;
+  wire and_3_out;
+  And and_3 (.a(b), .b(notA), .out(and_3_out));
+  assign bAndNotA = and_3_out;
+  wire or_4_out;
+  Or or_4 (.a(aAndNotB), .b(bAndNotA), .out(or_4_out));
+  assign out = or_4_out;
+endmodule
+
+module Or8Way(input [7:0] in, output out);
+  wire orUpTo1;
+  wire orUpTo2;
+  wire orUpTo3;
+  wire orUpTo4;
+  wire orUpTo5;
+  wire orUpTo6;
+  wire or_0_out;
+  Or or_0 (.a(in[0]), .b(in[1]), .out(or_0_out));
+  assign orUpTo1 = or_0_out;
+  wire or_1_out;
+  Or or_1 (.a(orUpTo1), .b(in[2]), .out(or_1_out));
+  assign orUpTo2 = or_1_out;
+  wire or_2_out;
+  Or or_2 (.a(orUpTo2), .b(in[3]), .out(or_2_out));
+  assign orUpTo3 = or_2_out;
+  wire or_3_out;
+  Or or_3 (.a(orUpTo3), .b(in[4]), .out(or_3_out));
+  assign orUpTo4 = or_3_out;
+  wire or_4_out;
+  Or or_4 (.a(orUpTo4), .b(in[5]), .out(or_4_out));
+  assign orUpTo5 = or_4_out;
+  wire or_5_out;
+  Or or_5 (.a(orUpTo5), .b(in[6]), .out(or_5_out));
+  assign orUpTo6 = or_5_out;
+  wire or_6_out;
+  Or or_6 (.a(orUpTo6), .b(in[7]), .out(or_6_out));
+  assign out = or_6_out;
+endmodule
+
@@ -0,0 +1,60 @@
+use std::{fmt, io};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    Script {
+        line: usize,
+        message: String,
+    },
+    /// An output line didn't match the compare-to file. `line` counts output
+    /// lines from 1, header included.
+    Comparison {
+        line: usize,
+        column: Option<String>,
+        expected: String,
+        found: String,
+    },
+    Io(io::Error),
+}
+
+impl Error {
+    pub fn script(line: usize, message: impl Into<String>) -> Self {
+        Self::Script {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Script { line, message } => {
+                write!(f, "[line {}] script error: {}", line, message)
+            }
+            Self::Comparison {
+                line,
+                column,
+                expected,
+                found,
+            } => {
+                write!(f, "comparison failure at line {}", line)?;
+                if let Some(column) = column {
+                    write!(f, ", column `{}`", column)?;
+                }
+                write!(f, ": expected `{}`, found `{}`", expected, found)
+            }
+            Self::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
@@ -0,0 +1,4 @@
+pub mod error;
+pub mod output;
+pub mod parser;
+pub mod runner;
@@ -0,0 +1,175 @@
+use std::fmt;
+
+/// A value read from a simulator for an output column.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Number(i64),
+    Text(String),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(n) => write!(f, "{}", n),
+            Self::Text(s) => f.write_str(s),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Binary,
+    Decimal,
+    Hex,
+    String,
+}
+
+/// A column of an `output-list`, such as `RAM[0]%D2.6.2`: the variable to
+/// print, its format, and the padding to its left, its width and the
+/// padding to its right.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub format: Format,
+    pub left: usize,
+    pub width: usize,
+    pub right: usize,
+}
+
+impl Column {
+    pub fn parse(spec: &str) -> Option<Self> {
+        let Some((name, format)) = spec.split_once('%') else {
+            return Some(Self {
+                name: spec.to_string(),
+                format: Format::Decimal,
+                left: 1,
+                width: 6,
+                right: 1,
+            });
+        };
+        let (format, sizes) = format.split_at_checked(1)?;
+        let format = match format {
+            "B" => Format::Binary,
+            "D" => Format::Decimal,
+            "X" => Format::Hex,
+            "S" => Format::String,
+            _ => return None,
+        };
+        let mut sizes = sizes.split('.').map(|s| s.parse().ok());
+        let (left, width, right) = (sizes.next()??, sizes.next()??, sizes.next()??);
+        if sizes.next().is_some() {
+            return None;
+        }
+        Some(Self {
+            name: name.to_string(),
+            format,
+            left,
+            width,
+            right,
+        })
+    }
+
+    /// The column's name, centered and cut to fit its whole width.
+    pub fn header(&self) -> String {
+        let total = self.left + self.width + self.right;
+        let name: String = self.name.chars().take(total).collect();
+        let padding = total - name.chars().count();
+        let left = padding / 2;
+        format!("{}{}{}", " ".repeat(left), name, " ".repeat(padding - left))
+    }
+
+    pub fn cell(&self, value: &Value) -> String {
+        let width = self.width;
+        let text = match (self.format, value) {
+            (Format::Binary, Value::Number(n)) => format!("{:0>width$b}", mask(*n, width)),
+            (Format::Hex, Value::Number(n)) => format!("{:0>width$X}", mask(*n, width * 4)),
+            (Format::String, value) => format!("{:<width$}", value.to_string()),
+            (_, value) => format!("{:>width$}", value.to_string()),
+        };
+        format!(
+            "{}{}{}",
+            " ".repeat(self.left),
+            text,
+            " ".repeat(self.right)
+        )
+    }
+}
+
+/// Keeps the lowest `bits` bits, which is how negative numbers show up in
+/// binary and hex columns.
+fn mask(n: i64, bits: usize) -> u64 {
+    if bits >= 64 {
+        n as u64
+    } else {
+        n as u64 & ((1 << bits) - 1)
+    }
+}
+
+/// Joins cells into an output line, e.g. `|  RAM[0]  |`.
+pub fn line(cells: impl IntoIterator<Item = String>) -> String {
+    let mut line = String::from("|");
+    for cell in cells {
+        line += &cell;
+        line += "|";
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(spec: &str) -> Column {
+        Column::parse(spec).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            column("RAM[0]%D2.6.2"),
+            Column {
+                name: "RAM[0]".to_string(),
+                format: Format::Decimal,
+                left: 2,
+                width: 6,
+                right: 2,
+            }
+        );
+        assert_eq!(column("time%S1.4.1").format, Format::String);
+        assert!(Column::parse("a%B1.16").is_none());
+        assert!(Column::parse("a%Q1.1.1").is_none());
+    }
+
+    #[test]
+    fn test_header() {
+        assert_eq!(column("RAM[0]%D2.6.2").header(), "  RAM[0]  ");
+        assert_eq!(column("a%B1.16.1").header(), "        a         ");
+        assert_eq!(column("RAM[3006]%D1.6.1").header(), "RAM[3006");
+        assert_eq!(column("writeM%B3.1.3").header(), "writeM ");
+    }
+
+    #[test]
+    fn test_cell() {
+        assert_eq!(
+            column("RAM[0]%D2.6.2").cell(&Value::Number(257)),
+            "     257  "
+        );
+        assert_eq!(column("out%D1.6.1").cell(&Value::Number(-2)), "     -2 ");
+        assert_eq!(column("sel%B2.1.2").cell(&Value::Number(1)), "  1  ");
+        assert_eq!(
+            column("out%B1.16.1").cell(&Value::Number(-1)),
+            " 1111111111111111 "
+        );
+        assert_eq!(column("x%X1.4.1").cell(&Value::Number(-1)), " FFFF ");
+        assert_eq!(
+            column("time%S1.4.1").cell(&Value::Text("0+".to_string())),
+            " 0+   "
+        );
+    }
+
+    #[test]
+    fn test_line() {
+        let cells = ["a".to_string(), " b ".to_string()];
+        assert_eq!(line(cells), "|a| b |");
+    }
+}
@@ -0,0 +1,309 @@
+use crate::error::{Error, Result};
+
+/// A statement of a test script. Commands are kept as plain words since
+/// their meaning depends on the simulator running the script.
+#[derive(Debug, PartialEq)]
+pub enum Statement {
+    Command {
+        line: usize,
+        words: Vec<String>,
+    },
+    Repeat {
+        line: usize,
+        count: u64,
+        body: Vec<Statement>,
+    },
+    While {
+        line: usize,
+        condition: Condition,
+        body: Vec<Statement>,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Condition {
+    pub left: String,
+    pub operator: Operator,
+    pub right: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl Operator {
+    pub fn eval(self, left: i64, right: i64) -> bool {
+        match self {
+            Self::Equal => left == right,
+            Self::NotEqual => left != right,
+            Self::Less => left < right,
+            Self::LessEqual => left <= right,
+            Self::Greater => left > right,
+            Self::GreaterEqual => left >= right,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Word(String),
+    /// The end of a command: `,`, `;` or `!`.
+    End,
+    LeftBrace,
+    RightBrace,
+}
+
+pub fn parse(source: &str) -> Result<Vec<Statement>> {
+    let tokens = scan(source)?;
+    let mut parser = Parser { tokens, current: 0 };
+    let statements = parser.block()?;
+    match parser.tokens.get(parser.current) {
+        Some((line, _)) => Err(Error::script(*line, "unexpected '}'")),
+        None => Ok(statements),
+    }
+}
+
+fn scan(source: &str) -> Result<Vec<(usize, Token)>> {
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            ',' | ';' | '!' => tokens.push((line, Token::End)),
+            '{' => tokens.push((line, Token::LeftBrace)),
+            '}' => tokens.push((line, Token::RightBrace)),
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.peek().is_some_and(|c| *c != '\n') {
+                    chars.next();
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                loop {
+                    match chars.next() {
+                        Some('/') if previous == '*' => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            previous = c;
+                        }
+                        None => return Err(Error::script(line, "unterminated comment")),
+                    }
+                }
+            }
+            '"' => {
+                let mut word = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\n') | None => {
+                            return Err(Error::script(line, "unterminated string"))
+                        }
+                        Some(c) => word.push(c),
+                    }
+                }
+                tokens.push((line, Token::Word(word)));
+            }
+            c if c.is_whitespace() => (),
+            c => {
+                let mut word = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || ",;!{}\"".contains(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push((line, Token::Word(word)));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    current: usize,
+}
+
+impl Parser {
+    /// Statements up to the end of input or a closing brace, which is left
+    /// for the caller to consume.
+    fn block(&mut self) -> Result<Vec<Statement>> {
+        let mut statements = vec![];
+        while let Some((line, token)) = self.tokens.get(self.current) {
+            let line = *line;
+            match token {
+                Token::RightBrace => break,
+                Token::End => self.current += 1,
+                Token::LeftBrace => return Err(Error::script(line, "unexpected '{'")),
+                Token::Word(word) if word == "repeat" => {
+                    self.current += 1;
+                    let count = match self.words().as_slice() {
+                        [count] => count
+                            .parse()
+                            .map_err(|_| Error::script(line, "expect repeat count"))?,
+                        _ => return Err(Error::script(line, "expect repeat count")),
+                    };
+                    let body = self.braced_block(line)?;
+                    statements.push(Statement::Repeat { line, count, body });
+                }
+                Token::Word(word) if word == "while" => {
+                    self.current += 1;
+                    let condition = match self.words().as_slice() {
+                        [left, operator, right] => Condition {
+                            left: left.clone(),
+                            operator: operator_from_str(operator)
+                                .ok_or_else(|| Error::script(line, "unknown operator"))?,
+                            right: right.clone(),
+                        },
+                        _ => return Err(Error::script(line, "expect while condition")),
+                    };
+                    let body = self.braced_block(line)?;
+                    statements.push(Statement::While {
+                        line,
+                        condition,
+                        body,
+                    });
+                }
+                Token::Word(_) => {
+                    let words = self.words();
+                    match self.tokens.get(self.current) {
+                        Some((_, Token::End)) => self.current += 1,
+                        _ => return Err(Error::script(line, "expect ',', ';' or '!'")),
+                    }
+                    statements.push(Statement::Command { line, words });
+                }
+            }
+        }
+        Ok(statements)
+    }
+
+    fn braced_block(&mut self, line: usize) -> Result<Vec<Statement>> {
+        match self.tokens.get(self.current) {
+            Some((_, Token::LeftBrace)) => self.current += 1,
+            _ => return Err(Error::script(line, "expect '{'")),
+        }
+        let body = self.block()?;
+        match self.tokens.get(self.current) {
+            Some((_, Token::RightBrace)) => self.current += 1,
+            _ => return Err(Error::script(line, "expect '}'")),
+        }
+        Ok(body)
+    }
+
+    fn words(&mut self) -> Vec<String> {
+        let mut words = vec![];
+        while let Some((_, Token::Word(word))) = self.tokens.get(self.current) {
+            words.push(word.clone());
+            self.current += 1;
+        }
+        words
+    }
+}
+
+fn operator_from_str(s: &str) -> Option<Operator> {
+    let operator = match s {
+        "=" => Operator::Equal,
+        "<>" => Operator::NotEqual,
+        "<" => Operator::Less,
+        "<=" => Operator::LessEqual,
+        ">" => Operator::Greater,
+        ">=" => Operator::GreaterEqual,
+        _ => return None,
+    };
+    Some(operator)
+}
+
+/// Parses a value as written in scripts: `-12`, `%D-12`, `%B101` or `%XFF`.
+pub fn parse_value(s: &str) -> Option<i64> {
+    let (radix, digits) = match s.strip_prefix('%') {
+        Some(rest) => match rest.split_at_checked(1)? {
+            ("B", digits) => (2, digits),
+            ("X", digits) => (16, digits),
+            ("D", digits) => (10, digits),
+            _ => return None,
+        },
+        None => (10, s),
+    };
+    i64::from_str_radix(digits, radix).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(line: usize, words: &[&str]) -> Statement {
+        Statement::Command {
+            line,
+            words: words.iter().map(|w| w.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_commands() {
+        let script = "load Foo.asm,\n// comment\noutput-list RAM[0]%D2.6.2\n  A%B1.16.1;\n";
+        assert_eq!(
+            parse(script).unwrap(),
+            vec![
+                command(1, &["load", "Foo.asm"]),
+                command(3, &["output-list", "RAM[0]%D2.6.2", "A%B1.16.1"]),
+            ]
+        );
+        assert_eq!(
+            parse("echo \"Hi, there\"; /* a\nb */ tick, tock!").unwrap(),
+            vec![
+                command(1, &["echo", "Hi, there"]),
+                command(2, &["tick"]),
+                command(2, &["tock"]),
+            ]
+        );
+        assert!(parse("tick").is_err());
+    }
+
+    #[test]
+    fn test_blocks() {
+        assert_eq!(
+            parse("repeat 3 {\n  ticktock;\n}\nwhile out <> 75 { eval; }").unwrap(),
+            vec![
+                Statement::Repeat {
+                    line: 1,
+                    count: 3,
+                    body: vec![command(2, &["ticktock"])],
+                },
+                Statement::While {
+                    line: 4,
+                    condition: Condition {
+                        left: "out".to_string(),
+                        operator: Operator::NotEqual,
+                        right: "75".to_string(),
+                    },
+                    body: vec![command(4, &["eval"])],
+                },
+            ]
+        );
+        assert!(parse("repeat { tick; }").is_err());
+        assert!(parse("repeat 2 { tick;").is_err());
+        assert!(parse("tick; }").is_err());
+    }
+
+    #[test]
+    fn test_parse_value() {
+        assert_eq!(parse_value("256"), Some(256));
+        assert_eq!(parse_value("-1"), Some(-1));
+        assert_eq!(parse_value("%D-3"), Some(-3));
+        assert_eq!(parse_value("%B0011000000111001"), Some(12345));
+        assert_eq!(parse_value("%XFF"), Some(255));
+        assert_eq!(parse_value("%Q1"), None);
+        assert_eq!(parse_value("RAM[0]"), None);
+    }
+}
@@ -0,0 +1,316 @@
+use crate::error::{Error, Result};
+use crate::output::{self, Column, Value};
+use crate::parser::{self, parse_value, Condition, Statement};
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+/// The part of a test script that depends on what's being tested, be it a
+/// chip, a machine language program or a VM program. Errors are plain
+/// messages; the runner adds the script line to them.
+pub trait Simulator {
+    /// Handles `load`, with paths relative to the script's directory. `file`
+    /// is `None` for a bare `load`.
+    fn load(&mut self, dir: &Path, file: Option<&str>) -> std::result::Result<(), String>;
+
+    fn get(&self, name: &str) -> std::result::Result<Value, String>;
+
+    fn set(&mut self, name: &str, value: i64) -> std::result::Result<(), String>;
+
+    /// Handles any other command, like `eval`, `ticktock` or `vmstep`.
+    fn command(&mut self, words: &[String]) -> std::result::Result<(), String>;
+
+    /// Sees `echo` messages, which usually ask the user to do something.
+    fn echo(&mut self, _message: &str) {}
+}
+
+/// Runs test scripts, taking care of the commands every dialect shares:
+/// `output-file`, `compare-to`, `output-list`, `output`, `echo`, `repeat`
+/// and `while`.
+pub struct Runner<S> {
+    pub simulator: S,
+    dir: PathBuf,
+    columns: Vec<Column>,
+    output: Vec<String>,
+    output_file: Option<PathBuf>,
+    compare_to: Option<Vec<String>>,
+    echoes: Vec<String>,
+}
+
+impl<S: Simulator> Runner<S> {
+    pub fn new(simulator: S, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            simulator,
+            dir: dir.into(),
+            columns: vec![],
+            output: vec![],
+            output_file: None,
+            compare_to: None,
+            echoes: vec![],
+        }
+    }
+
+    pub fn run(&mut self, source: &str) -> Result<()> {
+        let statements = parser::parse(source)?;
+        self.statements(&statements)
+    }
+
+    /// Everything output so far, one line per row.
+    pub fn output(&self) -> String {
+        self.output
+            .iter()
+            .map(|line| format!("{}\n", line))
+            .collect()
+    }
+
+    /// Writes the output to the file named by `output-file`, if any.
+    pub fn write_output_file(&self) -> io::Result<()> {
+        match &self.output_file {
+            Some(path) => fs::write(path, self.output()),
+            None => Ok(()),
+        }
+    }
+
+    pub fn echoes(&self) -> &[String] {
+        &self.echoes
+    }
+
+    fn statements(&mut self, statements: &[Statement]) -> Result<()> {
+        for statement in statements {
+            match statement {
+                Statement::Command { line, words } => self.command(*line, words)?,
+                Statement::Repeat { count, body, .. } => {
+                    for _ in 0..*count {
+                        self.statements(body)?;
+                    }
+                }
+                Statement::While {
+                    line,
+                    condition,
+                    body,
+                } => {
+                    while self.eval(*line, condition)? {
+                        self.statements(body)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn command(&mut self, line: usize, words: &[String]) -> Result<()> {
+        let words_str: Vec<&str> = words.iter().map(String::as_str).collect();
+        match words_str.as_slice() {
+            ["load", file] => self.simulator.load(&self.dir, Some(file)),
+            ["load"] => self.simulator.load(&self.dir, None),
+            ["output-file", file] => {
+                self.output_file = Some(self.dir.join(file));
+                Ok(())
+            }
+            ["compare-to", file] => {
+                let compare_to = fs::read_to_string(self.dir.join(file))?;
+                self.compare_to = Some(compare_to.lines().map(str::to_string).collect());
+                Ok(())
+            }
+            ["output-list", specs @ ..] => {
+                self.columns = specs
+                    .iter()
+                    .map(|spec| {
+                        Column::parse(spec).ok_or_else(|| {
+                            Error::script(line, format!("invalid column `{}`", spec))
+                        })
+                    })
+                    .collect::<Result<_>>()?;
+                let header = output::line(self.columns.iter().map(Column::header));
+                return self.emit(header);
+            }
+            ["output"] => {
+                let cells = self
+                    .columns
+                    .iter()
+                    .map(|column| {
+                        let value = self.simulator.get(&column.name);
+                        value.map(|value| column.cell(&value))
+                    })
+                    .collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(|e| Error::script(line, e))?;
+                return self.emit(output::line(cells));
+            }
+            ["echo", text @ ..] => {
+                let message = text.join(" ");
+                self.simulator.echo(&message);
+                self.echoes.push(message);
+                Ok(())
+            }
+            ["clear-echo"] => Ok(()),
+            ["set", name, value] => match parse_value(value) {
+                Some(value) => self.simulator.set(name, value),
+                None => Err(format!("invalid value `{}`", value)),
+            },
+            _ => self.simulator.command(words),
+        }
+        .map_err(|e| Error::script(line, e))
+    }
+
+    fn eval(&self, line: usize, condition: &Condition) -> Result<bool> {
+        let left = self.value(line, &condition.left)?;
+        let right = self.value(line, &condition.right)?;
+        Ok(condition.operator.eval(left, right))
+    }
+
+    /// A literal, or the current value of a variable.
+    fn value(&self, line: usize, s: &str) -> Result<i64> {
+        if let Some(value) = parse_value(s) {
+            return Ok(value);
+        }
+        match self.simulator.get(s).map_err(|e| Error::script(line, e))? {
+            Value::Number(n) => Ok(n),
+            Value::Text(_) => Err(Error::script(line, format!("`{}` isn't a number", s))),
+        }
+    }
+
+    /// Adds a line to the output, comparing it against the compare-to file.
+    fn emit(&mut self, found: String) -> Result<()> {
+        self.output.push(found);
+        let line = self.output.len();
+        let Some(expected) = self.compare_to.as_ref().and_then(|c| c.get(line - 1)) else {
+            return Ok(());
+        };
+        let found = &self.output[line - 1];
+        if matches(expected, found) {
+            return Ok(());
+        }
+        let column = expected
+            .split('|')
+            .zip(found.split('|'))
+            .skip(1)
+            .position(|(e, f)| !matches(e, f))
+            .and_then(|i| self.columns.get(i))
+            .map(|column| column.name.clone());
+        Err(Error::Comparison {
+            line,
+            column,
+            expected: expected.clone(),
+            found: found.clone(),
+        })
+    }
+}
+
+/// Compares two pieces of output, where `*` in the expected one matches
+/// any character.
+fn matches(expected: &str, found: &str) -> bool {
+    let (expected, found) = (expected.trim_end(), found.trim_end());
+    expected.chars().count() == found.chars().count()
+        && expected
+            .chars()
+            .zip(found.chars())
+            .all(|(e, f)| e == '*' || e == f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// A counter that goes up by one on every `tick`.
+    #[derive(Default)]
+    struct Counter {
+        loaded: Option<String>,
+        variables: HashMap<String, i64>,
+    }
+
+    impl Simulator for Counter {
+        fn load(&mut self, _dir: &Path, file: Option<&str>) -> std::result::Result<(), String> {
+            self.loaded = file.map(str::to_string);
+            Ok(())
+        }
+
+        fn get(&self, name: &str) -> std::result::Result<Value, String> {
+            match name {
+                "name" => Ok(Value::Text(self.loaded.clone().unwrap_or_default())),
+                _ => Ok(Value::Number(*self.variables.get(name).unwrap_or(&0))),
+            }
+        }
+
+        fn set(&mut self, name: &str, value: i64) -> std::result::Result<(), String> {
+            self.variables.insert(name.to_string(), value);
+            Ok(())
+        }
+
+        fn command(&mut self, words: &[String]) -> std::result::Result<(), String> {
+            match words {
+                [tick] if tick == "tick" => {
+                    *self.variables.entry("n".to_string()).or_default() += 1;
+                    Ok(())
+                }
+                _ => Err(format!("unknown command `{}`", words.join(" "))),
+            }
+        }
+    }
+
+    #[test]
+    fn test_run() {
+        let mut runner = Runner::new(Counter::default(), ".");
+        let script = "
+            load Foo.hdl,
+            output-list name%S1.3.1 n%D1.3.1 n%B1.4.1;
+            set n %B10, output;
+            repeat 3 { tick; }
+            output;
+            while n < 9 { tick; }
+            output;
+        ";
+        runner.run(script).unwrap();
+        assert_eq!(
+            runner.output(),
+            "|name |  n  |  n   |\n\
+             | Foo.hdl |   2 | 0010 |\n\
+             | Foo.hdl |   5 | 0101 |\n\
+             | Foo.hdl |   9 | 1001 |\n"
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut runner = Runner::new(Counter::default(), ".");
+        let error = runner.run("tick;\n\ntock;").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "[line 3] script error: unknown command `tock`"
+        );
+        let error = runner.run("set n %Q1;").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "[line 1] script error: invalid value `%Q1`"
+        );
+    }
+
+    #[test]
+    fn test_comparison() {
+        let mut runner = Runner::new(Counter::default(), ".");
+        runner.compare_to = Some(vec![
+            "|  a  |  n  |".to_string(),
+            "|*****|   1 |".to_string(),
+            "|   2 |   3 |".to_string(),
+        ]);
+        runner
+            .run("output-list a%D1.3.1 n%D1.3.1; set a 7, tick, output;")
+            .unwrap();
+        let error = runner.run("tick, output;").unwrap_err();
+        match error {
+            Error::Comparison { line, column, .. } => {
+                assert_eq!(line, 3);
+                assert_eq!(column.as_deref(), Some("a"));
+            }
+            e => panic!("unexpected {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_matches() {
+        assert!(matches("| 12 |", "| 12 |"));
+        assert!(matches("|****|", "| 12 |"));
+        assert!(matches("| 12 |  ", "| 12 |"));
+        assert!(!matches("| 12 |", "| 13 |"));
+        assert!(!matches("| 12 |", "| 12 | 1 |"));
+    }
+}
@@ -0,0 +1,157 @@
+use cli::{exit_with_usage, take_arg, take_flag};
+use emulator::cpu::{KBD, MEMORY_SIZE};
+use emulator::keyboard::Keyboard;
+use emulator::screen;
+use std::env::args;
+use std::path::{Path, PathBuf};
+use std::{fmt, fs, process};
+use test_script::error::Error;
+use test_script::runner::Runner;
+use vm_translator::interpreter::{Vm, SP};
+use vm_translator::script::VmEmulator;
+
+const USAGE: &str = "\
+Usage: vm-emulator script.tst
+       vm-emulator [-n steps] [-s address=value]... [-k keys.txt] [-t text.txt]
+                   [--type-period steps] [--screen-at step]... [--screen-at-halt]
+                   [-f png|pbm] [-o directory] file.vm|directory
+
+Runs a VM emulator test script, such as FibonacciElementVME.tst, or runs a
+VM program without translating it, from Sys.init like the code of
+vm-translator does if there is one, from Main.main if there is one, and else
+from its first command with SP at 256, until it halts or for the given number
+of steps (10000000 by default), writing the screen to program-<step>.png at
+the chosen steps or when the program halts. The Jack OS classes are built
+in, for those the program doesn't define.
+
+Key events come from lines like `1000 press left` and `2000 release` in
+keys.txt, or from typing text.txt, a key every 10000 steps by default.";
+
+fn main() {
+    let args: Vec<String> = args().skip(1).collect();
+    match args.as_slice() {
+        [path] if path.ends_with(".tst") => run_script(Path::new(path)),
+        _ => run_program(args),
+    }
+}
+
+fn run_script(path: &Path) {
+    let script = fs::read_to_string(path).unwrap_or_else(exit_with_error);
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let mut runner = Runner::new(VmEmulator::default(), dir);
+    let result = runner.run(&script);
+    // Like the official tools, keep the output up to a failure.
+    runner.write_output_file().unwrap_or_else(exit_with_error);
+    match result {
+        Ok(()) => println!("End of script - Comparison ended successfully"),
+        Err(e @ Error::Comparison { .. }) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        Err(e) => exit_with_error(e),
+    }
+}
+
+fn run_program(mut args: Vec<String>) {
+    let steps: u64 = take_arg(&mut args, "-n", USAGE)
+        .map(|n| n.parse().unwrap_or_else(exit_with_error))
+        .unwrap_or(10_000_000);
+    let mut settings: Vec<(usize, i16)> = vec![];
+    while let Some(setting) = take_arg(&mut args, "-s", USAGE) {
+        let parsed = setting
+            .split_once('=')
+            .and_then(|(address, value)| Some((address.parse().ok()?, value.parse().ok()?)));
+        match parsed {
+            Some((address, value)) if address < MEMORY_SIZE => settings.push((address, value)),
+            _ => usage(),
+        }
+    }
+    let mut screen_at: Vec<u64> = vec![];
+    while let Some(step) = take_arg(&mut args, "--screen-at", USAGE) {
+        screen_at.push(step.parse().unwrap_or_else(exit_with_error));
+    }
+    let keys = take_arg(&mut args, "-k", USAGE);
+    let text = take_arg(&mut args, "-t", USAGE);
+    let period = take_arg(&mut args, "--type-period", USAGE)
+        .map(|n| n.parse().unwrap_or_else(exit_with_error))
+        .unwrap_or(10_000);
+    let at_halt = take_flag(&mut args, "--screen-at-halt");
+    let format = take_arg(&mut args, "-f", USAGE).unwrap_or_else(|| "png".to_string());
+    let render = match format.as_str() {
+        "png" => screen::png,
+        "pbm" => screen::pbm,
+        _ => usage(),
+    };
+    let out_dir = take_arg(&mut args, "-o", USAGE).map(PathBuf::from);
+    let [path] = args.as_slice() else { usage() };
+    let path = Path::new(path);
+    let out_dir = out_dir.unwrap_or_else(|| {
+        path.parent()
+            .unwrap_or_else(|| Path::new("."))
+            .to_path_buf()
+    });
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+
+    let mut keyboard = Keyboard::default();
+    if let Some(keys) = keys {
+        let script = fs::read_to_string(keys).unwrap_or_else(exit_with_error);
+        let keys = Keyboard::parse(&script).unwrap_or_else(exit_with_error);
+        keyboard.merge(keys, 0);
+    }
+    if let Some(text) = text {
+        let text = fs::read_to_string(text).unwrap_or_else(exit_with_error);
+        keyboard.merge(Keyboard::typing(&text, period, period), 0);
+    }
+
+    let mut vm = Vm::load(path).unwrap_or_else(exit_with_error);
+    if vm.boot().is_err() {
+        vm.ram[SP] = 256;
+    }
+    for &(address, value) in &settings {
+        vm.ram[address] = value as u16;
+    }
+    let mut written = None;
+    let mut snapshot = |vm: &Vm| {
+        if written == Some(vm.steps) {
+            return;
+        }
+        written = Some(vm.steps);
+        let out_path = out_dir.join(format!("{}-{}.{}", stem, vm.steps, format));
+        fs::write(&out_path, render(&vm.ram)).unwrap_or_else(exit_with_error);
+        eprintln!("Successfully wrote {}", out_path.to_string_lossy());
+    };
+    let halted = loop {
+        if screen_at.contains(&vm.steps) {
+            snapshot(&vm);
+        }
+        if vm.is_halted() {
+            if at_halt {
+                snapshot(&vm);
+            }
+            break true;
+        }
+        if vm.steps >= steps {
+            break false;
+        }
+        if let Some(key) = keyboard.poll(vm.steps) {
+            vm.ram[KBD] = key;
+        }
+        vm.step();
+    };
+    let state = if halted { "Halted" } else { "Stopped" };
+    let sp = vm.ram[SP];
+    let top = vm.ram[sp.wrapping_sub(1) as usize % MEMORY_SIZE] as i16;
+    println!("{} at step {}: SP={} top={}", state, vm.steps, sp, top);
+    if let Some(code) = vm.os.error {
+        println!("Sys.error({})", code);
+    }
+}
+
+fn usage<V>() -> V {
+    exit_with_usage(USAGE)
+}
+
+fn exit_with_error<V, E: fmt::Display>(e: E) -> V {
+    eprintln!("{}", e);
+    process::exit(65)
+}
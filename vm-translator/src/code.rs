@@ -31,6 +31,24 @@ pub fn translate(commands: &[Option<Command>], static_prefix: &str) -> String {
         .collect()
 }
 
+/// Like `translate`, but prefixes the statics of each function with the class
+/// part of its name, e.g. `Foo` for `function Foo.bar 0`. This is for input
+/// that mixes several classes with no file names to go by, such as stdin.
+pub fn translate_by_class(commands: &[Option<Command>], default_prefix: &str) -> String {
+    let mut translator = Translator::default();
+    let mut static_prefix = default_prefix;
+    commands
+        .iter()
+        .flatten()
+        .map(|c| {
+            if let Function(name, _) = c {
+                static_prefix = name.split('.').next().unwrap_or(default_prefix);
+            }
+            translator.translate(c, static_prefix)
+        })
+        .collect()
+}
+
 pub fn boot() -> String {
     // Set SP to 256 and call Sys.init.
     hasm!("@256", "D=A", "@SP", "M=D").to_string() + &call("Sys.init", "BOOT", 0, 0)
@@ -318,3 +336,29 @@ fn point_to(i: u16) -> &'static str {
         _ => unimplemented!("pointer segment only supports 0 and 1"),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_translate_by_class() {
+        let source = "\
+push static 0
+function Foo.get 0
+push static 1
+return
+function Bar.set 0
+pop static 0
+return
+";
+        let commands = parse(source).unwrap().1;
+        let asm = translate_by_class(&commands, "Stdin");
+        let statics: Vec<&str> = asm
+            .lines()
+            .filter(|line| line.starts_with('@') && line.contains('.'))
+            .collect();
+        assert_eq!(statics, ["@Stdin.0", "@Foo.1", "@Bar.0"]);
+    }
+}
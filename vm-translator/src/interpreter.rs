@@ -0,0 +1,543 @@
+use crate::os::{self, Os, Outcome};
+use crate::parser::{parse, Command, Segment};
+use emulator::cpu::MEMORY_SIZE;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// The registers `code` keeps in RAM, at the same addresses.
+pub const SP: usize = 0;
+pub const LCL: usize = 1;
+pub const ARG: usize = 2;
+pub const THIS: usize = 3;
+pub const THAT: usize = 4;
+const TEMP: u16 = 5;
+/// Where the assembler allocates the first variable, and so the first static.
+const STATIC: u16 = 16;
+
+/// Where `push` and `pop` find a word.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Location {
+    Constant(u16),
+    /// The `i`th word past the address in LCL, ARG, THIS or THAT.
+    Indirect(usize, u16),
+    /// A static, pointer or temp word.
+    Direct(u16),
+}
+
+/// A command with its labels, functions and statics resolved.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Instruction {
+    Add,
+    Sub,
+    Neg,
+    Eq,
+    Gt,
+    Lt,
+    And,
+    Or,
+    Not,
+    Goto(usize),
+    IfGoto(usize),
+    Call {
+        function: usize,
+        args: u16,
+    },
+    /// A call to the OS built into the interpreter.
+    Native {
+        function: os::Function,
+        args: u16,
+    },
+    Function {
+        locals: u16,
+    },
+    Return,
+    Push(Location),
+    Pop(Location),
+}
+
+/// Runs VM commands directly, with the segments, stack and call frames in
+/// RAM exactly where the code `translate` generates puts them.
+pub struct Vm {
+    pub ram: Vec<u16>,
+    /// The index of the next command to run, counting the commands of every
+    /// file in the order they were given. Labels aren't commands that run,
+    /// as in the official VM emulator, so they don't count.
+    pub pc: usize,
+    /// Commands run since the program was loaded.
+    pub steps: u64,
+    pub os: Os,
+    instructions: Vec<Instruction>,
+    functions: HashMap<String, usize>,
+    /// The OS calls waiting for a function of the program they called: the
+    /// command making each, and SP once the function returned.
+    pending: Vec<(usize, u16)>,
+    /// The functions `boot` has yet to call, last first, each when the
+    /// previous one returns to the end of the program.
+    boot: Vec<usize>,
+}
+
+impl Vm {
+    /// Loads the commands of each file, parsed with `parse`, with the static
+    /// prefix of the file. Labels belong to the function they are in, like
+    /// `label LOOP` in `Foo.bar` being `Foo.bar$LOOP` in the VM spec.
+    ///
+    /// Calls to the classes of the Jack OS go to the OS built into the
+    /// interpreter, unless the files define the class. Arrays and strings of
+    /// the OS are then allocated with the files' `Memory`, if they have one.
+    pub fn new(files: &[(&str, &[Option<Command>])]) -> Result<Self, String> {
+        // Functions and labels first, as code can refer to them before them.
+        let mut functions = HashMap::new();
+        let mut classes = HashSet::new();
+        let mut labels = HashMap::new();
+        let mut index = 0;
+        for &(_, commands) in files {
+            let mut function = "";
+            for command in commands.iter().flatten() {
+                match *command {
+                    Command::Function(name, _) => {
+                        function = name;
+                        functions.insert(name.to_string(), index);
+                        classes.insert(name.split('.').next().unwrap_or_default());
+                    }
+                    Command::Label(label) => {
+                        labels.insert((function, label), index);
+                        continue;
+                    }
+                    _ => {}
+                }
+                index += 1;
+            }
+        }
+        // Return addresses are words in RAM, and so is the end of the
+        // program the boot call returns to.
+        if index >= 1 << 16 {
+            return Err(format!("too many commands: {}", index));
+        }
+
+        let mut statics = HashMap::new();
+        let mut instructions = vec![];
+        for &(prefix, commands) in files {
+            let mut function = "";
+            for (i, command) in commands.iter().enumerate() {
+                let Some(command) = command else { continue };
+                let error = |message: String| format!("{}:{}: {}", prefix, i + 1, message);
+                let label = |label: &str| {
+                    labels
+                        .get(&(function, label))
+                        .copied()
+                        .ok_or_else(|| error(format!("unknown label `{}`", label)))
+                };
+                let mut location = |segment: Segment, i: u16| -> Result<Location, String> {
+                    Ok(match segment {
+                        Segment::Constant => Location::Constant(i),
+                        Segment::Local => Location::Indirect(LCL, i),
+                        Segment::Argument => Location::Indirect(ARG, i),
+                        Segment::This => Location::Indirect(THIS, i),
+                        Segment::That => Location::Indirect(THAT, i),
+                        Segment::Pointer if i <= 1 => Location::Direct(THIS as u16 + i),
+                        Segment::Pointer => return Err(error(format!("no pointer {}", i))),
+                        Segment::Temp if i <= 7 => Location::Direct(TEMP + i),
+                        Segment::Temp => return Err(error(format!("no temp {}", i))),
+                        Segment::Static => {
+                            let next = STATIC + statics.len() as u16;
+                            Location::Direct(*statics.entry((prefix, i)).or_insert(next))
+                        }
+                    })
+                };
+                let instruction = match *command {
+                    Command::Add => Instruction::Add,
+                    Command::Sub => Instruction::Sub,
+                    Command::Neg => Instruction::Neg,
+                    Command::Eq => Instruction::Eq,
+                    Command::Gt => Instruction::Gt,
+                    Command::Lt => Instruction::Lt,
+                    Command::And => Instruction::And,
+                    Command::Or => Instruction::Or,
+                    Command::Not => Instruction::Not,
+                    Command::Return => Instruction::Return,
+                    Command::Label(_) => continue,
+                    Command::Goto(name) => Instruction::Goto(label(name)?),
+                    Command::IfGoto(name) => Instruction::IfGoto(label(name)?),
+                    Command::Call(name, args) => match functions.get(name) {
+                        Some(&function) => Instruction::Call { function, args },
+                        None => {
+                            let class = name.split('.').next().unwrap_or_default();
+                            let native = os::function(name).filter(|_| !classes.contains(class));
+                            match native {
+                                Some((function, n)) if n == args => {
+                                    let needs = os::memory_function(function)
+                                        .filter(|_| classes.contains("Memory"))
+                                        .filter(|needs| !functions.contains_key(*needs));
+                                    if let Some(needs) = needs {
+                                        return Err(error(format!(
+                                            "`{}` needs `{}`, as the program defines Memory",
+                                            name, needs
+                                        )));
+                                    }
+                                    Instruction::Native { function, args }
+                                }
+                                Some((_, n)) => {
+                                    return Err(error(format!(
+                                        "`{}` takes {} arguments, not {}",
+                                        name, n, args
+                                    )))
+                                }
+                                None => return Err(error(format!("unknown function `{}`", name))),
+                            }
+                        }
+                    },
+                    Command::Function(name, locals) => {
+                        function = name;
+                        Instruction::Function { locals }
+                    }
+                    Command::Push(segment, i) => Instruction::Push(location(segment, i)?),
+                    Command::Pop(Segment::Constant, _) => {
+                        return Err(error("can't pop to the constant segment".to_string()))
+                    }
+                    Command::Pop(segment, i) => Instruction::Pop(location(segment, i)?),
+                };
+                instructions.push(instruction);
+            }
+        }
+        let os = Os::new(classes.contains("Memory"));
+        Ok(Self {
+            ram: vec![0; MEMORY_SIZE],
+            pc: 0,
+            steps: 0,
+            os,
+            instructions,
+            functions,
+            pending: vec![],
+            boot: vec![],
+        })
+    }
+
+    /// Loads a `.vm` file, or every `.vm` file of a directory in the order of
+    /// their names, each with its file name as static prefix.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let paths = if path.is_dir() {
+            let entries = fs::read_dir(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            let mut paths = vec![];
+            for entry in entries {
+                let path = entry.map_err(|e| e.to_string())?.path();
+                if path.extension().is_some_and(|ext| ext == "vm") {
+                    paths.push(path);
+                }
+            }
+            if paths.is_empty() {
+                return Err(format!("{}: no .vm files", path.display()));
+            }
+            paths.sort();
+            paths
+        } else {
+            vec![path.to_path_buf()]
+        };
+        let mut sources = vec![];
+        for path in &paths {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            let source =
+                fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            sources.push((stem, source));
+        }
+        let mut commands = vec![];
+        for (stem, source) in &sources {
+            let (remaining_input, parsed) = parse(source).map_err(|e| e.to_string())?;
+            if !remaining_input.is_empty() {
+                return Err(format!("{}:{}: failed to parse", stem, parsed.len()));
+            }
+            commands.push(parsed);
+        }
+        let files: Vec<(&str, &[_])> = sources
+            .iter()
+            .zip(&commands)
+            .map(|((stem, _), commands)| (&stem[..], &commands[..]))
+            .collect();
+        Self::new(&files)
+    }
+
+    /// The index of the first command of a function.
+    pub fn entry(&self, function: &str) -> Option<usize> {
+        self.functions.get(function).copied()
+    }
+
+    /// Does what the boot code of `boot` does: sets SP to 256 and calls
+    /// `Sys.init`, which returns to the end of the program. Without one, the
+    /// OS's `Sys.init` calls `Main.main` the same way, after the `init` of
+    /// the OS classes the program defines, in the official OS's order.
+    pub fn boot(&mut self) -> Result<(), String> {
+        let end = self.instructions.len();
+        if let Some(function) = self.entry("Sys.init") {
+            self.ram[SP] = 256;
+            self.call(function, 0, end);
+            return Ok(());
+        }
+        let main = self
+            .entry("Main.main")
+            .ok_or("no Sys.init or Main.main function")?;
+        let inits = ["Memory", "Math", "Screen", "Output", "Keyboard"]
+            .iter()
+            .filter_map(|class| self.entry(&format!("{}.init", class)));
+        self.boot = std::iter::once(main).chain(inits.rev()).collect();
+        self.ram[SP] = 256;
+        let first = self.boot.pop().unwrap();
+        self.call(first, 0, end);
+        Ok(())
+    }
+
+    /// Whether the program ran to its end, or is stuck in a `goto` to the
+    /// label just before it.
+    pub fn is_halted(&self) -> bool {
+        match self.instructions.get(self.pc) {
+            None => self.boot.is_empty(),
+            Some(&Instruction::Goto(target)) => target == self.pc,
+            Some(_) => false,
+        }
+    }
+
+    /// Runs the command at PC, if the program hasn't ended.
+    pub fn step(&mut self) {
+        let Some(&instruction) = self.instructions.get(self.pc) else {
+            // The end of the program, which `boot` calls its functions from.
+            if let Some(function) = self.boot.pop() {
+                self.pop();
+                self.call(function, 0, self.pc);
+            }
+            return;
+        };
+        self.steps += 1;
+        self.pc += 1;
+        match instruction {
+            Instruction::Add => self.binary(|x, y| x.wrapping_add(y)),
+            Instruction::Sub => self.binary(|x, y| x.wrapping_sub(y)),
+            Instruction::And => self.binary(|x, y| x & y),
+            Instruction::Or => self.binary(|x, y| x | y),
+            Instruction::Eq => self.binary(|x, y| truth(x == y)),
+            Instruction::Gt => self.binary(|x, y| truth(x as i16 > y as i16)),
+            Instruction::Lt => self.binary(|x, y| truth((x as i16) < y as i16)),
+            Instruction::Neg => {
+                let x = self.pop();
+                self.push(x.wrapping_neg());
+            }
+            Instruction::Not => {
+                let x = self.pop();
+                self.push(!x);
+            }
+            Instruction::Goto(target) => self.pc = target,
+            Instruction::IfGoto(target) => {
+                if self.pop() != 0 {
+                    self.pc = target;
+                }
+            }
+            Instruction::Call { function, args } => self.call(function, args, self.pc),
+            Instruction::Native { function, args } => {
+                let at = self.pc - 1;
+                let returned = match self.pending.last() {
+                    Some(&pending) if pending == (at, self.ram[SP]) => {
+                        self.pending.pop();
+                        Some(self.pop())
+                    }
+                    _ => None,
+                };
+                let sp = self.ram[SP].wrapping_sub(args);
+                let args: Vec<u16> = (0..args)
+                    .map(|i| self.ram[sp.wrapping_add(i) as usize % MEMORY_SIZE])
+                    .collect();
+                match self.os.call(&mut self.ram, function, &args, returned) {
+                    Outcome::Return(value) => {
+                        self.ram[SP] = sp;
+                        self.push(value);
+                    }
+                    Outcome::Wait => self.pc = at,
+                    // `Vm::new` made sure the program has the function.
+                    Outcome::Call(name, arg) => {
+                        self.pending.push((at, self.ram[SP].wrapping_add(1)));
+                        self.push(arg);
+                        self.call(self.functions[name], 1, at);
+                    }
+                    Outcome::Halt => {
+                        self.pc = self.instructions.len();
+                        self.pending.clear();
+                        self.boot.clear();
+                    }
+                }
+            }
+            Instruction::Function { locals } => {
+                for _ in 0..locals {
+                    self.push(0);
+                }
+            }
+            Instruction::Return => {
+                let frame = self.ram[LCL];
+                let at = |n: u16| frame.wrapping_sub(n) as usize % MEMORY_SIZE;
+                let address = self.ram[at(5)];
+                let value = self.pop();
+                let arg = self.ram[ARG];
+                self.ram[arg as usize % MEMORY_SIZE] = value;
+                self.ram[SP] = arg.wrapping_add(1);
+                self.ram[THAT] = self.ram[at(1)];
+                self.ram[THIS] = self.ram[at(2)];
+                self.ram[ARG] = self.ram[at(3)];
+                self.ram[LCL] = self.ram[at(4)];
+                self.pc = address as usize;
+            }
+            Instruction::Push(location) => {
+                let value = match location {
+                    Location::Constant(value) => value,
+                    _ => self.ram[self.address(location)],
+                };
+                self.push(value);
+            }
+            Instruction::Pop(location) => {
+                let address = self.address(location);
+                self.ram[address] = self.pop();
+            }
+        }
+    }
+
+    fn address(&self, location: Location) -> usize {
+        let address = match location {
+            Location::Constant(_) => unreachable!("constants aren't in RAM"),
+            Location::Indirect(register, i) => self.ram[register].wrapping_add(i),
+            Location::Direct(address) => address,
+        };
+        address as usize % MEMORY_SIZE
+    }
+
+    /// Pushes the return address and the caller's segments, and jumps to
+    /// `function` with ARG at its arguments and LCL past the frame.
+    fn call(&mut self, function: usize, args: u16, return_to: usize) {
+        self.push(return_to as u16);
+        for register in [LCL, ARG, THIS, THAT] {
+            self.push(self.ram[register]);
+        }
+        self.ram[ARG] = self.ram[SP].wrapping_sub(5).wrapping_sub(args);
+        self.ram[LCL] = self.ram[SP];
+        self.pc = function;
+    }
+
+    fn push(&mut self, value: u16) {
+        let sp = self.ram[SP];
+        self.ram[sp as usize % MEMORY_SIZE] = value;
+        self.ram[SP] = sp.wrapping_add(1);
+    }
+
+    fn pop(&mut self) -> u16 {
+        let sp = self.ram[SP].wrapping_sub(1);
+        self.ram[SP] = sp;
+        self.ram[sp as usize % MEMORY_SIZE]
+    }
+
+    fn binary(&mut self, op: impl FnOnce(u16, u16) -> u16) {
+        let y = self.pop();
+        let x = self.pop();
+        self.push(op(x, y));
+    }
+}
+
+fn truth(condition: bool) -> u16 {
+    if condition {
+        0xFFFF
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Loads a file or a directory of the projects.
+    fn load(project: &str) -> Vm {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("..")
+            .join(project);
+        Vm::load(&path).unwrap()
+    }
+
+    fn run(vm: &mut Vm) {
+        while !vm.is_halted() {
+            vm.step();
+        }
+    }
+
+    fn signed(vm: &Vm, address: usize) -> i16 {
+        vm.ram[address] as i16
+    }
+
+    #[test]
+    fn test_basic() {
+        let mut vm = load("07/MemoryAccess/BasicTest/BasicTest.vm");
+        for (register, value) in [
+            (SP, 256),
+            (LCL, 300),
+            (ARG, 400),
+            (THIS, 3000),
+            (THAT, 3010),
+        ] {
+            vm.ram[register] = value;
+        }
+        run(&mut vm);
+        assert_eq!(vm.steps, 25);
+        // From BasicTest.cmp.
+        let expected = [(256, 472), (300, 10), (401, 21), (402, 22), (3006, 36)];
+        let expected = expected
+            .into_iter()
+            .chain([(3012, 42), (3015, 45), (11, 510)]);
+        for (address, value) in expected {
+            assert_eq!(signed(&vm, address), value, "RAM[{}]", address);
+        }
+    }
+
+    #[test]
+    fn test_stack() {
+        let mut vm = load("07/StackArithmetic/StackTest/StackTest.vm");
+        vm.ram[SP] = 256;
+        run(&mut vm);
+        let stack: Vec<i16> = (256..266).map(|address| signed(&vm, address)).collect();
+        assert_eq!(stack, [-1, 0, 0, 0, -1, 0, -1, 0, 0, -91]);
+        assert_eq!(vm.ram[SP], 266);
+    }
+
+    #[test]
+    fn test_fibonacci() {
+        let mut vm = load("08/FunctionCalls/FibonacciElement");
+        vm.boot().unwrap();
+        assert_eq!((vm.ram[SP], vm.ram[LCL]), (261, 261));
+        run(&mut vm);
+        assert_eq!((vm.ram[SP], vm.ram[261]), (262, 3));
+        // Stuck in Sys.init's `goto WHILE`.
+        assert!(vm.pc < vm.instructions.len());
+    }
+
+    #[test]
+    fn test_statics() {
+        let mut vm = load("08/FunctionCalls/StaticsTest");
+        vm.boot().unwrap();
+        run(&mut vm);
+        assert_eq!((signed(&vm, 261), signed(&vm, 262)), (-2, 8));
+        // Allocated like the assembler would, in order of first use.
+        assert_eq!(&vm.ram[16..20], [6, 8, 23, 15]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |source: &str| {
+            let commands = parse(source).unwrap().1;
+            Vm::new(&[("Foo", &commands)]).err().unwrap()
+        };
+        assert_eq!(
+            error("push constant 1\ngoto END"),
+            "Foo:2: unknown label `END`"
+        );
+        assert_eq!(error("call Foo.bar 0"), "Foo:1: unknown function `Foo.bar`");
+        assert_eq!(
+            error("pop constant 1"),
+            "Foo:1: can't pop to the constant segment"
+        );
+        assert_eq!(error("push temp 8"), "Foo:1: no temp 8");
+        // Labels belong to their function.
+        let source = "function Foo.a 0\nlabel L\nfunction Foo.b 0\ngoto L";
+        assert_eq!(error(source), "Foo:4: unknown label `L`");
+    }
+}
@@ -1,2 +1,5 @@
 pub mod code;
+pub mod interpreter;
+pub mod os;
 pub mod parser;
+pub mod script;
@@ -1,20 +1,27 @@
+use cli::{read_input, take_output_arg, write_output};
 use std::env::args;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::{fs, process};
-use vm_translator::code::{boot, translate};
-use vm_translator::parser::parse;
+use vm_translator::code::{boot, translate, translate_by_class};
+use vm_translator::parser::{parse, Command};
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync + 'static>>;
 
+const USAGE: &str = "Usage: vm-translator [-o output] [file, directory, or - for stdin]";
+
 fn main() -> Result<()> {
-    if args().count() > 2 {
-        eprintln!("Usage: vm-translator [file or directory]");
+    let mut args: Vec<String> = args().skip(1).collect();
+    let out_path = take_output_arg(&mut args, USAGE);
+    if args.len() > 1 {
+        eprintln!("{}", USAGE);
         process::exit(65);
-    } else if let Some(arg) = args().nth(1) {
-        if fs::metadata(&arg)?.is_dir() {
-            translate_dir(&arg)?;
+    } else if let Some(arg) = args.first() {
+        if arg == "-" {
+            translate_stdin(out_path)?;
+        } else if fs::metadata(arg)?.is_dir() {
+            translate_dir(arg, out_path)?;
         } else {
-            translate_file(&arg)?;
+            translate_file(arg, out_path)?;
         }
     } else {
         todo!("run on cwd if called without args");
@@ -22,7 +29,7 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn translate_dir<P: AsRef<Path>>(path: P) -> Result<()> {
+fn translate_dir<P: AsRef<Path>>(path: P, out_path: Option<PathBuf>) -> Result<()> {
     let path_ref = path.as_ref();
     let dir_name = file_stem(path_ref)?;
     let mut contents = boot();
@@ -37,32 +44,43 @@ fn translate_dir<P: AsRef<Path>>(path: P) -> Result<()> {
             _ => {}
         }
     }
-    let out_path = path_ref.join(dir_name).with_extension("asm");
-    fs::write(&out_path, contents)?;
-    eprintln!("Successfully wrote {}", out_path.to_string_lossy());
+    let out_path = out_path.unwrap_or_else(|| path_ref.join(dir_name).with_extension("asm"));
+    write_output(&out_path, &contents)?;
     Ok(())
 }
 
-fn translate_file<P: AsRef<Path>>(path: P) -> Result<()> {
+fn translate_file<P: AsRef<Path>>(path: P, out_path: Option<PathBuf>) -> Result<()> {
     let path_ref = path.as_ref();
-    let out_path = path_ref.with_extension("asm");
+    let out_path = out_path.unwrap_or_else(|| path_ref.with_extension("asm"));
     let stem = file_stem(path_ref)?;
     let source = fs::read_to_string(path_ref)?;
     // boot must be excluded, manually for now, in order to translate earlier
     // VM scripts that didn't rely on calling Sys.init by convention.
     let contents = boot() + &translate_str(&source, stem)?;
-    fs::write(&out_path, contents)?;
-    eprintln!("Successfully wrote {}", out_path.to_string_lossy());
+    write_output(&out_path, &contents)?;
+    Ok(())
+}
+
+fn translate_stdin(out_path: Option<PathBuf>) -> Result<()> {
+    let source = read_input(Path::new("-"))?;
+    let commands = parse_str(&source);
+    let contents = boot() + &translate_by_class(&commands, "Stdin");
+    write_output(&out_path.unwrap_or_else(|| PathBuf::from("-")), &contents)?;
     Ok(())
 }
 
 fn translate_str(input: &str, static_prefix: &str) -> Result<String> {
+    let commands = parse_str(input);
+    Ok(translate(&commands, static_prefix))
+}
+
+fn parse_str(input: &str) -> Vec<Option<Command<'_>>> {
     let (remaining_input, commands) = parse(input).unwrap_or_else(exit_with_error);
     if !remaining_input.is_empty() {
         eprintln!("[line {}] failed to parse entire input", commands.len());
         process::exit(65);
     }
-    Ok(translate(&commands, static_prefix))
+    commands
 }
 
 fn file_stem(path: &Path) -> Result<&str> {
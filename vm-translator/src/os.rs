@@ -0,0 +1,1115 @@
+use emulator::cpu::{KBD, MEMORY_SIZE, SCREEN};
+
+/// The heap, between the stack and the screen, as in the official OS.
+const HEAP: usize = 2048;
+const HEAP_END: usize = SCREEN;
+
+const WIDTH: i16 = 512;
+const HEIGHT: i16 = 256;
+/// Text is 23 rows of 64 characters of 8x11 pixels.
+const ROWS: u16 = 23;
+const COLUMNS: u16 = 64;
+
+const NEW_LINE: u16 = 128;
+const BACKSPACE: u16 = 129;
+const DOUBLE_QUOTE: u16 = 34;
+
+/// A function of the OS, called like any other, with the same stack effect.
+/// `Sys.init` isn't one, as it calls `Main.main`: `Vm::boot` does that.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Function {
+    ArrayNew,
+    ArrayDispose,
+    KeyboardInit,
+    KeyboardKeyPressed,
+    KeyboardReadChar,
+    KeyboardReadLine,
+    KeyboardReadInt,
+    MathInit,
+    MathAbs,
+    MathMultiply,
+    MathDivide,
+    MathMin,
+    MathMax,
+    MathSqrt,
+    MemoryInit,
+    MemoryPeek,
+    MemoryPoke,
+    MemoryAlloc,
+    MemoryDeAlloc,
+    OutputInit,
+    OutputMoveCursor,
+    OutputPrintChar,
+    OutputPrintString,
+    OutputPrintInt,
+    OutputPrintln,
+    OutputBackSpace,
+    ScreenInit,
+    ScreenClearScreen,
+    ScreenSetColor,
+    ScreenDrawPixel,
+    ScreenDrawLine,
+    ScreenDrawRectangle,
+    ScreenDrawCircle,
+    StringNew,
+    StringDispose,
+    StringLength,
+    StringCharAt,
+    StringSetCharAt,
+    StringAppendChar,
+    StringEraseLastChar,
+    StringIntValue,
+    StringSetInt,
+    StringBackSpace,
+    StringDoubleQuote,
+    StringNewLine,
+    SysHalt,
+    SysError,
+    SysWait,
+}
+
+/// Each function's name and number of arguments, `this` included.
+const FUNCTIONS: [(&str, Function, u16); 48] = [
+    ("Array.new", Function::ArrayNew, 1),
+    ("Array.dispose", Function::ArrayDispose, 1),
+    ("Keyboard.init", Function::KeyboardInit, 0),
+    ("Keyboard.keyPressed", Function::KeyboardKeyPressed, 0),
+    ("Keyboard.readChar", Function::KeyboardReadChar, 0),
+    ("Keyboard.readLine", Function::KeyboardReadLine, 1),
+    ("Keyboard.readInt", Function::KeyboardReadInt, 1),
+    ("Math.init", Function::MathInit, 0),
+    ("Math.abs", Function::MathAbs, 1),
+    ("Math.multiply", Function::MathMultiply, 2),
+    ("Math.divide", Function::MathDivide, 2),
+    ("Math.min", Function::MathMin, 2),
+    ("Math.max", Function::MathMax, 2),
+    ("Math.sqrt", Function::MathSqrt, 1),
+    ("Memory.init", Function::MemoryInit, 0),
+    ("Memory.peek", Function::MemoryPeek, 1),
+    ("Memory.poke", Function::MemoryPoke, 2),
+    ("Memory.alloc", Function::MemoryAlloc, 1),
+    ("Memory.deAlloc", Function::MemoryDeAlloc, 1),
+    ("Output.init", Function::OutputInit, 0),
+    ("Output.moveCursor", Function::OutputMoveCursor, 2),
+    ("Output.printChar", Function::OutputPrintChar, 1),
+    ("Output.printString", Function::OutputPrintString, 1),
+    ("Output.printInt", Function::OutputPrintInt, 1),
+    ("Output.println", Function::OutputPrintln, 0),
+    ("Output.backSpace", Function::OutputBackSpace, 0),
+    ("Screen.init", Function::ScreenInit, 0),
+    ("Screen.clearScreen", Function::ScreenClearScreen, 0),
+    ("Screen.setColor", Function::ScreenSetColor, 1),
+    ("Screen.drawPixel", Function::ScreenDrawPixel, 2),
+    ("Screen.drawLine", Function::ScreenDrawLine, 4),
+    ("Screen.drawRectangle", Function::ScreenDrawRectangle, 4),
+    ("Screen.drawCircle", Function::ScreenDrawCircle, 3),
+    ("String.new", Function::StringNew, 1),
+    ("String.dispose", Function::StringDispose, 1),
+    ("String.length", Function::StringLength, 1),
+    ("String.charAt", Function::StringCharAt, 2),
+    ("String.setCharAt", Function::StringSetCharAt, 3),
+    ("String.appendChar", Function::StringAppendChar, 2),
+    ("String.eraseLastChar", Function::StringEraseLastChar, 1),
+    ("String.intValue", Function::StringIntValue, 1),
+    ("String.setInt", Function::StringSetInt, 2),
+    ("String.backSpace", Function::StringBackSpace, 0),
+    ("String.doubleQuote", Function::StringDoubleQuote, 0),
+    ("String.newLine", Function::StringNewLine, 0),
+    ("Sys.halt", Function::SysHalt, 0),
+    ("Sys.error", Function::SysError, 1),
+    ("Sys.wait", Function::SysWait, 1),
+];
+
+/// The OS function with the given name, and its number of arguments.
+pub fn function(name: &str) -> Option<(Function, u16)> {
+    FUNCTIONS
+        .iter()
+        .find(|&&(function, _, _)| function == name)
+        .map(|&(_, function, args)| (function, args))
+}
+
+/// The function of the `Memory` class an OS function calls, like in the
+/// official OS, for objects to be allocated with the program's `Memory`.
+pub fn memory_function(function: Function) -> Option<&'static str> {
+    match function {
+        Function::ArrayNew | Function::StringNew | Function::KeyboardReadLine => {
+            Some("Memory.alloc")
+        }
+        Function::ArrayDispose | Function::StringDispose => Some("Memory.deAlloc"),
+        _ => None,
+    }
+}
+
+/// What became of a call.
+pub enum Outcome {
+    Return(u16),
+    /// Waiting for a key, so the call has to be made again.
+    Wait,
+    /// A call to a function of the program with one argument, after which
+    /// the call has to be made again with the value it returned.
+    Call(&'static str, u16),
+    Halt,
+}
+
+/// The error codes of the official OS, which `Sys.error` prints as `ERR<n>`.
+mod error {
+    pub const WAIT_DURATION: i16 = 1;
+    pub const ARRAY_SIZE: i16 = 2;
+    pub const DIVIDE_BY_ZERO: i16 = 3;
+    pub const NEGATIVE_SQRT: i16 = 4;
+    pub const ALLOC_SIZE: i16 = 5;
+    pub const HEAP_OVERFLOW: i16 = 6;
+    pub const PIXEL: i16 = 7;
+    pub const LINE: i16 = 8;
+    pub const RECTANGLE: i16 = 9;
+    pub const CIRCLE_CENTER: i16 = 12;
+    pub const CIRCLE_RADIUS: i16 = 13;
+    pub const STRING_LENGTH: i16 = 14;
+    pub const CHAR_AT: i16 = 15;
+    pub const SET_CHAR_AT: i16 = 16;
+    pub const STRING_FULL: i16 = 17;
+    pub const STRING_EMPTY: i16 = 18;
+    pub const SET_INT: i16 = 19;
+    pub const CURSOR: i16 = 20;
+}
+
+/// The state the OS keeps outside RAM: the free list of the heap, the
+/// screen color, the text cursor, and the keyboard input under way. Objects
+/// live in the heap, the screen in the screen memory map, and keys come
+/// from the keyboard register, like with the official OS. Strings are
+/// `[capacity, length, characters...]`.
+#[derive(Default)]
+pub struct Os {
+    /// The first free segment of the heap, each being its size and the next
+    /// one, or 0 for none. `None` until the heap is set up.
+    free: Option<usize>,
+    white: bool,
+    row: u16,
+    column: u16,
+    /// A key pressed, waiting to be released.
+    key: Option<u16>,
+    /// The characters of `readLine` or `readInt` so far, once the prompt
+    /// was printed.
+    line: Option<Vec<u16>>,
+    /// A line `readLine` read, while the program's `Memory.alloc` makes
+    /// room for it.
+    typed: Option<Vec<u16>>,
+    /// Whether the program has its own `Memory` class, which then allocates
+    /// the objects of the OS too.
+    program_memory: bool,
+    /// The code of the `Sys.error` that halted the program.
+    pub error: Option<i16>,
+}
+
+impl Os {
+    /// The OS of a program, which may have its own `Memory` class.
+    pub fn new(program_memory: bool) -> Self {
+        Self {
+            program_memory,
+            ..Self::default()
+        }
+    }
+
+    /// Runs `function` with `args`, which are still on the stack, and with
+    /// the value of the call it asked for, once made.
+    pub fn call(
+        &mut self,
+        ram: &mut [u16],
+        function: Function,
+        args: &[u16],
+        returned: Option<u16>,
+    ) -> Outcome {
+        let arg = |i: usize| args[i] as i16;
+        let result = match function {
+            Function::MathInit | Function::KeyboardInit => Ok(0),
+            Function::MathAbs => Ok(arg(0).wrapping_abs()),
+            Function::MathMultiply => Ok(arg(0).wrapping_mul(arg(1))),
+            Function::MathDivide if arg(1) == 0 => Err(error::DIVIDE_BY_ZERO),
+            Function::MathDivide => Ok(arg(0).wrapping_div(arg(1))),
+            Function::MathMin => Ok(arg(0).min(arg(1))),
+            Function::MathMax => Ok(arg(0).max(arg(1))),
+            Function::MathSqrt if arg(0) < 0 => Err(error::NEGATIVE_SQRT),
+            Function::MathSqrt => Ok(sqrt(arg(0) as u32) as i16),
+
+            Function::MemoryInit => {
+                self.free = None;
+                self.heap(ram);
+                Ok(0)
+            }
+            Function::MemoryPeek => Ok(ram[args[0] as usize % MEMORY_SIZE] as i16),
+            Function::MemoryPoke => {
+                ram[args[0] as usize % MEMORY_SIZE] = args[1];
+                Ok(0)
+            }
+            Function::MemoryAlloc if arg(0) <= 0 => Err(error::ALLOC_SIZE),
+            Function::ArrayNew if arg(0) <= 0 => Err(error::ARRAY_SIZE),
+            Function::MemoryAlloc => self.alloc(ram, args[0] as usize),
+            Function::ArrayNew => match self.allocate(ram, args[0] as usize, returned) {
+                Some(result) => result,
+                None => return Outcome::Call("Memory.alloc", args[0]),
+            },
+            Function::ArrayDispose | Function::StringDispose if self.program_memory => {
+                match returned {
+                    Some(_) => Ok(0),
+                    None => return Outcome::Call("Memory.deAlloc", args[0]),
+                }
+            }
+            Function::MemoryDeAlloc | Function::ArrayDispose | Function::StringDispose => {
+                self.de_alloc(ram, args[0] as usize);
+                Ok(0)
+            }
+
+            Function::OutputInit => {
+                (self.row, self.column) = (0, 0);
+                Ok(0)
+            }
+            Function::OutputMoveCursor if args[0] >= ROWS || args[1] >= COLUMNS => {
+                Err(error::CURSOR)
+            }
+            Function::OutputMoveCursor => {
+                (self.row, self.column) = (args[0], args[1]);
+                Ok(0)
+            }
+            Function::OutputPrintChar => {
+                self.print_char(ram, args[0]);
+                Ok(0)
+            }
+            Function::OutputPrintString => {
+                self.print_string(ram, args[0]);
+                Ok(0)
+            }
+            Function::OutputPrintInt => {
+                self.print(ram, &arg(0).to_string());
+                Ok(0)
+            }
+            Function::OutputPrintln => {
+                self.println();
+                Ok(0)
+            }
+            Function::OutputBackSpace => {
+                self.back_space(ram);
+                Ok(0)
+            }
+
+            Function::ScreenInit => {
+                self.white = false;
+                Ok(0)
+            }
+            Function::ScreenClearScreen => {
+                ram[SCREEN..KBD].fill(0);
+                Ok(0)
+            }
+            Function::ScreenSetColor => {
+                self.white = args[0] == 0;
+                Ok(0)
+            }
+            Function::ScreenDrawPixel if !on_screen(arg(0), arg(1)) => Err(error::PIXEL),
+            Function::ScreenDrawPixel => {
+                self.draw_pixel(ram, arg(0), arg(1));
+                Ok(0)
+            }
+            Function::ScreenDrawLine => {
+                let (x1, y1, x2, y2) = (arg(0), arg(1), arg(2), arg(3));
+                if !on_screen(x1, y1) || !on_screen(x2, y2) {
+                    Err(error::LINE)
+                } else {
+                    self.draw_line(ram, x1, y1, x2, y2);
+                    Ok(0)
+                }
+            }
+            Function::ScreenDrawRectangle => {
+                let (x1, y1, x2, y2) = (arg(0), arg(1), arg(2), arg(3));
+                if !on_screen(x1, y1) || !on_screen(x2, y2) || x1 > x2 || y1 > y2 {
+                    Err(error::RECTANGLE)
+                } else {
+                    for y in y1..=y2 {
+                        self.draw_span(ram, x1, x2, y);
+                    }
+                    Ok(0)
+                }
+            }
+            Function::ScreenDrawCircle if !on_screen(arg(0), arg(1)) => Err(error::CIRCLE_CENTER),
+            Function::ScreenDrawCircle if !(0..=181).contains(&arg(2)) => Err(error::CIRCLE_RADIUS),
+            Function::ScreenDrawCircle => {
+                let (x, y, r) = (arg(0) as i32, arg(1) as i32, arg(2) as i32);
+                for dy in -r..=r {
+                    let dx = sqrt((r * r - dy * dy) as u32) as i32;
+                    let row = y + dy;
+                    if (0..HEIGHT as i32).contains(&row) {
+                        let left = (x - dx).max(0) as i16;
+                        let right = (x + dx).min(WIDTH as i32 - 1) as i16;
+                        self.draw_span(ram, left, right, row as i16);
+                    }
+                }
+                Ok(0)
+            }
+
+            Function::StringNew if arg(0) < 0 => Err(error::STRING_LENGTH),
+            Function::StringNew => match self.allocate(ram, args[0] as usize + 2, returned) {
+                Some(result) => result.inspect(|&string| {
+                    let string = JackString::at(string as u16);
+                    ram[string.0] = args[0];
+                    ram[string.0 + 1] = 0;
+                }),
+                None => return Outcome::Call("Memory.alloc", args[0] + 2),
+            },
+            Function::StringLength => Ok(JackString::at(args[0]).length(ram) as i16),
+            Function::StringCharAt => {
+                let string = JackString::at(args[0]);
+                match string.index(ram, args[1]) {
+                    Some(at) => Ok(ram[at] as i16),
+                    None => Err(error::CHAR_AT),
+                }
+            }
+            Function::StringSetCharAt => {
+                let string = JackString::at(args[0]);
+                match string.index(ram, args[1]) {
+                    Some(at) => {
+                        ram[at] = args[2];
+                        Ok(0)
+                    }
+                    None => Err(error::SET_CHAR_AT),
+                }
+            }
+            Function::StringAppendChar => {
+                let string = JackString::at(args[0]);
+                if string.push(ram, args[1]) {
+                    Ok(arg(0))
+                } else {
+                    Err(error::STRING_FULL)
+                }
+            }
+            Function::StringEraseLastChar => {
+                let string = JackString::at(args[0]);
+                match string.length(ram) {
+                    0 => Err(error::STRING_EMPTY),
+                    length => {
+                        ram[string.0 + 1] = length as u16 - 1;
+                        Ok(0)
+                    }
+                }
+            }
+            Function::StringIntValue => {
+                let string = JackString::at(args[0]);
+                Ok(int_value(&string.chars(ram)))
+            }
+            Function::StringSetInt => {
+                let string = JackString::at(args[0]);
+                let digits = arg(1).to_string();
+                if digits.len() > ram[string.0] as usize {
+                    Err(error::SET_INT)
+                } else {
+                    ram[string.0 + 1] = 0;
+                    for c in digits.bytes() {
+                        string.push(ram, c as u16);
+                    }
+                    Ok(0)
+                }
+            }
+            Function::StringBackSpace => Ok(BACKSPACE as i16),
+            Function::StringDoubleQuote => Ok(DOUBLE_QUOTE as i16),
+            Function::StringNewLine => Ok(NEW_LINE as i16),
+
+            Function::KeyboardKeyPressed => Ok(ram[KBD] as i16),
+            Function::KeyboardReadChar => match self.read_char(ram) {
+                Some(c) => Ok(c as i16),
+                None => return Outcome::Wait,
+            },
+            Function::KeyboardReadLine => {
+                let Some(line) = self.typed.take().or_else(|| self.read_line(ram, args[0])) else {
+                    return Outcome::Wait;
+                };
+                let size = line.len() + 2;
+                match self.allocate(ram, size, returned) {
+                    Some(result) => result.map(|string| {
+                        let string = JackString::at(string as u16);
+                        ram[string.0] = line.len() as u16;
+                        ram[string.0 + 1] = 0;
+                        for c in line {
+                            string.push(ram, c);
+                        }
+                        string.0 as i16
+                    }),
+                    None => {
+                        self.typed = Some(line);
+                        return Outcome::Call("Memory.alloc", size as u16);
+                    }
+                }
+            }
+            Function::KeyboardReadInt => match self.read_line(ram, args[0]) {
+                Some(line) => Ok(int_value(&line)),
+                None => return Outcome::Wait,
+            },
+
+            Function::SysHalt => return Outcome::Halt,
+            Function::SysError => Err(arg(0)),
+            Function::SysWait if arg(0) < 0 => Err(error::WAIT_DURATION),
+            // Nothing to wait for without a clock.
+            Function::SysWait => Ok(0),
+        };
+        match result {
+            Ok(value) => Outcome::Return(value as u16),
+            Err(code) => {
+                self.print(ram, &format!("ERR{}", code));
+                self.error = Some(code);
+                Outcome::Halt
+            }
+        }
+    }
+
+    /// Sets up the heap as one free segment, if it isn't yet.
+    fn heap(&mut self, ram: &mut [u16]) -> usize {
+        *self.free.get_or_insert_with(|| {
+            ram[HEAP] = (HEAP_END - HEAP) as u16;
+            ram[HEAP + 1] = 0;
+            HEAP
+        })
+    }
+
+    /// Allocates `size` words, after a word holding the size of the block,
+    /// from the end of the first free segment big enough.
+    /// A list the program wrote over, reaching outside the heap or looping
+    /// back after a double free, runs out of memory.
+    fn alloc(&mut self, ram: &mut [u16], size: usize) -> Result<i16, i16> {
+        let need = size.max(1) + 1;
+        let (mut previous, mut segment) = (0, self.heap(ram));
+        // Segments are at least two words, so there can't be more.
+        let mut hops = (HEAP_END - HEAP) / 2;
+        while segment != 0 {
+            if !(HEAP..HEAP_END - 1).contains(&segment) || hops == 0 {
+                return Err(error::HEAP_OVERFLOW);
+            }
+            hops -= 1;
+            let length = ram[segment] as usize;
+            let next = ram[segment + 1] as usize;
+            if segment + length > HEAP_END {
+                return Err(error::HEAP_OVERFLOW);
+            }
+            let block = if length == need {
+                match previous {
+                    0 => self.free = Some(next),
+                    _ => ram[previous + 1] = next as u16,
+                }
+                segment
+            } else if length >= need + 2 {
+                ram[segment] = (length - need) as u16;
+                segment + length - need
+            } else {
+                (previous, segment) = (segment, next);
+                continue;
+            };
+            ram[block] = need as u16;
+            return Ok(block as i16 + 1);
+        }
+        Err(error::HEAP_OVERFLOW)
+    }
+
+    /// Allocates `size` words for an object of the OS, from the native heap,
+    /// or else with the value of the program's `Memory.alloc`, `None` until
+    /// it was called.
+    fn allocate(
+        &mut self,
+        ram: &mut [u16],
+        size: usize,
+        returned: Option<u16>,
+    ) -> Option<Result<i16, i16>> {
+        match (self.program_memory, returned) {
+            (false, _) => Some(self.alloc(ram, size)),
+            (true, Some(block)) => Some(Ok(block as i16)),
+            (true, None) => None,
+        }
+    }
+
+    /// Puts a block back at the front of the free list.
+    fn de_alloc(&mut self, ram: &mut [u16], object: usize) {
+        let block = object.wrapping_sub(1);
+        if !(HEAP..HEAP_END - 1).contains(&block) {
+            return;
+        }
+        ram[block + 1] = self.heap(ram) as u16;
+        self.free = Some(block);
+    }
+
+    fn print(&mut self, ram: &mut [u16], text: &str) {
+        for c in text.chars() {
+            self.print_char(ram, c as u16);
+        }
+    }
+
+    fn print_string(&mut self, ram: &mut [u16], string: u16) {
+        for c in JackString::at(string).chars(ram) {
+            self.print_char(ram, c);
+        }
+    }
+
+    fn print_char(&mut self, ram: &mut [u16], c: u16) {
+        match c {
+            NEW_LINE => self.println(),
+            BACKSPACE => self.back_space(ram),
+            _ => {
+                self.draw_char(ram, c);
+                self.column += 1;
+                if self.column == COLUMNS {
+                    self.println();
+                }
+            }
+        }
+    }
+
+    fn println(&mut self) {
+        self.column = 0;
+        self.row = (self.row + 1) % ROWS;
+    }
+
+    fn back_space(&mut self, ram: &mut [u16]) {
+        if self.column > 0 {
+            self.column -= 1;
+        } else if self.row > 0 {
+            (self.row, self.column) = (self.row - 1, COLUMNS - 1);
+        }
+        self.draw_char(ram, ' ' as u16);
+    }
+
+    /// Draws a character at the cursor: two characters share a word, the
+    /// one on the left in its low byte.
+    fn draw_char(&mut self, ram: &mut [u16], c: u16) {
+        let glyph = match c {
+            32..=126 => &FONT[c as usize - 32],
+            _ => &BLOCK,
+        };
+        let row = self.row as usize * 11;
+        let column = self.column as usize;
+        for (i, &bits) in glyph.iter().enumerate() {
+            let word = &mut ram[SCREEN + (row + i) * 32 + column / 2];
+            *word = if column.is_multiple_of(2) {
+                *word & 0xFF00 | bits as u16
+            } else {
+                *word & 0x00FF | (bits as u16) << 8
+            };
+        }
+    }
+
+    fn draw_pixel(&self, ram: &mut [u16], x: i16, y: i16) {
+        let word = &mut ram[SCREEN + y as usize * 32 + x as usize / 16];
+        let bit = 1 << (x % 16);
+        if self.white {
+            *word &= !bit;
+        } else {
+            *word |= bit;
+        }
+    }
+
+    fn draw_span(&self, ram: &mut [u16], x1: i16, x2: i16, y: i16) {
+        for x in x1..=x2 {
+            self.draw_pixel(ram, x, y);
+        }
+    }
+
+    fn draw_line(&self, ram: &mut [u16], x1: i16, y1: i16, x2: i16, y2: i16) {
+        let (dx, dy) = ((x2 - x1).abs(), -(y2 - y1).abs());
+        let (sx, sy) = ((x2 - x1).signum(), (y2 - y1).signum());
+        let (mut x, mut y, mut error) = (x1, y1, dx + dy);
+        loop {
+            self.draw_pixel(ram, x, y);
+            if (x, y) == (x2, y2) {
+                break;
+            }
+            if 2 * error >= dy {
+                error += dy;
+                x += sx;
+            }
+            if 2 * error <= dx {
+                error += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// Waits for a key to be pressed and released, then prints it.
+    fn read_char(&mut self, ram: &mut [u16]) -> Option<u16> {
+        let pressed = ram[KBD];
+        match self.key {
+            None => {
+                self.key = (pressed != 0).then_some(pressed);
+                None
+            }
+            Some(c) if pressed == 0 => {
+                self.key = None;
+                self.print_char(ram, c);
+                Some(c)
+            }
+            Some(_) => None,
+        }
+    }
+
+    /// Prints `message`, then reads characters up to a new line.
+    fn read_line(&mut self, ram: &mut [u16], message: u16) -> Option<Vec<u16>> {
+        if self.line.is_none() {
+            self.print_string(ram, message);
+            self.line = Some(vec![]);
+        }
+        let c = self.read_char(ram)?;
+        let line = self.line.as_mut().unwrap();
+        match c {
+            NEW_LINE => return self.line.take(),
+            BACKSPACE => {
+                line.pop();
+            }
+            _ => line.push(c),
+        }
+        None
+    }
+}
+
+fn on_screen(x: i16, y: i16) -> bool {
+    (0..WIDTH).contains(&x) && (0..HEIGHT).contains(&y)
+}
+
+fn sqrt(n: u32) -> u32 {
+    (n as f64).sqrt() as u32
+}
+
+/// The value of the digits at the start of a string, after an optional
+/// minus sign.
+fn int_value(chars: &[u16]) -> i16 {
+    let (negative, digits) = match chars {
+        [c, rest @ ..] if *c == '-' as u16 => (true, rest),
+        _ => (false, chars),
+    };
+    let value = digits
+        .iter()
+        .map_while(|&c| {
+            ('0' as u16..='9' as u16)
+                .contains(&c)
+                .then(|| c - '0' as u16)
+        })
+        .fold(0i16, |value, digit| {
+            value.wrapping_mul(10).wrapping_add(digit as i16)
+        });
+    if negative {
+        value.wrapping_neg()
+    } else {
+        value
+    }
+}
+
+/// A string object in RAM.
+#[derive(Clone, Copy)]
+struct JackString(usize);
+
+impl JackString {
+    fn at(address: u16) -> Self {
+        Self(address as usize % (MEMORY_SIZE - 2))
+    }
+
+    fn length(self, ram: &[u16]) -> usize {
+        ram[self.0 + 1] as usize
+    }
+
+    /// The address of character `i`, if the string has one.
+    fn index(self, ram: &[u16], i: u16) -> Option<usize> {
+        ((i as usize) < self.length(ram)).then_some(self.0 + 2 + i as usize)
+    }
+
+    fn chars(self, ram: &[u16]) -> Vec<u16> {
+        let start = self.0 + 2;
+        let end = (start + self.length(ram)).min(MEMORY_SIZE);
+        ram[start..end].to_vec()
+    }
+
+    /// Appends a character, if the string has room for it.
+    fn push(self, ram: &mut [u16], c: u16) -> bool {
+        let length = self.length(ram);
+        if length >= ram[self.0] as usize || self.0 + 2 + length >= MEMORY_SIZE {
+            return false;
+        }
+        ram[self.0 + 2 + length] = c;
+        ram[self.0 + 1] = length as u16 + 1;
+        true
+    }
+}
+
+/// The glyph of characters the font doesn't have.
+const BLOCK: [u8; 11] = [63, 63, 63, 63, 63, 63, 63, 63, 63, 0, 0];
+
+/// The font of the official OS, from space to `~`: the 11 rows of each
+/// character, the leftmost pixel in the lowest bit.
+#[rustfmt::skip]
+const FONT: [[u8; 11]; 95] = [
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],           // space
+    [12, 30, 30, 30, 12, 12, 0, 12, 12, 0, 0],   // !
+    [54, 54, 20, 0, 0, 0, 0, 0, 0, 0, 0],        // "
+    [0, 18, 18, 63, 18, 18, 63, 18, 18, 0, 0],   // #
+    [12, 30, 51, 3, 30, 48, 51, 30, 12, 12, 0],  // $
+    [0, 0, 35, 51, 24, 12, 6, 51, 49, 0, 0],     // %
+    [12, 30, 30, 12, 54, 27, 27, 27, 54, 0, 0],  // &
+    [12, 12, 6, 0, 0, 0, 0, 0, 0, 0, 0],         // '
+    [24, 12, 6, 6, 6, 6, 6, 12, 24, 0, 0],       // (
+    [6, 12, 24, 24, 24, 24, 24, 12, 6, 0, 0],    // )
+    [0, 0, 0, 51, 30, 63, 30, 51, 0, 0, 0],      // *
+    [0, 0, 0, 12, 12, 63, 12, 12, 0, 0, 0],      // +
+    [0, 0, 0, 0, 0, 0, 0, 12, 12, 6, 0],         // ,
+    [0, 0, 0, 0, 0, 63, 0, 0, 0, 0, 0],          // -
+    [0, 0, 0, 0, 0, 0, 0, 12, 12, 0, 0],         // .
+    [0, 0, 32, 48, 24, 12, 6, 3, 1, 0, 0],       // /
+    [12, 30, 51, 51, 51, 51, 51, 30, 12, 0, 0],  // 0
+    [12, 14, 15, 12, 12, 12, 12, 12, 63, 0, 0],  // 1
+    [30, 51, 48, 24, 12, 6, 3, 51, 63, 0, 0],    // 2
+    [30, 51, 48, 48, 28, 48, 48, 51, 30, 0, 0],  // 3
+    [16, 24, 28, 26, 25, 63, 24, 24, 60, 0, 0],  // 4
+    [63, 3, 3, 31, 48, 48, 48, 51, 30, 0, 0],    // 5
+    [28, 6, 3, 3, 31, 51, 51, 51, 30, 0, 0],     // 6
+    [63, 49, 48, 48, 24, 12, 12, 12, 12, 0, 0],  // 7
+    [30, 51, 51, 51, 30, 51, 51, 51, 30, 0, 0],  // 8
+    [30, 51, 51, 51, 62, 48, 48, 24, 14, 0, 0],  // 9
+    [0, 0, 12, 12, 0, 0, 12, 12, 0, 0, 0],       // :
+    [0, 0, 12, 12, 0, 0, 12, 12, 6, 0, 0],       // ;
+    [0, 0, 24, 12, 6, 3, 6, 12, 24, 0, 0],       // <
+    [0, 0, 0, 63, 0, 0, 63, 0, 0, 0, 0],         // =
+    [0, 0, 3, 6, 12, 24, 12, 6, 3, 0, 0],        // >
+    [30, 51, 51, 24, 12, 12, 0, 12, 12, 0, 0],   // ?
+    [30, 51, 51, 59, 59, 59, 27, 3, 30, 0, 0],   // @
+    [12, 30, 51, 51, 63, 51, 51, 51, 51, 0, 0],  // A
+    [31, 51, 51, 51, 31, 51, 51, 51, 31, 0, 0],  // B
+    [28, 54, 35, 3, 3, 3, 35, 54, 28, 0, 0],     // C
+    [15, 27, 51, 51, 51, 51, 51, 27, 15, 0, 0],  // D
+    [63, 51, 35, 11, 15, 11, 35, 51, 63, 0, 0],  // E
+    [63, 51, 35, 11, 15, 11, 3, 3, 3, 0, 0],     // F
+    [28, 54, 35, 3, 59, 51, 51, 54, 44, 0, 0],   // G
+    [51, 51, 51, 51, 63, 51, 51, 51, 51, 0, 0],  // H
+    [30, 12, 12, 12, 12, 12, 12, 12, 30, 0, 0],  // I
+    [60, 24, 24, 24, 24, 24, 27, 27, 14, 0, 0],  // J
+    [51, 51, 51, 27, 15, 27, 51, 51, 51, 0, 0],  // K
+    [3, 3, 3, 3, 3, 3, 35, 51, 63, 0, 0],        // L
+    [33, 51, 63, 63, 51, 51, 51, 51, 51, 0, 0],  // M
+    [51, 51, 55, 55, 63, 59, 59, 51, 51, 0, 0],  // N
+    [30, 51, 51, 51, 51, 51, 51, 51, 30, 0, 0],  // O
+    [31, 51, 51, 51, 31, 3, 3, 3, 3, 0, 0],      // P
+    [30, 51, 51, 51, 51, 51, 63, 59, 30, 48, 0], // Q
+    [31, 51, 51, 51, 31, 27, 51, 51, 51, 0, 0],  // R
+    [30, 51, 51, 6, 28, 48, 51, 51, 30, 0, 0],   // S
+    [63, 63, 45, 12, 12, 12, 12, 12, 30, 0, 0],  // T
+    [51, 51, 51, 51, 51, 51, 51, 51, 30, 0, 0],  // U
+    [51, 51, 51, 51, 51, 30, 30, 12, 12, 0, 0],  // V
+    [51, 51, 51, 51, 51, 63, 63, 63, 18, 0, 0],  // W
+    [51, 51, 30, 30, 12, 30, 30, 51, 51, 0, 0],  // X
+    [51, 51, 51, 51, 30, 12, 12, 12, 30, 0, 0],  // Y
+    [63, 51, 49, 24, 12, 6, 35, 51, 63, 0, 0],   // Z
+    [30, 6, 6, 6, 6, 6, 6, 6, 30, 0, 0],         // [
+    [0, 0, 1, 3, 6, 12, 24, 48, 32, 0, 0],       // \
+    [30, 24, 24, 24, 24, 24, 24, 24, 30, 0, 0],  // ]
+    [8, 28, 54, 0, 0, 0, 0, 0, 0, 0, 0],         // ^
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 63, 0],          // _
+    [6, 12, 24, 0, 0, 0, 0, 0, 0, 0, 0],         // `
+    [0, 0, 0, 14, 24, 30, 27, 27, 54, 0, 0],     // a
+    [3, 3, 3, 15, 27, 51, 51, 51, 30, 0, 0],     // b
+    [0, 0, 0, 30, 51, 3, 3, 51, 30, 0, 0],       // c
+    [48, 48, 48, 60, 54, 51, 51, 51, 30, 0, 0],  // d
+    [0, 0, 0, 30, 51, 63, 3, 51, 30, 0, 0],      // e
+    [28, 54, 38, 6, 15, 6, 6, 6, 15, 0, 0],      // f
+    [0, 0, 30, 51, 51, 51, 62, 48, 51, 30, 0],   // g
+    [3, 3, 3, 27, 55, 51, 51, 51, 51, 0, 0],     // h
+    [12, 12, 0, 14, 12, 12, 12, 12, 30, 0, 0],   // i
+    [48, 48, 0, 56, 48, 48, 48, 48, 51, 30, 0],  // j
+    [3, 3, 3, 51, 27, 15, 15, 27, 51, 0, 0],     // k
+    [14, 12, 12, 12, 12, 12, 12, 12, 30, 0, 0],  // l
+    [0, 0, 0, 29, 63, 43, 43, 43, 43, 0, 0],     // m
+    [0, 0, 0, 29, 51, 51, 51, 51, 51, 0, 0],     // n
+    [0, 0, 0, 30, 51, 51, 51, 51, 30, 0, 0],     // o
+    [0, 0, 0, 30, 51, 51, 51, 31, 3, 3, 0],      // p
+    [0, 0, 0, 30, 51, 51, 51, 62, 48, 48, 0],    // q
+    [0, 0, 0, 29, 55, 51, 3, 3, 7, 0, 0],        // r
+    [0, 0, 0, 30, 51, 6, 24, 51, 30, 0, 0],      // s
+    [4, 6, 6, 15, 6, 6, 6, 54, 28, 0, 0],        // t
+    [0, 0, 0, 27, 27, 27, 27, 27, 54, 0, 0],     // u
+    [0, 0, 0, 51, 51, 51, 51, 30, 12, 0, 0],     // v
+    [0, 0, 0, 51, 51, 51, 63, 63, 18, 0, 0],     // w
+    [0, 0, 0, 51, 30, 12, 12, 30, 51, 0, 0],     // x
+    [0, 0, 0, 51, 51, 51, 62, 48, 24, 15, 0],    // y
+    [0, 0, 0, 63, 27, 12, 6, 51, 63, 0, 0],      // z
+    [56, 12, 12, 12, 7, 12, 12, 12, 56, 0, 0],   // {
+    [12, 12, 12, 12, 12, 12, 12, 12, 12, 0, 0],  // |
+    [7, 12, 12, 12, 56, 12, 12, 12, 7, 0, 0],    // }
+    [38, 45, 25, 0, 0, 0, 0, 0, 0, 0, 0],        // ~
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Vm;
+    use crate::parser::parse;
+    use emulator::keyboard::Keyboard;
+    use std::path::Path;
+
+    /// Runs `Main.main` with the OS, returning its value.
+    fn run(source: &str) -> (Vm, i16) {
+        let commands = parse(source).unwrap().1;
+        let mut vm = Vm::new(&[("Main", &commands)]).unwrap();
+        vm.boot().unwrap();
+        while !vm.is_halted() {
+            vm.step();
+        }
+        let value = vm.ram[256] as i16;
+        (vm, value)
+    }
+
+    /// Code pushing a string constant, the way the Jack compiler does.
+    fn string(s: &str) -> std::string::String {
+        let mut code = format!("push constant {}\ncall String.new 1\n", s.len());
+        for c in s.chars() {
+            code += &format!("push constant {}\ncall String.appendChar 2\n", c as u16);
+        }
+        code
+    }
+
+    fn main(body: &str) -> std::string::String {
+        format!("function Main.main 0\n{}return\n", body)
+    }
+
+    #[test]
+    fn test_math() {
+        let call = |function: &str, args: &[i16]| {
+            let mut body = std::string::String::new();
+            for &arg in args {
+                body += &format!("push constant {}\n", arg.unsigned_abs());
+                if arg < 0 {
+                    body += "neg\n";
+                }
+            }
+            body += &format!("call Math.{} {}\n", function, args.len());
+            run(&main(&body)).1
+        };
+        assert_eq!(call("multiply", &[-7, 6]), -42);
+        assert_eq!(call("multiply", &[300, 300]), (90000i32 as i16));
+        assert_eq!(call("divide", &[-7, 2]), -3);
+        assert_eq!(call("sqrt", &[30000]), 173);
+        assert_eq!(call("abs", &[-5]), 5);
+        assert_eq!(call("min", &[-5, 3]), -5);
+        assert_eq!(call("max", &[-5, 3]), 3);
+    }
+
+    #[test]
+    fn test_output() {
+        let body = string("Hi") + "call Output.printString 1\npop temp 0\n";
+        let body = body + "push constant 12\nneg\ncall Output.printInt 1\n";
+        let (vm, _) = run(&main(&body));
+        // The top rows of `H`, `i`, `-` and `1`.
+        assert_eq!(vm.ram[SCREEN], 51 | 12 << 8);
+        assert_eq!(vm.ram[SCREEN + 1], 12 << 8);
+        assert_eq!(vm.ram[SCREEN + 5 * 32 + 1] & 0xFF, 63);
+        // The string, at the end of the heap.
+        assert_eq!(
+            vm.ram[HEAP_END - 5..HEAP_END],
+            [5, 2, 2, 'H' as u16, 'i' as u16]
+        );
+    }
+
+    #[test]
+    fn test_memory() {
+        let body = "push constant 3\ncall Memory.alloc 1\npop temp 0\n\
+            push constant 3\ncall Array.new 1\npop temp 1\n\
+            push temp 0\ncall Memory.deAlloc 1\npop temp 2\n\
+            push constant 3\ncall Memory.alloc 1\n\
+            push temp 0\neq\n";
+        let (vm, value) = run(&main(body));
+        // Freed blocks are reused.
+        assert_eq!(value, -1);
+        assert_eq!(vm.ram[5], (HEAP_END - 3) as u16);
+        assert_eq!(vm.ram[6], (HEAP_END - 7) as u16);
+    }
+
+    #[test]
+    fn test_corrupt_heap() {
+        // A free list pointing past the heap.
+        let body = "push constant 2999\npush constant 30000\ncall Memory.poke 2\npop temp 0\n\
+            push constant 3000\ncall Memory.deAlloc 1\npop temp 0\n\
+            push constant 5\ncall Memory.alloc 1\n";
+        let (vm, _) = run(&main(body));
+        assert_eq!(vm.os.error, Some(6));
+        // A double free, making the list loop.
+        let body = "push constant 3\ncall Memory.alloc 1\npop temp 0\n\
+            push temp 0\ncall Memory.deAlloc 1\npop temp 1\n\
+            push temp 0\ncall Memory.deAlloc 1\npop temp 1\n\
+            push constant 3\ncall Memory.alloc 1\npop temp 1\n\
+            push constant 10\ncall Memory.alloc 1\n";
+        let (vm, _) = run(&main(body));
+        assert_eq!(vm.os.error, Some(6));
+    }
+
+    #[test]
+    fn test_program_memory() {
+        // A Memory that allocates upwards from 10000 and counts frees.
+        let memory = "function Memory.init 0\npush constant 10000\npop static 0\n\
+            push constant 0\nreturn\n\
+            function Memory.alloc 0\npush static 0\n\
+            push static 0\npush argument 0\nadd\npop static 0\nreturn\n\
+            function Memory.deAlloc 0\npush static 1\npush constant 1\nadd\npop static 1\n\
+            push constant 0\nreturn\n";
+        let memory = parse(memory).unwrap().1;
+        let body = string("ab")
+            + "pop temp 0\npush constant 3\ncall Array.new 1\npop temp 1\n\
+            push temp 1\ncall Array.dispose 1\npop temp 2\n\
+            push temp 0\ncall String.dispose 1\npop temp 2\n"
+            + &string("? ")
+            + "call Keyboard.readLine 1\n";
+        let source = main(&body);
+        let commands = parse(&source).unwrap().1;
+        let mut vm = Vm::new(&[("Main", &commands), ("Memory", &memory)]).unwrap();
+        vm.boot().unwrap();
+        for key in [b'h', b'i', NEW_LINE as u8] {
+            for pressed in [key as u16, 0] {
+                vm.ram[KBD] = pressed;
+                for _ in 0..100 {
+                    vm.step();
+                }
+            }
+        }
+        assert!(vm.is_halted());
+        assert_eq!(vm.os.error, None);
+        assert_eq!(vm.ram[5..7], [10000, 10004]);
+        assert_eq!(vm.ram[10000..10004], [2, 2, 'a' as u16, 'b' as u16]);
+        // The prompt, then the line.
+        assert_eq!(vm.ram[256], 10011);
+        assert_eq!(vm.ram[10011..10015], [2, 2, 'h' as u16, 'i' as u16]);
+        assert_eq!(vm.ram[16..18], [10015, 2]);
+        // The native heap wasn't used.
+        assert_eq!(vm.ram[HEAP], 0);
+
+        let memory = parse("function Memory.alloc 0\npush constant 0\nreturn\n")
+            .unwrap()
+            .1;
+        let source = main("push constant 3\ncall Array.dispose 1\n");
+        let commands = parse(&source).unwrap().1;
+        let error = Vm::new(&[("Main", &commands), ("Memory", &memory)]).err();
+        assert_eq!(
+            error.unwrap(),
+            "Main:3: `Array.dispose` needs `Memory.deAlloc`, as the program defines Memory"
+        );
+    }
+
+    #[test]
+    fn test_screen() {
+        let body = "push constant 0\npush constant 0\npush constant 16\npush constant 1\n\
+            call Screen.drawRectangle 4\npop temp 0\n\
+            push constant 0\npush constant 10\npush constant 3\npush constant 13\n\
+            call Screen.drawLine 4\npop temp 0\n\
+            push constant 0\ncall Screen.setColor 1\npop temp 0\n\
+            push constant 1\npush constant 0\ncall Screen.drawPixel 2\n";
+        let (vm, _) = run(&main(body));
+        assert_eq!(vm.ram[SCREEN..SCREEN + 2], [0xFFFD, 1]);
+        assert_eq!(vm.ram[SCREEN + 32..SCREEN + 34], [0xFFFF, 1]);
+        let diagonal: Vec<u16> = (10..14).map(|y| vm.ram[SCREEN + y * 32]).collect();
+        assert_eq!(diagonal, [1, 2, 4, 8]);
+    }
+
+    #[test]
+    fn test_keyboard() {
+        let body = string("? ") + "call Keyboard.readInt 1\n";
+        let source = main(&body);
+        let commands = parse(&source).unwrap().1;
+        let mut vm = Vm::new(&[("Main", &commands)]).unwrap();
+        vm.boot().unwrap();
+        for key in [b'-', b'4', b'5', BACKSPACE as u8, b'2', NEW_LINE as u8] {
+            for pressed in [key as u16, 0] {
+                vm.ram[KBD] = pressed;
+                for _ in 0..50 {
+                    vm.step();
+                }
+            }
+        }
+        assert!(vm.is_halted());
+        assert_eq!(vm.ram[256] as i16, -42);
+        // The prompt, then what was typed.
+        assert_eq!(
+            vm.ram[SCREEN + 1] & 0xFF,
+            FONT[(b'-' - 32) as usize][0] as u16
+        );
+        assert_eq!(
+            vm.ram[SCREEN + 1] >> 8,
+            FONT[(b'4' - 32) as usize][0] as u16
+        );
+        assert_eq!(
+            vm.ram[SCREEN + 2] & 0xFF,
+            FONT[(b'2' - 32) as usize][0] as u16
+        );
+    }
+
+    /// The text on the screen, a line per row, with unknown glyphs as `?`.
+    fn screen_text(ram: &[u16]) -> Vec<std::string::String> {
+        let glyph = |row: usize, column: usize| {
+            let rows: Vec<u8> = (0..11)
+                .map(|y| {
+                    let word = ram[SCREEN + (row * 11 + y) * 32 + column / 2];
+                    (word >> (column % 2 * 8)) as u8
+                })
+                .collect();
+            let c = FONT.iter().position(|g| g[..] == rows[..]);
+            c.map_or('?', |c| (c as u8 + 32) as char)
+        };
+        (0..ROWS as usize)
+            .map(|row| {
+                let line: std::string::String =
+                    (0..COLUMNS as usize).map(|c| glyph(row, c)).collect();
+                line.trim_end().to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_average() {
+        // 11/Average, as compiled by the Jack compiler.
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/Average");
+        let mut vm = Vm::load(&path).unwrap();
+        vm.boot().unwrap();
+        let mut keyboard = Keyboard::typing("3\n10\n20\n30\n", 1000, 1000);
+        while !vm.is_halted() && vm.steps < 1_000_000 {
+            if let Some(key) = keyboard.poll(vm.steps) {
+                vm.ram[KBD] = key;
+            }
+            vm.step();
+        }
+        assert!(vm.is_halted());
+        assert_eq!(vm.os.error, None);
+        assert_eq!(
+            screen_text(&vm.ram)[..6],
+            [
+                "How many numbers? 3",
+                "Enter a number: 10",
+                "Enter a number: 20",
+                "Enter a number: 30",
+                "The average is 20",
+                "",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_error() {
+        let body = "push constant 1\npush constant 0\ncall Math.divide 2\n";
+        let (vm, _) = run(&main(body));
+        assert_eq!(vm.os.error, Some(3));
+        assert_eq!(vm.ram[SCREEN] & 0xFF, FONT[(b'E' - 32) as usize][0] as u16);
+
+        let (vm, _) = run(&main(
+            &(string("abc") + "push constant 3\ncall String.charAt 2\n"),
+        ));
+        assert_eq!(vm.os.error, Some(15));
+    }
+
+    #[test]
+    fn test_classes() {
+        let error = |source: &str| {
+            let commands = parse(source).unwrap().1;
+            Vm::new(&[("Main", &commands)]).err().unwrap()
+        };
+        assert_eq!(
+            error("call Math.multiply 1"),
+            "Main:1: `Math.multiply` takes 2 arguments, not 1"
+        );
+        // A class of the program replaces the OS's.
+        assert_eq!(
+            error("call Math.multiply 2\nfunction Math.abs 0"),
+            "Main:1: unknown function `Math.multiply`"
+        );
+    }
+}
@@ -45,7 +45,7 @@ pub enum Segment {
 
 type Result<'s, T> = IResult<&'s str, T, nom::error::Error<&'s str>>;
 
-fn comment(input: &str) -> Result<()> {
+fn comment(input: &str) -> Result<'_, ()> {
     map(preceded(tag("//"), not_line_ending), |_| ())(input)
 }
 
@@ -75,7 +75,7 @@ fn segment(input: &str) -> IResult<&str, Segment> {
 }
 
 // https://en.wikipedia.org/wiki/Arity#Nullary
-fn nullary_cmd(input: &str) -> IResult<&str, Command> {
+fn nullary_cmd(input: &str) -> IResult<&str, Command<'_>> {
     alt((
         map(tag("add"), |_| Command::Add),
         map(tag("sub"), |_| Command::Sub),
@@ -90,7 +90,7 @@ fn nullary_cmd(input: &str) -> IResult<&str, Command> {
     ))(input)
 }
 
-fn unary_cmd(input: &str) -> IResult<&str, Command> {
+fn unary_cmd(input: &str) -> IResult<&str, Command<'_>> {
     map(
         pair(
             alt((tag("label"), tag("goto"), tag("if-goto"))),
@@ -105,11 +105,11 @@ fn unary_cmd(input: &str) -> IResult<&str, Command> {
     )(input)
 }
 
-fn binary_cmd(input: &str) -> IResult<&str, Command> {
+fn binary_cmd(input: &str) -> IResult<&str, Command<'_>> {
     alt((pop_or_push, call_or_function))(input)
 }
 
-fn pop_or_push(input: &str) -> IResult<&str, Command> {
+fn pop_or_push(input: &str) -> IResult<&str, Command<'_>> {
     map(
         tuple((
             alt((tag("pop"), tag("push"))),
@@ -124,7 +124,7 @@ fn pop_or_push(input: &str) -> IResult<&str, Command> {
     )(input)
 }
 
-fn call_or_function(input: &str) -> IResult<&str, Command> {
+fn call_or_function(input: &str) -> IResult<&str, Command<'_>> {
     map(
         tuple((
             alt((tag("call"), tag("function"))),
@@ -139,11 +139,11 @@ fn call_or_function(input: &str) -> IResult<&str, Command> {
     )(input)
 }
 
-fn command(input: &str) -> IResult<&str, Command> {
+fn command(input: &str) -> IResult<&str, Command<'_>> {
     alt((nullary_cmd, unary_cmd, binary_cmd))(input)
 }
 
-fn line(input: &str) -> IResult<&str, Option<Command>> {
+fn line(input: &str) -> IResult<&str, Option<Command<'_>>> {
     preceded(
         space0,
         alt((
@@ -153,7 +153,7 @@ fn line(input: &str) -> IResult<&str, Option<Command>> {
     )(input)
 }
 
-pub fn parse(input: &str) -> Result<Vec<Option<Command>>> {
+pub fn parse(input: &str) -> Result<'_, Vec<Option<Command<'_>>>> {
     separated_list0(line_ending, line)(input)
 }
 
@@ -0,0 +1,159 @@
+use crate::interpreter::{Vm, ARG, LCL, SP, THAT, THIS};
+use emulator::cpu::MEMORY_SIZE;
+use std::path::Path;
+use test_script::output::Value;
+use test_script::runner::Simulator;
+
+/// The VM emulator dialect of test scripts: loads a `.vm` file or all of a
+/// directory's, starting at `Sys.init` if there is one, and knows `sp`,
+/// `local`, `argument`, `this`, `that`, `local[n]` and the like, `temp[n]`,
+/// `RAM[n]` and `vmstep`.
+#[derive(Default)]
+pub struct VmEmulator {
+    pub vm: Option<Vm>,
+}
+
+impl VmEmulator {
+    fn vm(&self) -> Result<&Vm, String> {
+        self.vm
+            .as_ref()
+            .ok_or_else(|| "no program loaded".to_string())
+    }
+
+    fn vm_mut(&mut self) -> Result<&mut Vm, String> {
+        self.vm
+            .as_mut()
+            .ok_or_else(|| "no program loaded".to_string())
+    }
+
+    /// The RAM address of a variable.
+    fn address(&self, name: &str) -> Result<usize, String> {
+        let unknown = || format!("unknown variable `{}`", name);
+        let Some((segment, rest)) = name.split_once('[') else {
+            return register(name).ok_or_else(unknown);
+        };
+        let index = rest
+            .strip_suffix(']')
+            .and_then(|n| n.parse::<usize>().ok())
+            .ok_or_else(unknown)?;
+        match segment {
+            "RAM" if index < MEMORY_SIZE => Ok(index),
+            "temp" if index < 8 => Ok(5 + index),
+            _ => {
+                let register = register(segment).filter(|&r| r != SP).ok_or_else(unknown)?;
+                Ok((self.vm()?.ram[register] as usize + index) % MEMORY_SIZE)
+            }
+        }
+    }
+}
+
+/// The register holding the stack pointer or the base of a segment.
+fn register(name: &str) -> Option<usize> {
+    match name {
+        "sp" => Some(SP),
+        "local" => Some(LCL),
+        "argument" => Some(ARG),
+        "this" => Some(THIS),
+        "that" => Some(THAT),
+        _ => None,
+    }
+}
+
+impl Simulator for VmEmulator {
+    fn load(&mut self, dir: &Path, file: Option<&str>) -> Result<(), String> {
+        let path = match file {
+            Some(file) => dir.join(file),
+            None => dir.to_path_buf(),
+        };
+        let mut vm = Vm::load(&path)?;
+        // Like the official VM emulator, start in Sys.init rather than call
+        // it, leaving the scripts to set up the stack, and else at the first
+        // command, even with the built-in OS.
+        vm.pc = vm.entry("Sys.init").unwrap_or(0);
+        self.vm = Some(vm);
+        Ok(())
+    }
+
+    fn get(&self, name: &str) -> Result<Value, String> {
+        let address = self.address(name)?;
+        Ok(Value::Number(self.vm()?.ram[address] as i16 as i64))
+    }
+
+    fn set(&mut self, name: &str, value: i64) -> Result<(), String> {
+        let address = self.address(name)?;
+        self.vm_mut()?.ram[address] = value as u16;
+        Ok(())
+    }
+
+    fn command(&mut self, words: &[String]) -> Result<(), String> {
+        match words {
+            [command] if command == "vmstep" => {
+                self.vm_mut()?.step();
+                Ok(())
+            }
+            _ => Err(format!("unknown command `{}`", words.join(" "))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use test_script::runner::Runner;
+
+    /// Runs the `<Test>VME.tst` script of a project against `<Test>.cmp`.
+    fn run_vme_test(project: &str, test: &str) {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("..")
+            .join(project);
+        let script = fs::read_to_string(dir.join(format!("{}VME.tst", test))).unwrap();
+        let mut runner = Runner::new(VmEmulator::default(), &dir);
+        if let Err(e) = runner.run(&script) {
+            panic!("{}: {}", test, e);
+        }
+    }
+
+    #[test]
+    fn test_stack_arithmetic() {
+        run_vme_test("07/StackArithmetic/SimpleAdd", "SimpleAdd");
+        run_vme_test("07/StackArithmetic/StackTest", "StackTest");
+    }
+
+    #[test]
+    fn test_memory_access() {
+        run_vme_test("07/MemoryAccess/BasicTest", "BasicTest");
+        run_vme_test("07/MemoryAccess/PointerTest", "PointerTest");
+        run_vme_test("07/MemoryAccess/StaticTest", "StaticTest");
+    }
+
+    #[test]
+    fn test_program_flow() {
+        run_vme_test("08/ProgramFlow/BasicLoop", "BasicLoop");
+        run_vme_test("08/ProgramFlow/FibonacciSeries", "FibonacciSeries");
+    }
+
+    #[test]
+    fn test_function_calls() {
+        run_vme_test("08/FunctionCalls/SimpleFunction", "SimpleFunction");
+        run_vme_test("08/FunctionCalls/NestedCall", "NestedCall");
+        run_vme_test("08/FunctionCalls/FibonacciElement", "FibonacciElement");
+        run_vme_test("08/FunctionCalls/StaticsTest", "StaticsTest");
+    }
+
+    #[test]
+    fn test_variables() {
+        let mut emulator = VmEmulator::default();
+        assert_eq!(emulator.get("sp").unwrap_err(), "no program loaded");
+        emulator.vm = Some(Vm::new(&[]).unwrap());
+        emulator.set("local", 300).unwrap();
+        emulator.set("local[2]", -7).unwrap();
+        emulator.set("temp[7]", 5).unwrap();
+        assert_eq!(emulator.get("RAM[302]").unwrap(), Value::Number(-7));
+        assert_eq!(emulator.get("RAM[12]").unwrap(), Value::Number(5));
+        assert!(emulator.get("temp[8]").is_err());
+        assert!(emulator.get("sp[0]").is_err());
+        assert!(emulator.get("RAM[32768]").is_err());
+        assert!(emulator.get("pointer").is_err());
+    }
+}